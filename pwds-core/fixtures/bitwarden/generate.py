@@ -0,0 +1,124 @@
+#!/usr/bin/env python3
+"""Writes the Bitwarden JSON exports read by the bitwarden module of pwds-core.
+
+The writer is independent of the Rust reader on purpose, and only needs the
+`cryptography` package. Run it from this directory. Every export holds the
+same items, and those protected by a password open with `pwds`.
+"""
+
+import base64
+import hashlib
+import hmac
+import json
+import random
+
+from cryptography.hazmat.primitives import padding
+from cryptography.hazmat.primitives.ciphers import Cipher, algorithms, modes
+from cryptography.hazmat.primitives.kdf.argon2 import Argon2id
+from cryptography.hazmat.primitives.kdf.pbkdf2 import PBKDF2HMAC
+from cryptography.hazmat.primitives import hashes
+
+PASSWORD = b"pwds"
+
+FOLDERS = [
+    {"id": "5f0c7a5e-0001-4000-8000-000000000001", "name": "Internet/Email"},
+    {"id": "5f0c7a5e-0001-4000-8000-000000000002", "name": "Finance"},
+]
+
+
+def item(item_type, name, folder=None, notes=None, fields=(), **content):
+    return {
+        "id": f"7c6a1d2e-{item_type:04d}-4000-8000-{len(name):012d}",
+        "organizationId": None,
+        "folderId": folder,
+        "type": item_type,
+        "reprompt": 0,
+        "name": name,
+        "notes": notes,
+        "favorite": False,
+        "fields": [{"name": n, "value": v, "type": t, "linkedId": l} for n, v, t, l in fields],
+        "collectionIds": None,
+        **content,
+    }
+
+
+ITEMS = [
+    item(1, "Mail", FOLDERS[0]["id"], login={
+        "uris": [{"match": None, "uri": "https://mail.example.com"},
+                 {"match": None, "uri": "https://webmail.example.com"}],
+        "username": "jdoe@example.com",
+        "password": "Tr0ub4dor&3",
+        "totp": "otpauth://totp/Mail:jdoe?secret=JBSWY3DPEHPK3PXP&issuer=Mail",
+    }),
+    item(1, "Forum", notes="Line one\nLine two", fields=[
+        ("PIN", "1234", 1, None), ("Remember me", "true", 2, None), ("Login", None, 3, 100),
+    ], login={
+        "uris": [{"match": None, "uri": " https://forum.example/ "}],
+        "username": "jdoe",
+        "password": "<&>\"' ünïcödé",
+        "totp": None,
+    }),
+    item(2, "Alarm code", notes="4321, then # twice", secureNote={"type": 0}),
+    item(3, "Visa", FOLDERS[1]["id"], card={
+        "cardholderName": "John Doe", "brand": "Visa", "number": "4111111111111111",
+        "expMonth": "5", "expYear": "2030", "code": "123",
+    }),
+    item(4, "Passport: John", identity={
+        "title": "Mr", "firstName": "John", "middleName": None, "lastName": "Doe",
+        "address1": "1 Main Street", "address2": None, "address3": None, "city": "Springfield",
+        "state": None, "postalCode": "12345", "country": "US", "company": None,
+        "email": "jdoe@example.com", "phone": None, "ssn": None, "username": None,
+        "passportNumber": "X1234567", "licenseNumber": None,
+    }),
+]
+
+
+def export():
+    return {"encrypted": False, "folders": FOLDERS, "items": ITEMS}
+
+
+def encrypt(data, enc_key, mac_key, rng):
+    iv = bytes(rng.getrandbits(8) for _ in range(16))
+    padder = padding.PKCS7(128).padder()
+    data = padder.update(data) + padder.finalize()
+    encryptor = Cipher(algorithms.AES(enc_key), modes.CBC(iv)).encryptor()
+    data = encryptor.update(data) + encryptor.finalize()
+    mac = hmac.new(mac_key, iv + data, hashlib.sha256).digest()
+    return "2." + "|".join(base64.b64encode(part).decode() for part in (iv, data, mac))
+
+
+def hkdf_expand(key, info):
+    return hmac.new(key, info + b"\x01", hashlib.sha256).digest()
+
+
+def write_protected(name, kdf):
+    rng = random.Random(name)
+    salt = base64.b64encode(bytes(rng.getrandbits(8) for _ in range(16))).decode()
+
+    if kdf == "pbkdf2":
+        settings = {"kdfType": 0, "kdfIterations": 5000, "kdfMemory": None, "kdfParallelism": None}
+        key = PBKDF2HMAC(hashes.SHA256(), 32, salt.encode(), 5000).derive(PASSWORD)
+    else:
+        settings = {"kdfType": 1, "kdfIterations": 3, "kdfMemory": 16, "kdfParallelism": 2}
+        key = Argon2id(salt=hashlib.sha256(salt.encode()).digest(), length=32, iterations=3,
+                       lanes=2, memory_cost=16 * 1024).derive(PASSWORD)
+
+    enc_key, mac_key = hkdf_expand(key, b"enc"), hkdf_expand(key, b"mac")
+    content = {
+        "encrypted": True,
+        "passwordProtected": True,
+        "salt": salt,
+        **settings,
+        "encKeyValidation_DO_NOT_EDIT": encrypt(b"8f3e2c1a-validation", enc_key, mac_key, rng),
+        "data": encrypt(json.dumps(export()).encode(), enc_key, mac_key, rng),
+    }
+
+    with open(name, "w") as file:
+        json.dump(content, file, indent=2)
+
+
+with open("plain.json", "w") as file:
+    json.dump(export(), file, indent=2, ensure_ascii=False)
+
+write_protected("pbkdf2.json", "pbkdf2")
+write_protected("argon2id.json", "argon2id")
@@ -0,0 +1,240 @@
+#!/usr/bin/env python3
+"""Writes the KDBX 4 databases read by the kdbx module of pwds-core.
+
+The writer is independent of the Rust reader on purpose, and only needs the
+`cryptography` package. Run it from this directory. Every database holds the
+same entries and opens with the master password `pwds`.
+"""
+
+import base64
+import gzip
+import hashlib
+import hmac
+import random
+import struct
+from xml.sax.saxutils import escape
+
+from cryptography.hazmat.primitives import padding
+from cryptography.hazmat.primitives.ciphers import Cipher, algorithms, modes
+from cryptography.hazmat.primitives.kdf.argon2 import Argon2d, Argon2id
+
+PASSWORD = b"pwds"
+
+SIGNATURE = bytes.fromhex("03d9a29a67fb4bb5")
+CIPHER_AES256 = bytes.fromhex("31c1f2e6bf714350be5805216afc5aff")
+CIPHER_CHACHA20 = bytes.fromhex("d6038a2b8b6f4cb5a524339a31dbb59a")
+KDF_AES = bytes.fromhex("c9d9f39a628a4460bf740d08c18a4fea")
+KDF_ARGON2D = bytes.fromhex("ef636ddf8c29444b91f7a9a403e30a0c")
+KDF_ARGON2ID = bytes.fromhex("9e298b1956db4773b23dfc3ec6f0a1e6")
+STREAM_SALSA20 = 2
+STREAM_CHACHA20 = 3
+
+# Seconds from 0001-01-01 to 1970-01-01
+UNIX_EPOCH_SECONDS = 62135596800
+
+
+def time(unix):
+    return base64.b64encode(struct.pack("<q", unix + UNIX_EPOCH_SECONDS)).decode()
+
+
+# (group path, title, username, password, url, tags, notes, custom strings, history)
+# History versions are (unix time, password), oldest first.
+ENTRIES = [
+    ([], "Bank", "jdoe", "correct horse battery staple", "https://bank.example",
+     "finance", "Branch 12", [("PIN", "1234", True), ("Account", "FR76 3000", False)], []),
+    (["Internet", "Email"], "Mail", "jdoe@example.com", "Tr0ub4dor&3", "https://mail.example.com",
+     "", "", [], [(1577880000, "hunter2"), (1609502400, "letmein")]),
+    (["Internet"], "Forum", "jdoe", "<&>\"' ünïcödé", " https://forum.example/ ",
+     "social;fun", "Line one\nLine two", [], []),
+    (["Internet"], "Wi-Fi", "", "wifi-passphrase", "", "", "Guest network", [], []),
+    (["Recycle Bin"], "Deleted", "gone", "gone", "https://gone.example", "", "", [], []),
+]
+
+
+class Salsa20:
+    """Salsa20/20 keystream, which the cryptography package lacks."""
+
+    def __init__(self, key, nonce):
+        constants = struct.unpack("<4I", b"expand 32-byte k")
+        key = struct.unpack("<8I", key)
+        nonce = struct.unpack("<2I", nonce)
+        self.state = [constants[0], *key[:4], constants[1], *nonce, 0, 0,
+                      constants[2], *key[4:], constants[3]]
+        self.buffer = b""
+
+    def block(self):
+        x = list(self.state)
+
+        def rotl(v, c):
+            return ((v << c) | (v >> (32 - c))) & 0xFFFFFFFF
+
+        def quarter(a, b, c, d):
+            x[b] ^= rotl((x[a] + x[d]) & 0xFFFFFFFF, 7)
+            x[c] ^= rotl((x[b] + x[a]) & 0xFFFFFFFF, 9)
+            x[d] ^= rotl((x[c] + x[b]) & 0xFFFFFFFF, 13)
+            x[a] ^= rotl((x[d] + x[c]) & 0xFFFFFFFF, 18)
+
+        for _ in range(10):
+            quarter(0, 4, 8, 12), quarter(5, 9, 13, 1)
+            quarter(10, 14, 2, 6), quarter(15, 3, 7, 11)
+            quarter(0, 1, 2, 3), quarter(5, 6, 7, 4)
+            quarter(10, 11, 8, 9), quarter(15, 12, 13, 14)
+
+        out = struct.pack("<16I", *((a + b) & 0xFFFFFFFF for a, b in zip(x, self.state)))
+        counter = (self.state[8] | self.state[9] << 32) + 1
+        self.state[8], self.state[9] = counter & 0xFFFFFFFF, counter >> 32
+        return out
+
+    def update(self, data):
+        while len(self.buffer) < len(data):
+            self.buffer += self.block()
+        stream, self.buffer = self.buffer[:len(data)], self.buffer[len(data):]
+        return bytes(a ^ b for a, b in zip(data, stream))
+
+
+def inner_stream(stream_id, key):
+    if stream_id == STREAM_SALSA20:
+        return Salsa20(hashlib.sha256(key).digest(), bytes.fromhex("e830094b97205d2a"))
+    digest = hashlib.sha512(key).digest()
+    nonce = b"\0" * 4 + digest[32:44]
+    return Cipher(algorithms.ChaCha20(digest[:32], nonce), None).encryptor()
+
+
+def xml_document(stream, uuid):
+    def value(text, protected=False):
+        if protected:
+            text = base64.b64encode(stream.update(text.encode())).decode()
+            return f'<Value Protected="True">{text}</Value>'
+        return f"<Value>{escape(text)}</Value>"
+
+    def entry(title, username, password, url, tags, notes, custom, modified, history):
+        strings = [("Title", title, False), ("UserName", username, False),
+                   ("Password", password, True), ("URL", url, False),
+                   ("Notes", notes, False), *custom]
+        xml = [f"<Entry><UUID>{uuid()}</UUID><Tags>{escape(tags)}</Tags>",
+               f"<Times><LastModificationTime>{time(modified)}</LastModificationTime></Times>"]
+        xml += [f"<String><Key>{escape(k)}</Key>{value(v, p)}</String>" for k, v, p in strings]
+        if history:
+            xml.append("<History>")
+            for when, old in history:
+                xml.append(entry(title, username, old, url, tags, notes, custom, when, []))
+            xml.append("</History>")
+        xml.append("</Entry>")
+        return "".join(xml)
+
+    recycle_bin = uuid()
+
+    def group(name, path, group_uuid=None):
+        xml = [f"<Group><UUID>{group_uuid or uuid()}</UUID><Name>{escape(name)}</Name>"]
+        children = []
+        for item in ENTRIES:
+            item_path = item[0]
+            if item_path == path:
+                xml.append(entry(*item[1:8], 1640995200, item[8]))
+            elif item_path[:len(path)] == path and item_path[len(path)] not in children:
+                children.append(item_path[len(path)])
+        for child in children:
+            child_uuid = recycle_bin if child == "Recycle Bin" else None
+            xml.append(group(child, path + [child], child_uuid))
+        xml.append("</Group>")
+        return "".join(xml)
+
+    root = group("Passwords", [])
+    return ('<?xml version="1.0" encoding="utf-8" standalone="yes"?>\n'
+            "<KeePassFile><Meta><Generator>pwds fixtures</Generator>"
+            "<DatabaseName>Fixtures</DatabaseName><RecycleBinEnabled>True</RecycleBinEnabled>"
+            f"<RecycleBinUUID>{recycle_bin}</RecycleBinUUID></Meta>"
+            f"<Root>{root}<DeletedObjects/></Root></KeePassFile>").encode()
+
+
+def variant_dictionary(items):
+    data = struct.pack("<H", 0x0100)
+    for kind, name, value in items:
+        data += struct.pack("<BI", kind, len(name)) + name.encode()
+        data += struct.pack("<I", len(value)) + value
+    return data + b"\0"
+
+
+def header_field(kind, data):
+    return struct.pack("<BI", kind, len(data)) + data
+
+
+def block_key(hmac_key, index):
+    return hashlib.sha512(struct.pack("<Q", index) + hmac_key).digest()
+
+
+def write(name, kdf, cipher, stream_id, compressed):
+    rng = random.Random(name)
+
+    def random_bytes(n):
+        return bytes(rng.getrandbits(8) for _ in range(n))
+
+    def uuid():
+        return base64.b64encode(random_bytes(16)).decode()
+
+    composite = hashlib.sha256(hashlib.sha256(PASSWORD).digest()).digest()
+    salt = random_bytes(32)
+
+    if kdf == "aes":
+        rounds = 1000
+        parameters = [(0x42, "$UUID", KDF_AES), (0x05, "R", struct.pack("<Q", rounds)),
+                      (0x42, "S", salt)]
+        encryptor = Cipher(algorithms.AES(salt), modes.ECB()).encryptor()
+        transformed = composite
+        for _ in range(rounds):
+            transformed = encryptor.update(transformed)
+        transformed = hashlib.sha256(transformed).digest()
+    else:
+        argon2, uuid_bytes = (Argon2d, KDF_ARGON2D) if kdf == "argon2d" else (Argon2id, KDF_ARGON2ID)
+        memory, iterations, lanes = 1024 * 1024, 2, 2
+        parameters = [(0x42, "$UUID", uuid_bytes), (0x42, "S", salt),
+                      (0x04, "P", struct.pack("<I", lanes)), (0x05, "M", struct.pack("<Q", memory)),
+                      (0x05, "I", struct.pack("<Q", iterations)), (0x04, "V", struct.pack("<I", 0x13))]
+        transformed = argon2(salt=salt, length=32, iterations=iterations, lanes=lanes,
+                             memory_cost=memory // 1024).derive(composite)
+
+    master_seed = random_bytes(32)
+    iv = random_bytes(16 if cipher == "aes" else 12)
+
+    header = SIGNATURE + struct.pack("<HH", 1, 4)
+    header += header_field(2, CIPHER_AES256 if cipher == "aes" else CIPHER_CHACHA20)
+    header += header_field(3, struct.pack("<I", 1 if compressed else 0))
+    header += header_field(4, master_seed)
+    header += header_field(7, iv)
+    header += header_field(11, variant_dictionary(parameters))
+    header += header_field(0, b"\r\n\r\n")
+
+    cipher_key = hashlib.sha256(master_seed + transformed).digest()
+    hmac_key = hashlib.sha512(master_seed + transformed + b"\x01").digest()
+
+    stream_key = random_bytes(64 if stream_id == STREAM_CHACHA20 else 32)
+    content = header_field(1, struct.pack("<I", stream_id)) + header_field(2, stream_key)
+    content += header_field(0, b"")
+    content += xml_document(inner_stream(stream_id, stream_key), uuid)
+    if compressed:
+        content = gzip.compress(content, mtime=0)
+
+    if cipher == "aes":
+        padder = padding.PKCS7(128).padder()
+        content = padder.update(content) + padder.finalize()
+        encryptor = Cipher(algorithms.AES(cipher_key), modes.CBC(iv)).encryptor()
+    else:
+        encryptor = Cipher(algorithms.ChaCha20(cipher_key, b"\0" * 4 + iv), None).encryptor()
+    content = encryptor.update(content) + encryptor.finalize()
+
+    data = header + hashlib.sha256(header).digest()
+    data += hmac.new(block_key(hmac_key, 2**64 - 1), header, hashlib.sha256).digest()
+
+    blocks = [content[i:i + 1024 * 1024] for i in range(0, len(content), 1024 * 1024)] + [b""]
+    for index, block in enumerate(blocks):
+        signed = struct.pack("<QI", index, len(block)) + block
+        data += hmac.new(block_key(hmac_key, index), signed, hashlib.sha256).digest()
+        data += struct.pack("<I", len(block)) + block
+
+    with open(name, "wb") as file:
+        file.write(data)
+
+
+write("argon2d-chacha20.kdbx", "argon2d", "chacha20", STREAM_CHACHA20, True)
+write("argon2id-aes.kdbx", "argon2id", "aes", STREAM_SALSA20, True)
+write("aes-kdf-aes.kdbx", "aes", "aes", STREAM_CHACHA20, False)
@@ -0,0 +1,32 @@
+#!/bin/sh
+# Writes the password store read by the pass module of pwds-core.
+#
+# The files are encrypted for the throwaway key in key.asc, which has no
+# passphrase, except Wi-Fi.gpg which is encrypted for a key that is thrown
+# away so that it cannot be decrypted. Hidden directories are not part of
+# the store. Run it from this directory.
+
+set -eu
+
+GNUPGHOME=$(mktemp -d)
+export GNUPGHOME
+trap 'gpgconf --kill gpg-agent; rm -rf "$GNUPGHOME"' EXIT
+
+gpg --batch --passphrase '' --quick-gen-key 'pwds fixtures <fixtures@pwds.invalid>' future-default default never
+gpg --batch --passphrase '' --quick-gen-key 'Lost key <lost@pwds.invalid>' future-default default never
+gpg --armor --export-secret-keys fixtures@pwds.invalid > key.asc
+
+rm -rf store
+mkdir -p store/Internet/Email store/Bank store/.extensions
+
+write() {
+    printf '%b' "$2" | gpg --batch --yes --trust-model always --encrypt --recipient "$1" --output "store/$3"
+}
+
+gpg --with-colons --list-keys fixtures@pwds.invalid | awk -F: '/^fpr/ { print $10; exit }' > store/.gpg-id
+
+write fixtures@pwds.invalid 'Tr0ub4dor&3\nlogin: jdoe@example.com\nurl: https://mail.example.com\notpauth://totp/Mail:jdoe?secret=JBSWY3DPEHPK3PXP&issuer=Mail\n' Internet/Email/Mail.gpg
+write fixtures@pwds.invalid '<&>"'"'"' ünïcödé\nUsername: jdoe\nURL: https://forum.example/\nLine one\nSecurity question: Rex\n' Internet/Forum.gpg
+write fixtures@pwds.invalid 'correct horse battery staple\n' Bank/jdoe.gpg
+write lost@pwds.invalid 'wifi-passphrase\n' Wi-Fi.gpg
+write lost@pwds.invalid 'hidden\n' .extensions/hidden.gpg
@@ -0,0 +1,477 @@
+//! Reading of Bitwarden JSON exports, plain or protected by a password.
+//!
+//! ```
+//! use pwds_core::bitwarden;
+//! use pwds_core::import::{Import, OnDuplicate};
+//!
+//! /* Every export holds the same items, the plain one needs no password */
+//! for (fixture, protected) in [("plain", false), ("pbkdf2", true), ("argon2id", true)] {
+//!     let path = format!("fixtures/bitwarden/{fixture}.json");
+//!     let mut asked = false;
+//!     let export = bitwarden::open(&path, || {
+//!         asked = true;
+//!         Ok("pwds".to_string())
+//!     })?;
+//!     assert_eq!(asked, protected, "{fixture}");
+//!
+//!     let entries = export.entries();
+//!     assert_eq!(entries.len(), 5, "{fixture}");
+//!
+//!     let mail = entries.iter().find(|entry| entry.title == "Mail").unwrap();
+//!     assert_eq!(mail.password, "Tr0ub4dor&3", "{fixture}");
+//!     assert_eq!(mail.tags, ["Internet/Email"]);
+//!     assert!(mail.otp.starts_with("otpauth://totp/Mail:jdoe?"));
+//!
+//!     /* Items without a username are imported under their name */
+//!     let import = Import::plan(&[], entries, OnDuplicate::Skip);
+//!     let card = import.added.iter().find(|entry| entry.title == "Visa").unwrap();
+//!     assert_eq!(card.username, "Visa");
+//!     assert_eq!(card.password, "4111111111111111", "{fixture}");
+//!     assert_eq!(card.tags, ["Finance", "Card"]);
+//!
+//!     let passport = import.added.iter().find(|entry| entry.title == "Passport: John").unwrap();
+//!     assert_eq!(passport.username, "Passport John");
+//!
+//!     if protected {
+//!         let error = bitwarden::open(&path, || Ok("wrong".to_string())).unwrap_err();
+//!         assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied, "{fixture}");
+//!     }
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::{BlockDecryptMut, KeyIvInit};
+use aes::Aes256;
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use pbkdf2::pbkdf2_hmac_array;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::import::append_section;
+use crate::otp;
+use crate::pwds::Entry;
+
+const KDF_PBKDF2: u32 = 0;
+const KDF_ARGON2ID: u32 = 1;
+
+/* Item types */
+const LOGIN: u32 = 1;
+const SECURE_NOTE: u32 = 2;
+const CARD: u32 = 3;
+const IDENTITY: u32 = 4;
+const SSH_KEY: u32 = 5;
+
+/// The content of an export.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Export {
+    folders: Vec<Folder>,
+    collections: Vec<Folder>,
+    items: Vec<Item>,
+}
+
+/// The file as written by Bitwarden, the export being in `data` when it is
+/// protected by a password.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportFile {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    password_protected: bool,
+    salt: Option<String>,
+    kdf_type: Option<u32>,
+    kdf_iterations: Option<u32>,
+    kdf_memory: Option<u32>,
+    kdf_parallelism: Option<u32>,
+    #[serde(rename = "encKeyValidation_DO_NOT_EDIT")]
+    key_validation: Option<String>,
+    data: Option<String>,
+    #[serde(flatten)]
+    export: Export,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Folder {
+    id: String,
+    name: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Item {
+    #[serde(rename = "type")]
+    kind: u32,
+    name: String,
+    notes: Option<String>,
+    folder_id: Option<String>,
+    collection_ids: Option<Vec<String>>,
+    fields: Option<Vec<CustomField>>,
+    login: Option<Login>,
+    card: Option<Card>,
+    identity: Option<Identity>,
+    ssh_key: Option<SshKey>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+struct CustomField {
+    name: Option<String>,
+    /// Missing for fields linked to another one, which are left out.
+    value: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+struct Login {
+    username: Option<String>,
+    password: Option<String>,
+    totp: Option<String>,
+    uris: Option<Vec<Uri>>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+struct Uri {
+    uri: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Card {
+    cardholder_name: Option<String>,
+    brand: Option<String>,
+    number: Option<String>,
+    exp_month: Option<String>,
+    exp_year: Option<String>,
+    code: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Identity {
+    title: Option<String>,
+    first_name: Option<String>,
+    middle_name: Option<String>,
+    last_name: Option<String>,
+    company: Option<String>,
+    email: Option<String>,
+    phone: Option<String>,
+    username: Option<String>,
+    address1: Option<String>,
+    address2: Option<String>,
+    address3: Option<String>,
+    city: Option<String>,
+    state: Option<String>,
+    postal_code: Option<String>,
+    country: Option<String>,
+    ssn: Option<String>,
+    passport_number: Option<String>,
+    license_number: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct SshKey {
+    private_key: Option<String>,
+    public_key: Option<String>,
+    key_fingerprint: Option<String>,
+}
+
+impl Export {
+    /// Converts every item. Folders and collections become tags.
+    ///
+    /// Logins keep their fields, the other items have no username nor
+    /// password: their secret becomes the password, which is the number of
+    /// a card, the text of a secure note, the details of an identity and the
+    /// private key of an SSH key, and they are tagged with their type. TOTP seeds give the one-time
+    /// passwords; other URLs and custom fields are appended to the notes.
+    pub fn entries(&self) -> Vec<Entry> {
+        let groups: HashMap<&str, &str> = self
+            .folders
+            .iter()
+            .chain(&self.collections)
+            .map(|folder| (folder.id.as_str(), folder.name.as_str()))
+            .collect();
+
+        self.items
+            .iter()
+            .map(|item| {
+                let tags = item
+                    .folder_id
+                    .iter()
+                    .chain(item.collection_ids.iter().flatten())
+                    .filter_map(|id| groups.get(id.as_str()))
+                    .map(|name| name.to_string())
+                    .collect();
+
+                item.to_entry(tags)
+            })
+            .collect()
+    }
+}
+
+impl Item {
+    fn to_entry(&self, mut tags: Vec<String>) -> Entry {
+        let mut entry = Entry {
+            title: self.name.clone(),
+            notes: text(&self.notes).to_string(),
+            ..Default::default()
+        };
+        let mut details: Vec<(&str, String)> = Vec::new();
+
+        match self.kind {
+            LOGIN => {
+                let login = self.login.clone().unwrap_or_default();
+                entry.username = text(&login.username).to_string();
+                entry.password = text(&login.password).to_string();
+
+                let mut uris = login.uris.iter().flatten();
+                entry.url = uris
+                    .next()
+                    .map_or("", |uri| text(&uri.uri))
+                    .trim()
+                    .to_string();
+                details.extend(uris.map(|uri| ("URL", text(&uri.uri).trim().to_string())));
+                match otp::import_uri(text(&login.totp)) {
+                    Some(uri) => entry.otp = uri,
+                    None => details.push(("TOTP", text(&login.totp).to_string())),
+                }
+            }
+            SECURE_NOTE => {
+                entry.password = std::mem::take(&mut entry.notes);
+                tags.push("Secure note".to_string());
+            }
+            CARD => {
+                let card = self.card.clone().unwrap_or_default();
+                entry.password = text(&card.number).to_string();
+
+                let expires = [text(&card.exp_month), text(&card.exp_year)].join("/");
+                details.push(("Cardholder", text(&card.cardholder_name).to_string()));
+                details.push(("Brand", text(&card.brand).to_string()));
+                details.push(("Expires", expires.trim_matches('/').to_string()));
+                details.push(("Security code", text(&card.code).to_string()));
+                tags.push("Card".to_string());
+            }
+            IDENTITY => {
+                let identity = self.identity.clone().unwrap_or_default();
+                entry.password = lines(&identity.details());
+                tags.push("Identity".to_string());
+            }
+            SSH_KEY => {
+                let key = self.ssh_key.clone().unwrap_or_default();
+                entry.password = text(&key.private_key).to_string();
+                details.push(("Public key", text(&key.public_key).to_string()));
+                details.push(("Fingerprint", text(&key.key_fingerprint).to_string()));
+                tags.push("SSH key".to_string());
+            }
+            _ => {}
+        }
+
+        for field in self.fields.iter().flatten() {
+            let name = text(&field.name);
+            details.push((name, text(&field.value).to_string()));
+        }
+        append_section(&mut entry.notes, &lines(&details));
+
+        entry.tags = tags;
+        entry
+    }
+}
+
+impl Identity {
+    fn details(&self) -> Vec<(&str, String)> {
+        let name: Vec<&str> = [
+            &self.title,
+            &self.first_name,
+            &self.middle_name,
+            &self.last_name,
+        ]
+        .into_iter()
+        .map(text)
+        .filter(|part| !part.is_empty())
+        .collect();
+
+        let mut details = vec![("Name", name.join(" "))];
+        details.extend(
+            [
+                ("Company", &self.company),
+                ("Email", &self.email),
+                ("Phone", &self.phone),
+                ("Username", &self.username),
+                ("Address", &self.address1),
+                ("Address", &self.address2),
+                ("Address", &self.address3),
+                ("City", &self.city),
+                ("State", &self.state),
+                ("Postal code", &self.postal_code),
+                ("Country", &self.country),
+                ("SSN", &self.ssn),
+                ("Passport number", &self.passport_number),
+                ("License number", &self.license_number),
+            ]
+            .map(|(label, value)| (label, text(value).to_string())),
+        );
+        details
+    }
+}
+
+fn text(value: &Option<String>) -> &str {
+    value.as_deref().unwrap_or_default()
+}
+
+/// Joins the non-empty values as `label: value` lines.
+fn lines(details: &[(&str, String)]) -> String {
+    details
+        .iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(label, value)| format!("{label}: {value}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Reads an export made by Bitwarden in the JSON format. `password` is only
+/// asked for when the export is protected by one. Fails with
+/// `PermissionDenied` when the password is wrong, `Unsupported` for exports
+/// encrypted with the account key, which only Bitwarden can read, and
+/// `InvalidData` when the file is damaged.
+pub fn open(
+    path: impl AsRef<Path>,
+    password: impl FnOnce() -> io::Result<String>,
+) -> io::Result<Export> {
+    let file: ExportFile = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|e| invalid(format!("Not a Bitwarden JSON export: {e}")))?;
+
+    if !file.encrypted {
+        return Ok(file.export);
+    }
+
+    if !file.password_protected {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Exports encrypted with the account key cannot be read, export with a password instead",
+        ));
+    }
+
+    let (enc_key, mac_key) = derive_keys(&file, &password()?)?;
+
+    let validation = file
+        .key_validation
+        .as_deref()
+        .ok_or_else(|| invalid("Missing key validation"))?;
+    if decrypt(validation, &enc_key, &mac_key).is_err() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "Wrong password",
+        ));
+    }
+
+    let data = file
+        .data
+        .as_deref()
+        .ok_or_else(|| invalid("Missing data"))?;
+    serde_json::from_slice(&decrypt(data, &enc_key, &mac_key)?)
+        .map_err(|e| invalid(format!("Invalid export data: {e}")))
+}
+
+/// Derives the encryption and MAC keys from the password, as Bitwarden
+/// does for its PIN and export keys: the key from the KDF is stretched by
+/// HKDF-Expand.
+fn derive_keys(file: &ExportFile, password: &str) -> io::Result<([u8; 32], [u8; 32])> {
+    let salt = file
+        .salt
+        .as_deref()
+        .ok_or_else(|| invalid("Missing salt"))?;
+    let iterations = file
+        .kdf_iterations
+        .filter(|&iterations| iterations > 0)
+        .ok_or_else(|| invalid("Missing KDF iterations"))?;
+
+    let key = match file.kdf_type.unwrap_or(KDF_PBKDF2) {
+        KDF_PBKDF2 => {
+            pbkdf2_hmac_array::<Sha256, 32>(password.as_bytes(), salt.as_bytes(), iterations)
+        }
+        KDF_ARGON2ID => {
+            let memory = file
+                .kdf_memory
+                .ok_or_else(|| invalid("Missing KDF memory"))?;
+            let parallelism = file
+                .kdf_parallelism
+                .ok_or_else(|| invalid("Missing KDF parallelism"))?;
+
+            /* The memory is given in mebibytes, Argon2 counts kibibytes */
+            let params = Params::new(
+                memory.saturating_mul(1024),
+                iterations,
+                parallelism,
+                Some(32),
+            )
+            .map_err(|e| invalid(format!("Invalid Argon2 parameters: {e}")))?;
+
+            let mut key = [0; 32];
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password_into(password.as_bytes(), &Sha256::digest(salt), &mut key)
+                .map_err(|e| invalid(format!("Argon2 failed: {e}")))?;
+            key
+        }
+        kdf => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("Unsupported KDF {kdf}"),
+            ))
+        }
+    };
+
+    Ok((hkdf_expand(&key, b"enc"), hkdf_expand(&key, b"mac")))
+}
+
+/// Decrypts an encrypted string of type 2, `2.iv|data|mac` in base64,
+/// AES-256-CBC authenticated by HMAC-SHA256.
+fn decrypt(text: &str, enc_key: &[u8; 32], mac_key: &[u8; 32]) -> io::Result<Vec<u8>> {
+    let parts = text
+        .strip_prefix("2.")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "Unsupported encryption type"))?
+        .split('|')
+        .map(|part| BASE64.decode(part))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| invalid(format!("Invalid encrypted data: {e}")))?;
+
+    let [iv, data, tag] = parts.as_slice() else {
+        return Err(invalid("Invalid encrypted data"));
+    };
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(mac_key).expect("HMAC takes keys of any size");
+    mac.update(iv);
+    mac.update(data);
+    mac.verify_slice(tag)
+        .map_err(|_| invalid("Damaged encrypted data"))?;
+
+    cbc::Decryptor::<Aes256>::new_from_slices(enc_key, iv)
+        .map_err(|_| invalid("Invalid IV"))?
+        .decrypt_padded_vec_mut::<Pkcs7>(data)
+        .map_err(|_| invalid("Invalid padding"))
+}
+
+/// HKDF-Expand with HMAC-SHA256, for a 32 byte key.
+fn hkdf_expand(key: &[u8; 32], info: &[u8]) -> [u8; 32] {
+    let mut expanded = [0; 32];
+    Hkdf::<Sha256>::from_prk(key)
+        .expect("The key is as long as a SHA-256 hash")
+        .expand(info, &mut expanded)
+        .expect("32 bytes fit in one block");
+    expanded
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
@@ -0,0 +1,110 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+/// Pads or truncates a user supplied key to the 32 bytes AES-256 needs.
+/// A character cut by the 32nd byte is left out and padded for instead.
+///
+/// ```
+/// use pwds_core::crypto::adjust_password_length;
+///
+/// assert_eq!(adjust_password_length("key"), format!("key{}", "0".repeat(29)));
+/// assert_eq!(adjust_password_length(&"k".repeat(40)), "k".repeat(32));
+/// assert_eq!(
+///     adjust_password_length(&format!("a{}", "é".repeat(18))),
+///     format!("a{}0", "é".repeat(15))
+/// );
+/// ```
+pub fn adjust_password_length(password: &str) -> String {
+    const MAX_LENGTH: usize = 32;
+
+    let length = password
+        .char_indices()
+        .map(|(index, c)| index + c.len_utf8())
+        .take_while(|end| *end <= MAX_LENGTH)
+        .last()
+        .unwrap_or(0);
+
+    let mut adjusted = password[..length].to_string();
+    adjusted.push_str(&"0".repeat(MAX_LENGTH - length));
+    adjusted
+}
+
+pub fn gen_pwd() -> Result<String, &'static str> {
+    const LENGTH: usize = 14;
+
+    gen_pwd_with_length(LENGTH)
+}
+
+/// Generates an alphanumeric password of the given length.
+pub fn gen_pwd_with_length(length: usize) -> Result<String, &'static str> {
+    if length == 0 {
+        return Err("Password length must be positive");
+    }
+
+    let mut rng = rand::thread_rng();
+    let pwd = std::iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(length)
+        .collect();
+
+    Ok(pwd)
+}
+
+pub trait CryptoManager {
+    fn encrypt(&self, plaintext: String) -> String;
+    fn decrypt(&self, encrypted_data: String) -> Result<String, String>;
+}
+
+pub struct Cipher {
+    key: Key<Aes256Gcm>,
+}
+
+impl Cipher {
+    /// `base_key` must be 32 bytes long, see `adjust_password_length`;
+    /// `Vault` checks its keys before making a cipher of them.
+    pub(crate) fn new(base_key: &str) -> Self {
+        let key = Key::<Aes256Gcm>::from_slice(base_key.as_bytes());
+        Self { key: *key }
+    }
+}
+
+impl CryptoManager for Cipher {
+    fn encrypt(&self, plaintext: String) -> String {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let cipher = Aes256Gcm::new(&self.key);
+        let ciphered_data = cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .expect("Failed to encrypt");
+        let mut encrypted_data: Vec<u8> = nonce.to_vec();
+        encrypted_data.extend_from_slice(&ciphered_data);
+        hex::encode(encrypted_data)
+    }
+
+    fn decrypt(&self, encrypted_data: String) -> Result<String, String> {
+        let encrypted_data = hex::decode(encrypted_data)
+            .map_err(|e| format!("Failed to decode hex string into vec: {}", e))?;
+
+        let nonce_len = 12;
+        if encrypted_data.len() < nonce_len {
+            return Err(format!(
+                "Data length too short: expected at least {}, got {}",
+                nonce_len,
+                encrypted_data.len()
+            ));
+        }
+        let (nonce_arr, ciphered_data) = encrypted_data.split_at(nonce_len);
+        let nonce = Nonce::from_slice(nonce_arr);
+        let cipher = Aes256Gcm::new(&self.key);
+        cipher
+            .decrypt(nonce, ciphered_data)
+            .map_err(|e| format!("Failed to decrypt data: {}", e))
+            .and_then(|plaintext| {
+                String::from_utf8(plaintext)
+                    .map_err(|e| format!("Failed to convert bytes to string: {}", e))
+            })
+    }
+}
@@ -0,0 +1,330 @@
+//! Reading of the CSV exports of browsers and password managers, see
+//! `Preset`, or of any CSV file given the field of each column.
+
+use std::io;
+use std::path::Path;
+
+use crate::otp;
+use crate::pwds::{parse_tags, Entry};
+
+/// The pwds field a CSV column is imported into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    Title,
+    Username,
+    Password,
+    Url,
+    Tags,
+    Notes,
+    /// An `otpauth://` URI or a base32 secret, see `otp::import_uri`,
+    /// treated like `Extra` when it is neither.
+    Otp,
+    /// Appended to the notes as `header: value`.
+    Extra,
+}
+
+impl Field {
+    pub const ALL: [Field; 8] = [
+        Field::Title,
+        Field::Username,
+        Field::Password,
+        Field::Url,
+        Field::Tags,
+        Field::Notes,
+        Field::Otp,
+        Field::Extra,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Title => "title",
+            Field::Username => "username",
+            Field::Password => "password",
+            Field::Url => "url",
+            Field::Tags => "tags",
+            Field::Notes => "notes",
+            Field::Otp => "otp",
+            Field::Extra => "extra",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Field::ALL.into_iter().find(|field| field.name() == name)
+    }
+}
+
+/// Layout of the CSV files exported by common browsers and password managers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    Chrome,
+    Firefox,
+    Bitwarden,
+    OnePassword,
+    LastPass,
+}
+
+impl Preset {
+    /// Most specific layouts first, as `detect` tries them in order.
+    pub const ALL: [Preset; 5] = [
+        Preset::Bitwarden,
+        Preset::Firefox,
+        Preset::OnePassword,
+        Preset::LastPass,
+        Preset::Chrome,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Chrome => "Chrome",
+            Preset::Firefox => "Firefox",
+            Preset::Bitwarden => "Bitwarden",
+            Preset::OnePassword => "1Password",
+            Preset::LastPass => "LastPass",
+        }
+    }
+
+    /// Headers only found in the exports of this layout.
+    fn markers(self) -> &'static [&'static str] {
+        match self {
+            Preset::Chrome => &["name", "url", "username", "password"],
+            Preset::Firefox => &["httpRealm", "formActionOrigin"],
+            Preset::Bitwarden => &["login_uri", "login_password"],
+            Preset::OnePassword => &["Title", "OTPAuth"],
+            Preset::LastPass => &["grouping", "extra"],
+        }
+    }
+
+    /// Header of each imported column, with its field.
+    fn columns(self) -> &'static [(&'static str, Field)] {
+        match self {
+            Preset::Chrome => &[
+                ("name", Field::Title),
+                ("url", Field::Url),
+                ("username", Field::Username),
+                ("password", Field::Password),
+                ("note", Field::Notes),
+            ],
+            Preset::Firefox => &[
+                ("url", Field::Url),
+                ("username", Field::Username),
+                ("password", Field::Password),
+            ],
+            Preset::Bitwarden => &[
+                ("folder", Field::Tags),
+                ("name", Field::Title),
+                ("notes", Field::Notes),
+                ("fields", Field::Extra),
+                ("login_uri", Field::Url),
+                ("login_username", Field::Username),
+                ("login_password", Field::Password),
+                ("login_totp", Field::Otp),
+            ],
+            Preset::OnePassword => &[
+                ("Title", Field::Title),
+                ("Url", Field::Url),
+                ("Username", Field::Username),
+                ("Password", Field::Password),
+                ("OTPAuth", Field::Otp),
+                ("Tags", Field::Tags),
+                ("Notes", Field::Notes),
+            ],
+            Preset::LastPass => &[
+                ("url", Field::Url),
+                ("username", Field::Username),
+                ("password", Field::Password),
+                ("totp", Field::Otp),
+                ("extra", Field::Notes),
+                ("name", Field::Title),
+                ("grouping", Field::Tags),
+            ],
+        }
+    }
+
+    /// Recognizes the layout of a file from its header.
+    ///
+    /// ```
+    /// use pwds_core::csv::{Field, Preset};
+    ///
+    /// let headers: Vec<String> = ["name", "url", "username", "password", "note"]
+    ///     .map(String::from)
+    ///     .into();
+    /// let preset = Preset::detect(&headers).unwrap();
+    /// assert_eq!(preset, Preset::Chrome);
+    ///
+    /// let row = ["Mail", "https://mail.example", "jdoe", "hunter2", ""].map(String::from);
+    /// let entry = preset.mapping(&headers).entry(&headers, &row);
+    /// assert_eq!((entry.title.as_str(), entry.password.as_str()), ("Mail", "hunter2"));
+    /// assert!(preset.mapping(&headers).has(Field::Url));
+    /// ```
+    pub fn detect(headers: &[String]) -> Option<Self> {
+        Preset::ALL.into_iter().find(|preset| {
+            preset
+                .markers()
+                .iter()
+                .all(|marker| column(headers, marker).is_some())
+        })
+    }
+
+    /// Maps the columns of a file in this layout. Columns the layout does
+    /// not know are ignored.
+    pub fn mapping(self, headers: &[String]) -> Mapping {
+        let mut mapping = Mapping(vec![None; headers.len()]);
+
+        for (header, field) in self.columns() {
+            if let Some(index) = column(headers, header) {
+                mapping.0[index] = Some(*field);
+            }
+        }
+
+        mapping
+    }
+}
+
+/// Finds a column by header, ignoring case.
+pub fn column(headers: &[String], name: &str) -> Option<usize> {
+    headers
+        .iter()
+        .position(|header| header.eq_ignore_ascii_case(name))
+}
+
+/// The field of each column, `None` for the ignored ones.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Mapping(pub Vec<Option<Field>>);
+
+impl Mapping {
+    pub fn has(&self, field: Field) -> bool {
+        self.0.contains(&Some(field))
+    }
+
+    /// Builds the entry of a row. When several columns hold the same field,
+    /// the first non-empty one wins, but notes and tags are joined.
+    pub fn entry(&self, headers: &[String], row: &[String]) -> Entry {
+        let mut entry = Entry::default();
+        let mut notes = Vec::new();
+        let mut extra = Vec::new();
+
+        for ((field, value), header) in self.0.iter().zip(row).zip(headers) {
+            let value = value.trim();
+            let Some(field) = field.filter(|_| !value.is_empty()) else {
+                continue;
+            };
+
+            let single = match field {
+                Field::Title => &mut entry.title,
+                Field::Username => &mut entry.username,
+                Field::Password => &mut entry.password,
+                Field::Url => &mut entry.url,
+                Field::Tags => {
+                    for tag in parse_tags(value) {
+                        if !entry.tags.contains(&tag) {
+                            entry.tags.push(tag);
+                        }
+                    }
+                    continue;
+                }
+                Field::Notes => {
+                    notes.push(value.to_string());
+                    continue;
+                }
+                Field::Otp => {
+                    match otp::import_uri(value) {
+                        Some(uri) if entry.otp.is_empty() => entry.otp = uri,
+                        _ => extra.push(format!("{header}: {value}")),
+                    }
+                    continue;
+                }
+                Field::Extra => {
+                    extra.push(format!("{header}: {value}"));
+                    continue;
+                }
+            };
+
+            if single.is_empty() {
+                *single = value.to_string();
+            }
+        }
+
+        if !extra.is_empty() {
+            notes.push(extra.join("\n"));
+        }
+        entry.notes = notes.join("\n\n");
+
+        entry
+    }
+}
+
+/// A CSV file whose first line holds the headers.
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    pub headers: Vec<String>,
+    /// The rows with their line number.
+    pub rows: Vec<(u64, Vec<String>)>,
+    /// Rows that could not be read, with their line number and the reason.
+    pub malformed: Vec<(u64, String)>,
+}
+
+impl Table {
+    /// Reads a CSV file. Rows without as many fields as there are headers
+    /// are reported as malformed rather than guessed at; empty lines are
+    /// ignored.
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut reader = ::csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_path(path)
+            .map_err(csv_error)?;
+
+        let headers: Vec<String> = reader
+            .headers()
+            .map_err(csv_error)?
+            .iter()
+            .map(|header| header.trim_start_matches('\u{feff}').trim().to_string())
+            .collect();
+
+        let mut table = Table {
+            headers,
+            ..Default::default()
+        };
+
+        for record in reader.records() {
+            match record {
+                Ok(record) => {
+                    let line = record.position().map_or(0, |position| position.line());
+
+                    if record.len() == table.headers.len() {
+                        table
+                            .rows
+                            .push((line, record.iter().map(String::from).collect()));
+                    } else {
+                        table.malformed.push((
+                            line,
+                            format!("{} fields instead of {}", record.len(), table.headers.len()),
+                        ));
+                    }
+                }
+                Err(e) => {
+                    let line = e.position().map_or(0, |position| position.line());
+                    table.malformed.push((line, e.to_string()));
+                }
+            }
+        }
+
+        Ok(table)
+    }
+
+    /// Builds the entry of every row, see `Mapping::entry`.
+    pub fn entries(&self, mapping: &Mapping) -> Vec<Entry> {
+        self.rows
+            .iter()
+            .map(|(_, row)| mapping.entry(&self.headers, row))
+            .collect()
+    }
+}
+
+fn csv_error(e: ::csv::Error) -> io::Error {
+    let message = e.to_string();
+
+    match e.into_kind() {
+        ::csv::ErrorKind::Io(e) => e,
+        _ => io::Error::new(io::ErrorKind::InvalidData, message),
+    }
+}
@@ -0,0 +1,173 @@
+//! Adding entries read from other password managers to a vault, see
+//! `bitwarden`, `csv`, `kdbx` and `pass`.
+
+use std::io;
+
+use crate::pwds::{new_id, Entry};
+use crate::vault::Vault;
+
+/// What to do with an imported entry having the same username and URL as
+/// a stored one, or as one imported before it. See `Entry::is_duplicate_of`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnDuplicate {
+    /// Leave the imported entry out.
+    #[default]
+    Skip,
+    /// Merge it into the other entry, see `Entry::merge`.
+    Merge,
+    /// Replace the other entry, keeping its id.
+    Overwrite,
+    /// Store both.
+    KeepBoth,
+}
+
+/// The changes an import makes, planned before anything is written so that
+/// they can be reviewed first.
+#[derive(Clone, Debug, Default)]
+pub struct Import {
+    /// New entries, with the ids they will be stored under.
+    pub added: Vec<Entry>,
+    /// Stored entries as they will be after the import.
+    pub updated: Vec<Entry>,
+    /// Entries left out, with the reason.
+    pub skipped: Vec<(Entry, String)>,
+}
+
+impl Import {
+    /// Plans the import of `entries` into a vault holding `stored`. Entries
+    /// without a username, like Wi-Fi passphrases, cards or notes, are
+    /// stored under their title. Entries the vault cannot hold anyway, see
+    /// `Entry::validate`, are skipped.
+    ///
+    /// ```
+    /// use pwds_core::import::{Import, OnDuplicate};
+    /// use pwds_core::pwds::Entry;
+    ///
+    /// let wifi = Entry {
+    ///     title: "Home: Wi-Fi".to_string(),
+    ///     password: "secret".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let import = Import::plan(&[], vec![wifi, Entry::default()], OnDuplicate::Skip);
+    /// assert_eq!(import.added[0].username, "Home Wi-Fi");
+    /// assert_eq!(import.skipped.len(), 1);
+    /// ```
+    pub fn plan(stored: &[Entry], entries: Vec<Entry>, on_duplicate: OnDuplicate) -> Self {
+        let mut import = Import::default();
+
+        for mut entry in entries {
+            entry.id = new_id();
+
+            if entry.username.is_empty() {
+                entry.username = username_from_name(&entry.title);
+            }
+
+            if let Err(reason) = entry.validate() {
+                import.skipped.push((entry, reason));
+                continue;
+            }
+
+            if on_duplicate == OnDuplicate::KeepBoth {
+                import.added.push(entry);
+                continue;
+            }
+
+            /* Entries already planned hold the latest version */
+            if let Some(planned) = import
+                .added
+                .iter_mut()
+                .chain(&mut import.updated)
+                .find(|planned| entry.is_duplicate_of(planned))
+            {
+                match on_duplicate {
+                    OnDuplicate::Merge => planned.merge(&entry),
+                    OnDuplicate::Overwrite => {
+                        entry.id = std::mem::take(&mut planned.id);
+                        *planned = entry;
+                    }
+                    _ => import.skipped.push((
+                        entry,
+                        "Same username and URL as another imported entry".to_string(),
+                    )),
+                }
+                continue;
+            }
+
+            let Some(duplicate) = stored.iter().find(|stored| entry.is_duplicate_of(stored)) else {
+                import.added.push(entry);
+                continue;
+            };
+
+            match on_duplicate {
+                OnDuplicate::Merge => {
+                    let mut merged = duplicate.clone();
+                    merged.merge(&entry);
+                    import.updated.push(merged);
+                }
+                OnDuplicate::Overwrite => {
+                    entry.id = duplicate.id.clone();
+                    import.updated.push(entry);
+                }
+                _ => {
+                    let reason = format!("Same username and URL as entry {}", duplicate.id);
+                    import.skipped.push((entry, reason));
+                }
+            }
+        }
+
+        import
+    }
+
+    /// Writes the planned changes to the vault at once, or nothing when one
+    /// of them fails, see `Vault::save_all`.
+    ///
+    /// ```
+    /// use pwds_core::import::{Import, OnDuplicate};
+    /// use pwds_core::pwds::Entry;
+    /// use pwds_core::vault::Vault;
+    ///
+    /// let path = std::env::temp_dir().join(format!("pwds-import-{}.enc", std::process::id()));
+    /// let vault = Vault::new(&path, "0".repeat(32))?;
+    ///
+    /// let entry = |username: &str| Entry {
+    ///     username: username.to_string(),
+    ///     password: "secret".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// vault.add(&entry("carol"))?;
+    ///
+    /// /* Planned against an entry removed since */
+    /// let stored = [Entry { id: "gone".to_string(), ..entry("alice") }];
+    /// let import = Import::plan(&stored, vec![entry("alice"), entry("zed")], OnDuplicate::Overwrite);
+    /// assert!(import.apply(&vault).is_err());
+    /// assert_eq!(vault.entries()?.len(), 1);
+    ///
+    /// std::fs::remove_file(&path)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn apply(&self, vault: &Vault) -> io::Result<()> {
+        vault.save_all(&self.updated, &self.added).map(|_| ())
+    }
+}
+
+/// Appends a paragraph to the notes of an imported entry.
+pub(crate) fn append_section(notes: &mut String, section: &str) {
+    if section.is_empty() {
+        return;
+    }
+
+    if !notes.is_empty() {
+        notes.push_str("\n\n");
+    }
+    notes.push_str(section);
+}
+
+/// A username for an imported entry that has none, made of its name, as
+/// the vault cannot hold entries without one. Usernames cannot hold colons
+/// nor line breaks.
+fn username_from_name(name: &str) -> String {
+    name.split(|c: char| c == ':' || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
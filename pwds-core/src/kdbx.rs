@@ -0,0 +1,1179 @@
+//! Reading and writing of KeePass KDBX 4 databases, the format of KeePass
+//! 2.35 and later and of KeePassXC, so that entries can move between them
+//! and a vault.
+//!
+//! ```
+//! use pwds_core::import::{Import, OnDuplicate};
+//! use pwds_core::kdbx::{self, GroupMapping};
+//!
+//! /* Every key derivation and cipher holds the same entries */
+//! for fixture in ["argon2d-chacha20", "argon2id-aes", "aes-kdf-aes"] {
+//!     let database = kdbx::open(format!("fixtures/kdbx/{fixture}.kdbx"), "pwds")?;
+//!     let entries = database.entries(GroupMapping::Folder);
+//!     assert_eq!(entries.len(), 4, "{fixture}");
+//!
+//!     let mail = entries.iter().find(|entry| entry.title == "Mail").unwrap();
+//!     assert_eq!(mail.password, "Tr0ub4dor&3", "{fixture}");
+//!     assert_eq!(mail.tags, ["Internet/Email"]);
+//!     assert!(mail.notes.contains("letmein"));
+//!
+//!     let forum = entries.iter().find(|entry| entry.title == "Forum").unwrap();
+//!     assert_eq!(forum.password, "<&>\"' ünïcödé", "{fixture}");
+//!
+//!     /* Entries without a username are imported under their title */
+//!     let import = Import::plan(&[], entries, OnDuplicate::Skip);
+//!     let wifi = import.added.iter().find(|entry| entry.title == "Wi-Fi").unwrap();
+//!     assert_eq!(wifi.username, "Wi-Fi");
+//!
+//!     let error = kdbx::open(format!("fixtures/kdbx/{fixture}.kdbx"), "wrong").unwrap_err();
+//!     assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied, "{fixture}");
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::{BlockDecryptMut, BlockEncrypt, KeyInit, KeyIvInit, StreamCipher};
+use aes::{Aes256, Block};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20::ChaCha20;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use hmac::{Hmac, Mac};
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use rand::RngCore;
+use salsa20::Salsa20;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::import::append_section;
+use crate::otp;
+use crate::pwds::Entry;
+
+const SIGNATURE: [u8; 8] = [0x03, 0xd9, 0xa2, 0x9a, 0x67, 0xfb, 0x4b, 0xb5];
+
+const CIPHER_AES256: [u8; 16] = [
+    0x31, 0xc1, 0xf2, 0xe6, 0xbf, 0x71, 0x43, 0x50, 0xbe, 0x58, 0x05, 0x21, 0x6a, 0xfc, 0x5a, 0xff,
+];
+const CIPHER_CHACHA20: [u8; 16] = [
+    0xd6, 0x03, 0x8a, 0x2b, 0x8b, 0x6f, 0x4c, 0xb5, 0xa5, 0x24, 0x33, 0x9a, 0x31, 0xdb, 0xb5, 0x9a,
+];
+
+const KDF_AES: [u8; 16] = [
+    0xc9, 0xd9, 0xf3, 0x9a, 0x62, 0x8a, 0x44, 0x60, 0xbf, 0x74, 0x0d, 0x08, 0xc1, 0x8a, 0x4f, 0xea,
+];
+const KDF_ARGON2D: [u8; 16] = [
+    0xef, 0x63, 0x6d, 0xdf, 0x8c, 0x29, 0x44, 0x4b, 0x91, 0xf7, 0xa9, 0xa4, 0x03, 0xe3, 0x0a, 0x0c,
+];
+const KDF_ARGON2ID: [u8; 16] = [
+    0x9e, 0x29, 0x8b, 0x19, 0x56, 0xdb, 0x47, 0x73, 0xb2, 0x3d, 0xfc, 0x3e, 0xc6, 0xf0, 0xa1, 0xe6,
+];
+
+/// Inner random streams protecting fields like passwords in the XML.
+const STREAM_NONE: u32 = 0;
+const STREAM_SALSA20: u32 = 2;
+const STREAM_CHACHA20: u32 = 3;
+const SALSA20_NONCE: [u8; 8] = [0xe8, 0x30, 0x09, 0x4b, 0x97, 0x20, 0x5d, 0x2a];
+
+/// Strings every KeePass entry has. Any other string is a custom one.
+const STANDARD_FIELDS: [&str; 5] = ["Title", "UserName", "Password", "URL", "Notes"];
+/// The string KeePassXC keeps the `otpauth://` URI of an entry in.
+const OTP_FIELD: &str = "otp";
+
+/// Heading of the notes paragraph listing the passwords of the history.
+const HISTORY_HEADING: &str = "Previous passwords:";
+
+/// Argon2id parameters of written databases. Deriving the key takes about
+/// a second, as with the parameters KeePassXC picks.
+const ARGON2_MEMORY: u64 = 64 * 1024 * 1024;
+const ARGON2_ITERATIONS: u64 = 10;
+const ARGON2_PARALLELISM: u32 = 2;
+
+/// Size of the HMAC checked blocks of written databases.
+const BLOCK_SIZE: usize = 1024 * 1024;
+
+/// Seconds from 0001-01-01, where KDBX 4 times start, to 1970-01-01.
+const UNIX_EPOCH_SECONDS: i64 = 62_135_596_800;
+
+/// A decrypted KDBX database. The recycle bin is left out.
+#[derive(Clone, Debug, Default)]
+pub struct Database {
+    pub root: Group,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Group {
+    pub name: String,
+    pub uuid: String,
+    pub groups: Vec<Group>,
+    pub entries: Vec<Record>,
+}
+
+/// A KeePass entry: its strings, standard and custom, in file order.
+#[derive(Clone, Debug, Default)]
+pub struct Record {
+    pub strings: Vec<(String, String)>,
+    pub tags: Vec<String>,
+    /// Last modification, as `YYYY-MM-DD HH:MM` in UTC.
+    pub modified: String,
+    /// Previous versions of the entry, oldest first.
+    pub history: Vec<Record>,
+}
+
+/// How the group of a KeePass entry is kept, pwds having tags only.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GroupMapping {
+    /// One tag holding the path of the group, like `Internet/Email`.
+    #[default]
+    Folder,
+    /// One tag per group on the path.
+    Tags,
+    /// Groups are dropped.
+    None,
+}
+
+impl Database {
+    /// Builds a database holding `entries` in a single group named `name`,
+    /// see `Record::from_entry`.
+    pub fn from_entries(name: &str, entries: &[Entry]) -> Self {
+        Database {
+            root: Group {
+                name: name.to_string(),
+                entries: entries.iter().map(Record::from_entry).collect(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Returns every entry as a pwds entry without id, see `Record::to_entry`.
+    /// The root group, usually named after the database, is not a folder.
+    pub fn entries(&self, groups: GroupMapping) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        collect_entries(&self.root, &mut Vec::new(), groups, &mut entries);
+        entries
+    }
+}
+
+fn collect_entries(
+    group: &Group,
+    path: &mut Vec<String>,
+    groups: GroupMapping,
+    entries: &mut Vec<Entry>,
+) {
+    let tags = match groups {
+        GroupMapping::Folder if !path.is_empty() => vec![path.join("/")],
+        GroupMapping::Tags => path.clone(),
+        _ => vec![],
+    };
+
+    entries.extend(group.entries.iter().map(|record| record.to_entry(&tags)));
+
+    for subgroup in &group.groups {
+        /* Tags are stored comma separated */
+        path.push(subgroup.name.replace(',', " "));
+        collect_entries(subgroup, path, groups, entries);
+        path.pop();
+    }
+}
+
+impl Record {
+    /// Returns a string by key, empty when the entry has none.
+    pub fn get(&self, key: &str) -> &str {
+        self.strings
+            .iter()
+            .find(|(name, _)| name == key)
+            .map_or("", |(_, value)| value.as_str())
+    }
+
+    /// Converts the entry, with `group_tags` added to its own tags. The
+    /// `otp` string of KeePassXC gives the one-time passwords. Other custom strings and the passwords found in the
+    /// history, which pwds has no place for, are appended to the notes.
+    pub fn to_entry(&self, group_tags: &[String]) -> Entry {
+        let mut notes = self.get("Notes").to_string();
+        let otp = otp::import_uri(self.get(OTP_FIELD)).unwrap_or_default();
+
+        let custom: Vec<String> = self
+            .strings
+            .iter()
+            .filter(|(name, value)| !STANDARD_FIELDS.contains(&name.as_str()) && !value.is_empty())
+            .filter(|(name, _)| name != OTP_FIELD || otp.is_empty())
+            .map(|(name, value)| format!("{name}: {value}"))
+            .collect();
+        append_section(&mut notes, &custom.join("\n"));
+
+        let password = self.get("Password");
+        let mut previous: Vec<&str> = Vec::new();
+        let mut history = String::from(HISTORY_HEADING);
+
+        for record in self.history.iter().rev() {
+            let old = record.get("Password");
+            if old.is_empty() || old == password || previous.contains(&old) {
+                continue;
+            }
+
+            previous.push(old);
+            history.push_str(&format!("\n{}  {}", record.modified, old));
+        }
+
+        if !previous.is_empty() {
+            append_section(&mut notes, &history);
+        }
+
+        let mut tags = self.tags.clone();
+        for tag in group_tags {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+
+        Entry {
+            id: String::new(),
+            title: self.get("Title").to_string(),
+            username: self.get("UserName").to_string(),
+            password: password.to_string(),
+            url: self.get("URL").trim().to_string(),
+            tags,
+            notes,
+            otp,
+        }
+    }
+
+    /// Converts a pwds entry. A history written by `to_entry` is taken out
+    /// of the notes and turned back into previous versions of the entry.
+    pub fn from_entry(entry: &Entry) -> Self {
+        let (notes, history) = split_history(&entry.notes);
+
+        let strings = |password: &str| {
+            [
+                ("Title", entry.title.as_str()),
+                ("UserName", &entry.username),
+                ("Password", password),
+                ("URL", &entry.url),
+                ("Notes", &notes),
+                (OTP_FIELD, &entry.otp),
+            ]
+            .into_iter()
+            .filter(|(name, value)| *name != OTP_FIELD || !value.is_empty())
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<_>>()
+        };
+
+        Record {
+            strings: strings(&entry.password),
+            tags: entry.tags.clone(),
+            modified: format_unix(now()),
+            history: history
+                .into_iter()
+                .rev()
+                .map(|(modified, password)| Record {
+                    strings: strings(password),
+                    tags: entry.tags.clone(),
+                    modified: modified.to_string(),
+                    history: vec![],
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Splits the notes paragraph written by `Record::to_entry` off the notes,
+/// giving the rest of the notes and the previous passwords, newest first.
+/// Notes are kept whole when the paragraph does not look like one.
+fn split_history(notes: &str) -> (String, Vec<(&str, &str)>) {
+    let start = if notes.starts_with(HISTORY_HEADING) {
+        0
+    } else if let Some(index) = notes.find(&format!("\n\n{HISTORY_HEADING}\n")) {
+        index + 2
+    } else {
+        return (notes.to_string(), vec![]);
+    };
+
+    let section_end = notes[start..]
+        .find("\n\n")
+        .map_or(notes.len(), |end| start + end);
+
+    let history: Option<Vec<(&str, &str)>> = notes[start..section_end]
+        .lines()
+        .skip(1)
+        .map(|line| {
+            line.split_once("  ")
+                .filter(|(time, _)| parse_time(time).is_some())
+        })
+        .collect();
+
+    match history {
+        Some(history) if !history.is_empty() => {
+            let rest = [notes[..start].trim_end(), notes[section_end..].trim_start()];
+            (rest.join("\n\n").trim().to_string(), history)
+        }
+        _ => (notes.to_string(), vec![]),
+    }
+}
+
+/// Decrypts the database at `path` with its master password. Key files are
+/// not supported. Fails with `PermissionDenied` when the password is wrong,
+/// `Unsupported` for other KDBX versions and ciphers, and `InvalidData`
+/// when the file is damaged.
+pub fn open(path: impl AsRef<Path>, password: &str) -> io::Result<Database> {
+    let data = fs::read(path)?;
+    let mut bytes = Bytes::new(&data);
+
+    if bytes.take(8)? != SIGNATURE {
+        return Err(invalid("Not a KeePass database"));
+    }
+
+    let minor = bytes.u16()?;
+    let major = bytes.u16()?;
+    if major != 4 {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("KDBX {major}.{minor} is not supported, save the database as KDBX 4"),
+        ));
+    }
+
+    let header = Header::read(&mut bytes)?;
+    let header_end = bytes.position;
+
+    if Sha256::digest(&data[..header_end]).as_slice() != bytes.take(32)? {
+        return Err(invalid("The KDBX header is corrupt"));
+    }
+
+    let transformed = transform_key(&header.kdf, &composite_key(password))?;
+    let (cipher_key, hmac_key) = derive_keys(&header.master_seed, &transformed);
+
+    let mut mac = block_mac(&hmac_key, u64::MAX);
+    mac.update(&data[..header_end]);
+    if mac.verify_slice(bytes.take(32)?).is_err() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "Wrong password for the KeePass database",
+        ));
+    }
+
+    let encrypted = read_blocks(&mut bytes, &hmac_key)?;
+
+    let mut content = header.decrypt(&cipher_key, encrypted)?;
+
+    if header.compressed {
+        let mut decompressed = Vec::new();
+        GzDecoder::new(content.as_slice())
+            .read_to_end(&mut decompressed)
+            .map_err(|e| invalid(format!("Cannot decompress the KDBX content: {e}")))?;
+        content = decompressed;
+    }
+
+    let mut inner = Bytes::new(&content);
+    let stream = InnerStream::read(&mut inner)?;
+
+    let xml = std::str::from_utf8(&content[inner.position..])
+        .map_err(|_| invalid("The KDBX content is not UTF-8"))?;
+
+    parse_xml(xml, stream)
+}
+
+impl Database {
+    /// Writes the database to `path`, protected by `password`, with the
+    /// KeePassXC defaults: Argon2id, ChaCha20 and gzip compression.
+    ///
+    /// ```
+    /// use pwds_core::kdbx::{self, Database, GroupMapping};
+    /// use pwds_core::pwds::Entry;
+    ///
+    /// let entry = Entry {
+    ///     title: "Mail".to_string(),
+    ///     username: "jdoe@example.com".to_string(),
+    ///     password: "correct horse".to_string(),
+    ///     url: "https://mail.example.com".to_string(),
+    ///     tags: vec!["work".to_string(), "Internet/Email".to_string()],
+    ///     notes: "Shared inbox\n\nPrevious passwords:\n\
+    ///             2021-01-01 12:00  letmein\n\
+    ///             2020-01-01 12:00  hunter2"
+    ///         .to_string(),
+    ///     otp: "otpauth://totp/Mail?secret=JBSWY3DPEHPK3PXP".to_string(),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let path = std::env::temp_dir().join(format!("pwds-save-{}.kdbx", std::process::id()));
+    /// Database::from_entries("pwds", &[entry.clone()]).save(&path, "master")?;
+    /// let database = kdbx::open(&path, "master")?;
+    /// std::fs::remove_file(&path)?;
+    ///
+    /// /* The history became previous versions of the KeePass entry */
+    /// let record = &database.root.entries[0];
+    /// assert_eq!(record.get("Notes"), "Shared inbox");
+    /// assert_eq!(record.history.len(), 2);
+    /// assert_eq!(record.history[0].get("Password"), "hunter2");
+    ///
+    /// let entries = database.entries(GroupMapping::Folder);
+    /// let [saved] = entries.as_slice() else { panic!("{entries:?}") };
+    /// assert_eq!(saved.title, entry.title);
+    /// assert_eq!(saved.username, entry.username);
+    /// assert_eq!(saved.password, entry.password);
+    /// assert_eq!(saved.url, entry.url);
+    /// assert_eq!(saved.tags, entry.tags);
+    /// assert_eq!(saved.otp, entry.otp);
+    /// assert_eq!(saved.notes, entry.notes);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn save(&self, path: impl AsRef<Path>, password: &str) -> io::Result<()> {
+        let master_seed: [u8; 32] = random_bytes();
+        let iv: [u8; 12] = random_bytes();
+        let stream_key: [u8; 64] = random_bytes();
+
+        let kdf_items: [(u8, &str, Vec<u8>); 6] = [
+            (0x42, "$UUID", KDF_ARGON2ID.to_vec()),
+            (0x42, "S", random_bytes::<32>().to_vec()),
+            (0x04, "P", ARGON2_PARALLELISM.to_le_bytes().to_vec()),
+            (0x05, "M", ARGON2_MEMORY.to_le_bytes().to_vec()),
+            (0x05, "I", ARGON2_ITERATIONS.to_le_bytes().to_vec()),
+            (0x04, "V", 0x13u32.to_le_bytes().to_vec()),
+        ];
+        let kdf = kdf_items
+            .iter()
+            .map(|(_, name, value)| (name.to_string(), value.clone()))
+            .collect();
+
+        let transformed = transform_key(&kdf, &composite_key(password))?;
+        let (cipher_key, hmac_key) = derive_keys(&master_seed, &transformed);
+
+        let mut header = SIGNATURE.to_vec();
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&4u16.to_le_bytes());
+        write_field(&mut header, 2, &CIPHER_CHACHA20);
+        write_field(&mut header, 3, &1u32.to_le_bytes());
+        write_field(&mut header, 4, &master_seed);
+        write_field(&mut header, 7, &iv);
+        write_field(&mut header, 11, &write_variant_dictionary(&kdf_items));
+        write_field(&mut header, 0, b"\r\n\r\n");
+
+        let mut inner = Vec::new();
+        write_field(&mut inner, 1, &STREAM_CHACHA20.to_le_bytes());
+        write_field(&mut inner, 2, &stream_key);
+        write_field(&mut inner, 0, &[]);
+
+        let mut stream = InnerStream::new(STREAM_CHACHA20, &stream_key)?;
+        inner.extend_from_slice(self.to_xml(&mut stream).as_bytes());
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&inner)?;
+        let mut content = encoder.finish()?;
+
+        ChaCha20::new(&cipher_key.into(), &iv.into()).apply_keystream(&mut content);
+
+        let mut data = header.clone();
+        data.extend_from_slice(&Sha256::digest(&header));
+        let mut mac = block_mac(&hmac_key, u64::MAX);
+        mac.update(&header);
+        data.extend_from_slice(&mac.finalize().into_bytes());
+        write_blocks(&mut data, &content, &hmac_key);
+
+        fs::write(path, data)
+    }
+
+    /// Writes the XML document, protecting passwords with `stream` in order.
+    fn to_xml(&self, stream: &mut InnerStream) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\n<KeePassFile><Meta>\
+             <Generator>pwds</Generator>",
+        );
+        xml.push_str(&format!(
+            "<DatabaseName>{}</DatabaseName>",
+            escape(&self.root.name)
+        ));
+        xml.push_str(
+            "<MemoryProtection><ProtectPassword>True</ProtectPassword></MemoryProtection>\
+             <RecycleBinEnabled>False</RecycleBinEnabled></Meta><Root>",
+        );
+        write_group(&mut xml, &self.root, stream);
+        xml.push_str("<DeletedObjects/></Root></KeePassFile>");
+        xml
+    }
+}
+
+fn write_group(xml: &mut String, group: &Group, stream: &mut InnerStream) {
+    let uuid = match group.uuid.as_str() {
+        "" => new_uuid(),
+        uuid => uuid.to_string(),
+    };
+
+    xml.push_str(&format!(
+        "<Group><UUID>{uuid}</UUID><Name>{}</Name>",
+        escape(&group.name)
+    ));
+    for record in &group.entries {
+        write_record(xml, record, &new_uuid(), stream);
+    }
+    for subgroup in &group.groups {
+        write_group(xml, subgroup, stream);
+    }
+    xml.push_str("</Group>");
+}
+
+/// Writes an entry. Previous versions share the UUID of the entry.
+fn write_record(xml: &mut String, record: &Record, uuid: &str, stream: &mut InnerStream) {
+    let time = kdbx_time(&record.modified);
+
+    xml.push_str(&format!(
+        "<Entry><UUID>{uuid}</UUID><Tags>{}</Tags><Times>\
+         <CreationTime>{time}</CreationTime>\
+         <LastModificationTime>{time}</LastModificationTime>\
+         <LastAccessTime>{time}</LastAccessTime>\
+         <Expires>False</Expires></Times>",
+        escape(record.tags.join(";"))
+    ));
+
+    for (name, value) in &record.strings {
+        let value = if name == "Password" {
+            format!(
+                "<Value Protected=\"True\">{}</Value>",
+                stream.protect(value)
+            )
+        } else {
+            format!("<Value>{}</Value>", escape(value))
+        };
+        xml.push_str(&format!(
+            "<String><Key>{}</Key>{value}</String>",
+            escape(name)
+        ));
+    }
+
+    if !record.history.is_empty() {
+        xml.push_str("<History>");
+        for previous in &record.history {
+            write_record(xml, previous, uuid, stream);
+        }
+        xml.push_str("</History>");
+    }
+
+    xml.push_str("</Entry>");
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+fn new_uuid() -> String {
+    BASE64.encode(random_bytes::<16>())
+}
+
+/// Appends a header field: its type, then its data prefixed by its length.
+fn write_field(output: &mut Vec<u8>, kind: u8, data: &[u8]) {
+    output.push(kind);
+    output.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    output.extend_from_slice(data);
+}
+
+/// Splits the content in blocks each followed by its HMAC, ending with an
+/// empty block, see `read_blocks`.
+fn write_blocks(output: &mut Vec<u8>, content: &[u8], hmac_key: &[u8; 64]) {
+    let blocks = content.chunks(BLOCK_SIZE).chain([&[][..]]);
+
+    for (index, block) in (0u64..).zip(blocks) {
+        let length = (block.len() as u32).to_le_bytes();
+
+        let mut mac = block_mac(hmac_key, index);
+        mac.update(&index.to_le_bytes());
+        mac.update(&length);
+        mac.update(block);
+
+        output.extend_from_slice(&mac.finalize().into_bytes());
+        output.extend_from_slice(&length);
+        output.extend_from_slice(block);
+    }
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Reads little-endian values off a byte slice, failing on truncated input.
+struct Bytes<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Bytes<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn take(&mut self, length: usize) -> io::Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| invalid("The KDBX file is truncated"))?;
+
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(self.take(2)?);
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    /// Reads a header field: its type, then its data prefixed by its length.
+    fn field(&mut self) -> io::Result<(u8, &'a [u8])> {
+        let kind = self.u8()?;
+        let length = self.u32()? as usize;
+        Ok((kind, self.take(length)?))
+    }
+}
+
+/// The unencrypted outer header.
+#[derive(Default)]
+struct Header {
+    cipher: Vec<u8>,
+    compressed: bool,
+    master_seed: Vec<u8>,
+    iv: Vec<u8>,
+    kdf: HashMap<String, Vec<u8>>,
+}
+
+impl Header {
+    fn read(bytes: &mut Bytes) -> io::Result<Self> {
+        let mut header = Header::default();
+
+        loop {
+            match bytes.field()? {
+                (0, _) => break,
+                (2, cipher) => header.cipher = cipher.to_vec(),
+                (3, flags) => header.compressed = flags.first().is_some_and(|flag| *flag == 1),
+                (4, seed) => header.master_seed = seed.to_vec(),
+                (7, iv) => header.iv = iv.to_vec(),
+                (11, parameters) => header.kdf = read_variant_dictionary(parameters)?,
+                _ => {}
+            }
+        }
+
+        Ok(header)
+    }
+
+    fn decrypt(&self, key: &[u8; 32], mut data: Vec<u8>) -> io::Result<Vec<u8>> {
+        if self.cipher == CIPHER_AES256 {
+            return cbc::Decryptor::<Aes256>::new_from_slices(key, &self.iv)
+                .map_err(|_| invalid("Invalid AES initialization vector"))?
+                .decrypt_padded_vec_mut::<Pkcs7>(&data)
+                .map_err(|_| invalid("The KDBX content is corrupt"));
+        }
+
+        if self.cipher == CIPHER_CHACHA20 {
+            ChaCha20::new_from_slices(key, &self.iv)
+                .map_err(|_| invalid("Invalid ChaCha20 nonce"))?
+                .apply_keystream(&mut data);
+            return Ok(data);
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Only AES-256 and ChaCha20 encrypted KDBX databases are supported",
+        ))
+    }
+}
+
+/// Parses the typed key-value list holding the KDF parameters. Values are
+/// kept as raw bytes, the KDF knows their types.
+fn read_variant_dictionary(data: &[u8]) -> io::Result<HashMap<String, Vec<u8>>> {
+    let mut bytes = Bytes::new(data);
+    let mut items = HashMap::new();
+
+    if bytes.u16()? >> 8 != 1 {
+        return Err(invalid("Unknown KDF parameters version"));
+    }
+
+    while bytes.u8()? != 0 {
+        let name_length = bytes.u32()? as usize;
+        let name = String::from_utf8_lossy(bytes.take(name_length)?).into_owned();
+        let value_length = bytes.u32()? as usize;
+        items.insert(name, bytes.take(value_length)?.to_vec());
+    }
+
+    Ok(items)
+}
+
+/// Reads a UInt32 or UInt64 KDF parameter.
+fn kdf_number(kdf: &HashMap<String, Vec<u8>>, name: &str) -> io::Result<u64> {
+    match kdf.get(name).map(Vec::as_slice) {
+        Some(&[a, b, c, d]) => Ok(u32::from_le_bytes([a, b, c, d]).into()),
+        Some(&[a, b, c, d, e, f, g, h]) => Ok(u64::from_le_bytes([a, b, c, d, e, f, g, h])),
+        _ => Err(invalid(format!("Missing KDF parameter {name}"))),
+    }
+}
+
+fn write_variant_dictionary(items: &[(u8, &str, Vec<u8>)]) -> Vec<u8> {
+    let mut data = 0x0100u16.to_le_bytes().to_vec();
+
+    for (kind, name, value) in items {
+        data.push(*kind);
+        data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        data.extend_from_slice(name.as_bytes());
+        data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        data.extend_from_slice(value);
+    }
+
+    data.push(0);
+    data
+}
+
+fn kdf_bytes<'a>(kdf: &'a HashMap<String, Vec<u8>>, name: &str) -> io::Result<&'a [u8]> {
+    kdf.get(name)
+        .map(Vec::as_slice)
+        .ok_or_else(|| invalid(format!("Missing KDF parameter {name}")))
+}
+
+fn composite_key(password: &str) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(password.as_bytes())).into()
+}
+
+/// Derives the key of the content cipher and the base key of the HMACs.
+fn derive_keys(master_seed: &[u8], transformed: &[u8; 32]) -> ([u8; 32], [u8; 64]) {
+    let cipher_key = Sha256::new()
+        .chain_update(master_seed)
+        .chain_update(transformed)
+        .finalize()
+        .into();
+    let hmac_key = Sha512::new()
+        .chain_update(master_seed)
+        .chain_update(transformed)
+        .chain_update([1])
+        .finalize()
+        .into();
+
+    (cipher_key, hmac_key)
+}
+
+/// Derives the key the content is encrypted with from the composite key.
+fn transform_key(kdf: &HashMap<String, Vec<u8>>, key: &[u8; 32]) -> io::Result<[u8; 32]> {
+    let uuid = kdf_bytes(kdf, "$UUID")?;
+    let salt = kdf_bytes(kdf, "S")?;
+
+    if uuid == KDF_AES {
+        let cipher =
+            Aes256::new_from_slice(salt).map_err(|_| invalid("Invalid AES-KDF seed length"))?;
+        let mut blocks = [
+            Block::clone_from_slice(&key[..16]),
+            Block::clone_from_slice(&key[16..]),
+        ];
+
+        for _ in 0..kdf_number(kdf, "R")? {
+            cipher.encrypt_blocks(&mut blocks);
+        }
+
+        return Ok(Sha256::new()
+            .chain_update(blocks[0])
+            .chain_update(blocks[1])
+            .finalize()
+            .into());
+    }
+
+    let algorithm = match uuid {
+        uuid if uuid == KDF_ARGON2D => Algorithm::Argon2d,
+        uuid if uuid == KDF_ARGON2ID => Algorithm::Argon2id,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Unknown key derivation function",
+            ))
+        }
+    };
+
+    let version = u32::try_from(kdf_number(kdf, "V")?)
+        .ok()
+        .and_then(|version| Version::try_from(version).ok())
+        .ok_or_else(|| invalid("Unknown Argon2 version"))?;
+
+    /* The memory is given in bytes, Argon2 counts kibibytes */
+    let params = Params::new(
+        (kdf_number(kdf, "M")? / 1024) as u32,
+        kdf_number(kdf, "I")? as u32,
+        kdf_number(kdf, "P")? as u32,
+        Some(32),
+    )
+    .map_err(|e| invalid(format!("Invalid Argon2 parameters: {e}")))?;
+
+    let secret = kdf.get("K").map(Vec::as_slice).unwrap_or_default();
+    let argon2 = Argon2::new_with_secret(secret, algorithm, version, params)
+        .map_err(|e| invalid(format!("Invalid Argon2 parameters: {e}")))?;
+
+    let mut transformed = [0; 32];
+    argon2
+        .hash_password_into(key, salt, &mut transformed)
+        .map_err(|e| invalid(format!("Argon2 failed: {e}")))?;
+
+    Ok(transformed)
+}
+
+/// Returns the HMAC checking block `index`, or the header for `u64::MAX`.
+fn block_mac(hmac_key: &[u8; 64], index: u64) -> Hmac<Sha256> {
+    let key = Sha512::new()
+        .chain_update(index.to_le_bytes())
+        .chain_update(hmac_key)
+        .finalize();
+
+    <Hmac<Sha256> as Mac>::new_from_slice(&key).expect("HMAC takes keys of any length")
+}
+
+/// Reads the encrypted content, split in blocks each checked by an HMAC.
+fn read_blocks(bytes: &mut Bytes, hmac_key: &[u8; 64]) -> io::Result<Vec<u8>> {
+    let mut content = Vec::new();
+
+    for index in 0u64.. {
+        let hmac = bytes.take(32)?;
+        let length = bytes.u32()?;
+        let block = bytes.take(length as usize)?;
+
+        let mut mac = block_mac(hmac_key, index);
+        mac.update(&index.to_le_bytes());
+        mac.update(&length.to_le_bytes());
+        mac.update(block);
+        if mac.verify_slice(hmac).is_err() {
+            return Err(invalid(format!(
+                "Block {index} of the KDBX file is corrupt"
+            )));
+        }
+
+        if block.is_empty() {
+            break;
+        }
+        content.extend_from_slice(block);
+    }
+
+    Ok(content)
+}
+
+/// The cipher stream protected values of the XML are XORed with, in order.
+enum InnerStream {
+    None,
+    Salsa20(Box<Salsa20>),
+    ChaCha20(Box<ChaCha20>),
+}
+
+impl InnerStream {
+    /// Reads the inner header, before the XML, holding the stream and its key.
+    fn read(bytes: &mut Bytes) -> io::Result<Self> {
+        let mut id = STREAM_NONE;
+        let mut key: &[u8] = &[];
+
+        loop {
+            match bytes.field()? {
+                (0, _) => break,
+                (1, &[a, b, c, d]) => id = u32::from_le_bytes([a, b, c, d]),
+                (2, stream_key) => key = stream_key,
+                /* Attachments, pwds has no place for them */
+                _ => {}
+            }
+        }
+
+        Self::new(id, key)
+    }
+
+    fn new(id: u32, key: &[u8]) -> io::Result<Self> {
+        match id {
+            STREAM_NONE => Ok(InnerStream::None),
+            STREAM_SALSA20 => {
+                let key = Sha256::digest(key);
+                Ok(InnerStream::Salsa20(Box::new(Salsa20::new(
+                    &key,
+                    &SALSA20_NONCE.into(),
+                ))))
+            }
+            STREAM_CHACHA20 => {
+                let hash = Sha512::digest(key);
+                let stream = ChaCha20::new_from_slices(&hash[..32], &hash[32..44])
+                    .map_err(|_| invalid("Invalid inner stream key"))?;
+                Ok(InnerStream::ChaCha20(Box::new(stream)))
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("Unknown inner stream {id}"),
+            )),
+        }
+    }
+
+    fn unprotect(&mut self, text: &str) -> io::Result<String> {
+        let mut bytes = BASE64
+            .decode(text.trim())
+            .map_err(|_| invalid("Invalid protected value"))?;
+
+        match self {
+            InnerStream::None => {}
+            InnerStream::Salsa20(stream) => stream.apply_keystream(&mut bytes),
+            InnerStream::ChaCha20(stream) => stream.apply_keystream(&mut bytes),
+        }
+
+        String::from_utf8(bytes).map_err(|_| invalid("A protected value is not UTF-8"))
+    }
+
+    fn protect(&mut self, text: &str) -> String {
+        let mut bytes = text.as_bytes().to_vec();
+
+        match self {
+            InnerStream::None => {}
+            InnerStream::Salsa20(stream) => stream.apply_keystream(&mut bytes),
+            InnerStream::ChaCha20(stream) => stream.apply_keystream(&mut bytes),
+        }
+
+        BASE64.encode(bytes)
+    }
+}
+
+/// Builds the database from the XML, element by element.
+#[derive(Default)]
+struct XmlState {
+    groups: Vec<Group>,
+    records: Vec<Record>,
+    key: String,
+    value: String,
+    recycle_bin: Option<String>,
+    root: Option<Group>,
+}
+
+fn parse_xml(xml: &str, mut stream: InnerStream) -> io::Result<Database> {
+    let mut reader = Reader::from_str(xml);
+    let mut state = XmlState::default();
+    let mut path: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut protected = false;
+
+    let xml_error = |e: quick_xml::Error| invalid(format!("Invalid KDBX XML: {e}"));
+
+    loop {
+        let (start, end) = match reader.read_event().map_err(xml_error)? {
+            Event::Start(element) => (Some(element), false),
+            Event::Empty(element) => (Some(element), true),
+            Event::End(_) => (None, true),
+            Event::Text(content) => {
+                text.push_str(&content.unescape().map_err(xml_error)?);
+                continue;
+            }
+            Event::CData(content) => {
+                text.push_str(&String::from_utf8_lossy(&content));
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+
+        if let Some(element) = start {
+            let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+            protected = is_protected(&element);
+            state.start(&name);
+            path.push(name);
+            text.clear();
+        }
+
+        if end {
+            /* Protected values must be read in order, they share the stream */
+            if protected {
+                text = stream.unprotect(&text)?;
+            }
+
+            let name = path.pop().unwrap_or_default();
+            state.end(&name, &path, std::mem::take(&mut text));
+            protected = false;
+        }
+    }
+
+    match state.root {
+        Some(root) => Ok(Database { root }),
+        None => Err(invalid("The KDBX database has no root group")),
+    }
+}
+
+fn is_protected(element: &BytesStart) -> bool {
+    matches!(
+        element.try_get_attribute("Protected"),
+        Ok(Some(attribute)) if attribute.value.as_ref() == b"True"
+    )
+}
+
+/// Tells whether the innermost elements of `path` are `parents`.
+fn within(path: &[String], parents: &[&str]) -> bool {
+    path.len() >= parents.len()
+        && path[path.len() - parents.len()..]
+            .iter()
+            .zip(parents)
+            .all(|(element, parent)| element == parent)
+}
+
+impl XmlState {
+    fn start(&mut self, name: &str) {
+        match name {
+            "Group" => self.groups.push(Group::default()),
+            "Entry" => self.records.push(Record::default()),
+            "String" => {
+                self.key.clear();
+                self.value.clear();
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles the end of element `name` holding `text`, `path` leading to it.
+    fn end(&mut self, name: &str, path: &[String], text: String) {
+        let group = self.groups.last_mut();
+        let record = self.records.last_mut();
+
+        match (name, group, record) {
+            ("RecycleBinUUID", _, _) if within(path, &["Meta"]) => self.recycle_bin = Some(text),
+            ("RecycleBinEnabled", _, _) if within(path, &["Meta"]) && text != "True" => {
+                self.recycle_bin = None
+            }
+            ("Name", Some(group), _) if within(path, &["Group"]) => group.name = text,
+            ("UUID", Some(group), _) if within(path, &["Group"]) => group.uuid = text,
+            ("Key", _, Some(_)) if within(path, &["String"]) => self.key = text,
+            ("Value", _, Some(_)) if within(path, &["String"]) => self.value = text,
+            ("String", _, Some(record)) if within(path, &["Entry"]) => record.strings.push((
+                std::mem::take(&mut self.key),
+                std::mem::take(&mut self.value),
+            )),
+            ("Tags", _, Some(record)) if within(path, &["Entry"]) => {
+                record.tags = text
+                    .split([';', ','])
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .map(String::from)
+                    .collect()
+            }
+            ("LastModificationTime", _, Some(record)) if within(path, &["Entry", "Times"]) => {
+                record.modified = format_time(&text)
+            }
+            ("Entry", _, _) => {
+                let Some(record) = self.records.pop() else {
+                    return;
+                };
+
+                if within(path, &["History"]) {
+                    if let Some(current) = self.records.last_mut() {
+                        current.history.push(record);
+                    }
+                } else if let Some(group) = self.groups.last_mut() {
+                    group.entries.push(record);
+                }
+            }
+            ("Group", _, _) => {
+                let Some(group) = self.groups.pop() else {
+                    return;
+                };
+
+                if self.recycle_bin.as_ref() == Some(&group.uuid) {
+                    return;
+                }
+
+                match self.groups.last_mut() {
+                    Some(parent) => parent.groups.push(group),
+                    None => self.root = Some(group),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Formats a KDBX 4 time, base64 seconds since year 1, as `YYYY-MM-DD HH:MM`.
+/// Times in other formats are kept as they are.
+fn format_time(text: &str) -> String {
+    match BASE64.decode(text.trim()).as_deref() {
+        Ok(&[a, b, c, d, e, f, g, h]) => {
+            format_unix(i64::from_le_bytes([a, b, c, d, e, f, g, h]) - UNIX_EPOCH_SECONDS)
+        }
+        _ => text.to_string(),
+    }
+}
+
+/// Formats seconds since 1970-01-01 as `YYYY-MM-DD HH:MM` in UTC.
+fn format_unix(seconds: i64) -> String {
+    let (days, seconds) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+/// Parses a time formatted by `format_unix`.
+fn parse_time(text: &str) -> Option<i64> {
+    let (date, time) = text.split_once(' ')?;
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let (hours, minutes) = time.split_once(':')?;
+    let (hours, minutes) = (hours.parse::<i64>().ok()?, minutes.parse::<i64>().ok()?);
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hours > 23 || minutes > 59 {
+        return None;
+    }
+
+    Some(days_from_civil(year, month, day) * 86_400 + hours * 3600 + minutes * 60)
+}
+
+/// Converts a time formatted by `format_unix` to a KDBX 4 time, the
+/// current time when it cannot be parsed.
+fn kdbx_time(text: &str) -> String {
+    let seconds = parse_time(text).unwrap_or_else(now) + UNIX_EPOCH_SECONDS;
+    BASE64.encode(seconds.to_le_bytes())
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+/// Converts a proleptic Gregorian date to days since 1970-01-01.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let shifted_month = (month + 9) % 12;
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Converts days since 1970-01-01 to a proleptic Gregorian date.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
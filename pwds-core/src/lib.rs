@@ -0,0 +1,28 @@
+//! Storage and cryptography of pwds vaults, shared by the graphical,
+//! terminal and command-line interfaces and usable on its own:
+//!
+//! ```no_run
+//! use pwds_core::Vault;
+//!
+//! let vault = Vault::unlock("pwds.enc", "my key")?;
+//! for entry in vault.entry_list()? {
+//!     println!("{} {}", entry.username, entry.url);
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+pub mod bitwarden;
+pub mod crypto;
+pub mod csv;
+pub mod import;
+pub mod kdbx;
+pub mod otp;
+pub mod pass;
+pub mod plaintext;
+pub mod portable;
+pub mod pwds;
+pub mod search;
+pub mod vault;
+
+pub use pwds::Entry;
+pub use vault::Vault;
@@ -0,0 +1,425 @@
+//! One-time passwords: HOTP (RFC 4226) and TOTP (RFC 6238), configured by
+//! `otpauth://` URIs as shown in QR codes by most sites.
+//!
+//! ```
+//! use pwds_core::otp::{hotp, Algorithm, Kind, Otp};
+//!
+//! /* RFC 4226, appendix D */
+//! let secret = b"12345678901234567890";
+//! let codes: Vec<String> = (0..10)
+//!     .map(|counter| hotp(secret, counter, Algorithm::Sha1, 6))
+//!     .collect();
+//! assert_eq!(
+//!     codes,
+//!     [
+//!         "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583",
+//!         "399871", "520489",
+//!     ]
+//! );
+//!
+//! /* RFC 6238, appendix B, with the seed of each algorithm */
+//! let vectors = [
+//!     (
+//!         Algorithm::Sha1,
+//!         "12345678901234567890",
+//!         ["94287082", "07081804", "14050471", "89005924", "69279037", "65353130"],
+//!     ),
+//!     (
+//!         Algorithm::Sha256,
+//!         "12345678901234567890123456789012",
+//!         ["46119246", "68084774", "67062674", "91819424", "90698825", "77737706"],
+//!     ),
+//!     (
+//!         Algorithm::Sha512,
+//!         "1234567890123456789012345678901234567890123456789012345678901234",
+//!         ["90693936", "25091201", "99943326", "93441116", "38618901", "47863826"],
+//!     ),
+//! ];
+//! let times = [59, 1111111109, 1111111111, 1234567890, 2000000000, 20000000000];
+//!
+//! for (algorithm, seed, expected) in vectors {
+//!     let otp = Otp {
+//!         kind: Kind::Totp { period: 30 },
+//!         secret: seed.as_bytes().to_vec(),
+//!         algorithm,
+//!         digits: 8,
+//!         ..Default::default()
+//!     };
+//!     for (time, code) in times.iter().zip(expected) {
+//!         assert_eq!(otp.code(*time), code);
+//!     }
+//! }
+//!
+//! /* The same SHA-1 seed, as a site would hand it out */
+//! let otp = Otp::parse(
+//!     "otpauth://totp/Example:alice%40example.com?\
+//!      secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=Example&digits=8",
+//! )?;
+//! assert_eq!(otp.label, "Example:alice@example.com");
+//! assert_eq!(otp.code(59), "94287082");
+//! assert_eq!(otp.remaining(59), 1);
+//! assert_eq!(Otp::parse(&otp.to_uri())?, otp);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::digest::KeyInit;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// The hash of the HMAC, SHA-1 unless the URI says otherwise.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Algorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Sha1 => "SHA1",
+            Algorithm::Sha256 => "SHA256",
+            Algorithm::Sha512 => "SHA512",
+        }
+    }
+
+    /// Accepts the names of `otpauth://` URIs, ignoring case and dashes.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.replace('-', "").to_uppercase().as_str() {
+            "SHA1" => Some(Algorithm::Sha1),
+            "SHA256" => Some(Algorithm::Sha256),
+            "SHA512" => Some(Algorithm::Sha512),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// Codes change every `period` seconds.
+    Totp { period: u64 },
+    /// Codes change on every use. `counter` is the one of the next code,
+    /// see `Otp::advance`.
+    Hotp { counter: u64 },
+}
+
+impl Default for Kind {
+    fn default() -> Self {
+        Kind::Totp { period: 30 }
+    }
+}
+
+/// A one-time password generator, as stored in `Entry::otp`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Otp {
+    pub kind: Kind,
+    pub secret: Vec<u8>,
+    pub algorithm: Algorithm,
+    pub digits: u32,
+    /// Usually `Issuer:account`.
+    pub label: String,
+    pub issuer: String,
+}
+
+impl Default for Otp {
+    fn default() -> Self {
+        Self {
+            kind: Kind::default(),
+            secret: Vec::new(),
+            algorithm: Algorithm::default(),
+            digits: 6,
+            label: String::new(),
+            issuer: String::new(),
+        }
+    }
+}
+
+impl Otp {
+    /// Reads an `otpauth://totp/...` or `otpauth://hotp/...` URI. Fails
+    /// with `InvalidData` when the URI or one of its parameters is invalid.
+    pub fn parse(uri: &str) -> io::Result<Self> {
+        let uri = uri.trim();
+        let rest = uri
+            .get(..10)
+            .filter(|scheme| scheme.eq_ignore_ascii_case("otpauth://"))
+            .map(|_| &uri[10..])
+            .ok_or_else(|| invalid("Not an otpauth:// URI"))?;
+
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let (kind, label) = path.split_once('/').unwrap_or((path, ""));
+
+        let mut otp = Otp {
+            label: percent_decode(label)?,
+            ..Default::default()
+        };
+        let mut period = 30;
+        let mut counter = 0;
+
+        for parameter in query.split('&').filter(|parameter| !parameter.is_empty()) {
+            let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+            let value = percent_decode(value)?;
+
+            match name.to_lowercase().as_str() {
+                "secret" => otp.secret = base32_decode(&value)?,
+                "issuer" => otp.issuer = value,
+                "algorithm" => {
+                    otp.algorithm = Algorithm::from_name(&value)
+                        .ok_or_else(|| invalid(format!("Unsupported algorithm {value}")))?
+                }
+                "digits" => otp.digits = number(name, &value)?,
+                "period" => period = number(name, &value)?,
+                "counter" => counter = number(name, &value)?,
+                /* Like image, which some sites add */
+                _ => {}
+            }
+        }
+
+        otp.kind = match kind.to_lowercase().as_str() {
+            "totp" => Kind::Totp { period },
+            "hotp" => Kind::Hotp { counter },
+            _ => return Err(invalid(format!("Unknown one-time password type {kind}"))),
+        };
+
+        otp.check()?;
+        Ok(otp)
+    }
+
+    /// A TOTP generator with the usual settings, for the base32 secrets
+    /// some exports hold instead of a URI.
+    pub fn from_secret(secret: &str) -> io::Result<Self> {
+        let otp = Otp {
+            secret: base32_decode(secret)?,
+            ..Default::default()
+        };
+
+        otp.check()?;
+        Ok(otp)
+    }
+
+    /// Writes the URI `parse` reads back.
+    pub fn to_uri(&self) -> String {
+        let (kind, parameter) = match self.kind {
+            Kind::Totp { period } => ("totp", format!("period={period}")),
+            Kind::Hotp { counter } => ("hotp", format!("counter={counter}")),
+        };
+
+        let mut uri = format!(
+            "otpauth://{kind}/{}?secret={}",
+            percent_encode(&self.label),
+            base32_encode(&self.secret)
+        );
+        if !self.issuer.is_empty() {
+            uri.push_str(&format!("&issuer={}", percent_encode(&self.issuer)));
+        }
+        uri.push_str(&format!(
+            "&algorithm={}&digits={}&{parameter}",
+            self.algorithm.name(),
+            self.digits
+        ));
+
+        uri
+    }
+
+    /// The code at `time`, in seconds since the Unix epoch, for TOTP, or
+    /// the code of the counter for HOTP.
+    pub fn code(&self, time: u64) -> String {
+        let counter = match self.kind {
+            Kind::Totp { period } => time / period,
+            Kind::Hotp { counter } => counter,
+        };
+
+        hotp(&self.secret, counter, self.algorithm, self.digits)
+    }
+
+    /// Seconds the TOTP code at `time` is still valid, 0 for HOTP.
+    pub fn remaining(&self, time: u64) -> u64 {
+        match self.kind {
+            Kind::Totp { period } => period - time % period,
+            Kind::Hotp { .. } => 0,
+        }
+    }
+
+    /// Moves an HOTP generator to its next code, once the current one was
+    /// shown. The URI must then be stored again, so that no code is used
+    /// twice.
+    pub fn advance(&mut self) {
+        if let Kind::Hotp { counter } = &mut self.kind {
+            *counter += 1;
+        }
+    }
+
+    fn check(&self) -> io::Result<()> {
+        if self.secret.is_empty() {
+            return Err(invalid("The secret is missing"));
+        }
+
+        if !(6..=10).contains(&self.digits) {
+            return Err(invalid(format!(
+                "Codes of {} digits are not supported",
+                self.digits
+            )));
+        }
+
+        if self.kind == (Kind::Totp { period: 0 }) {
+            return Err(invalid("The period cannot be 0"));
+        }
+
+        Ok(())
+    }
+}
+
+/// The URI to store for what exports hold: an `otpauth://` URI, kept as
+/// is, or a bare base32 secret of the usual TOTP. `None` for anything else,
+/// like the secrets of Steam Guard.
+pub fn import_uri(text: &str) -> Option<String> {
+    let text = text.trim();
+
+    if Otp::parse(text).is_ok() {
+        return Some(text.to_string());
+    }
+
+    Otp::from_secret(text).ok().map(|otp| otp.to_uri())
+}
+
+/// Seconds since the Unix epoch, the time `Otp::code` expects.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// The HOTP code of `counter`, dynamically truncated as in RFC 4226 and
+/// padded with zeros to `digits`.
+pub fn hotp(secret: &[u8], counter: u64, algorithm: Algorithm, digits: u32) -> String {
+    let message = counter.to_be_bytes();
+    let hash = match algorithm {
+        Algorithm::Sha1 => hmac::<Hmac<Sha1>>(secret, &message),
+        Algorithm::Sha256 => hmac::<Hmac<Sha256>>(secret, &message),
+        Algorithm::Sha512 => hmac::<Hmac<Sha512>>(secret, &message),
+    };
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+
+    let code = u64::from(binary) % 10u64.pow(digits);
+    format!("{code:0width$}", width = digits as usize)
+}
+
+/// The HMAC of `message` keyed with `secret`, `M` being one of `Hmac<Sha1>`,
+/// `Hmac<Sha256>` or `Hmac<Sha512>`.
+fn hmac<M: Mac + KeyInit>(secret: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = <M as KeyInit>::new_from_slice(secret).expect("HMAC takes keys of any size");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Decodes base32 as in RFC 4648, ignoring case, spaces and padding.
+fn base32_decode(text: &str) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in text.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|letter| char::from(*letter) == c.to_ascii_uppercase())
+            .ok_or_else(|| invalid(format!("Invalid character {c:?} in the secret")))?;
+
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Ok(bytes)
+}
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for byte in bytes {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            text.push(char::from(BASE32_ALPHABET[(buffer >> bits) as usize & 31]));
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        text.push(char::from(
+            BASE32_ALPHABET[(buffer << (5 - bits)) as usize & 31],
+        ));
+    }
+
+    text
+}
+
+fn percent_decode(text: &str) -> io::Result<String> {
+    let mut bytes = Vec::new();
+    let mut rest = text.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        match byte {
+            b'%' => {
+                let value = tail
+                    .get(..2)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| invalid(format!("Invalid escape in {text}")))?;
+                bytes.push(value);
+                rest = &tail[2..];
+            }
+            b'+' => {
+                bytes.push(b' ');
+                rest = tail;
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+
+    String::from_utf8(bytes).map_err(|_| invalid(format!("Invalid text in {text}")))
+}
+
+/// Escapes everything but unreserved characters and the colon separating
+/// the issuer from the account in labels.
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b':' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn number<T: std::str::FromStr>(name: &str, value: &str) -> io::Result<T> {
+    value
+        .parse()
+        .map_err(|_| invalid(format!("Invalid {name} {value}")))
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
@@ -0,0 +1,192 @@
+//! Reading of `pass` password stores, each file being decrypted by a local
+//! `gpg`, which uses the keys and agent of the user or of `GNUPGHOME`.
+//!
+//! ```
+//! use std::process::Command;
+//!
+//! use pwds_core::pass;
+//!
+//! /* A throwaway GnuPG home holding the key of the fixtures */
+//! let home = std::env::temp_dir().join(format!("pwds-pass-{}", std::process::id()));
+//! std::fs::create_dir_all(&home)?;
+//! std::env::set_var("GNUPGHOME", &home);
+//! Command::new("gpg")
+//!     .args(["--batch", "--quiet", "--import", "fixtures/pass/key.asc"])
+//!     .status()?;
+//!
+//! let store = pass::open("fixtures/pass/store")?;
+//!
+//! let mail = store.entries.iter().find(|entry| entry.title == "Mail").unwrap();
+//! assert_eq!(mail.username, "jdoe@example.com");
+//! assert_eq!(mail.password, "Tr0ub4dor&3");
+//! assert_eq!(mail.tags, ["Internet/Email"]);
+//!
+//! let bank = store.entries.iter().find(|entry| entry.title == "Bank").unwrap();
+//! assert_eq!(bank.username, "jdoe");
+//!
+//! assert_eq!(store.failed.len(), 1);
+//! assert_eq!(store.failed[0].0, std::path::Path::new("Wi-Fi.gpg"));
+//! # Command::new("gpgconf").args(["--kill", "gpg-agent"]).status()?;
+//! # std::fs::remove_dir_all(&home)?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::otp::Otp;
+use crate::pwds::Entry;
+
+/// Keys of the lines holding the username, compared ignoring case.
+const USERNAME_KEYS: [&str; 4] = ["login", "username", "user", "email"];
+/// Keys of the lines holding the URL, compared ignoring case.
+const URL_KEYS: [&str; 3] = ["url", "website", "site"];
+
+/// The entries of a store.
+#[derive(Clone, Debug, Default)]
+pub struct Store {
+    pub entries: Vec<Entry>,
+    /// Files that could not be decrypted, relative to the store, with the
+    /// reason.
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+/// Decrypts every `.gpg` file under `dir`, hidden directories like `.git`
+/// excepted, see `to_entry`. Files `gpg` cannot decrypt, for example
+/// because they are encrypted for another key, are reported in `failed`.
+pub fn open(dir: impl AsRef<Path>) -> io::Result<Store> {
+    let dir = dir.as_ref();
+    let mut files = Vec::new();
+    find_files(dir, &mut files)?;
+
+    let mut store = Store::default();
+
+    for file in files {
+        let relative = file.strip_prefix(dir).unwrap_or(&file).to_path_buf();
+
+        match decrypt(&file) {
+            Ok(content) => store.entries.push(to_entry(&relative, &content)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(e),
+            Err(e) => store.failed.push((relative, e.to_string())),
+        }
+    }
+
+    Ok(store)
+}
+
+/// Converts a decrypted file, at `path` in the store. The first line is the
+/// password, and `key: value` lines give the username and URL, the keys
+/// being those of `pass` extensions and browser plugins. An `otpauth://`
+/// line, as written by `pass otp`, gives the one-time passwords. The other
+/// lines are kept in the notes. The directory becomes a tag like
+/// `Internet/Email` and the file name the title, but when no line gives
+/// the username it is the file name, following the `site/username`
+/// layout, and the title is the directory name.
+pub fn to_entry(path: &Path, content: &str) -> Entry {
+    let mut lines = content.lines();
+    let mut entry = Entry {
+        password: lines.next().unwrap_or_default().to_string(),
+        ..Default::default()
+    };
+
+    let mut notes = Vec::new();
+
+    for line in lines {
+        let (key, value) = line.split_once(": ").unwrap_or_default();
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+
+        if USERNAME_KEYS.contains(&key.as_str()) && entry.username.is_empty() {
+            entry.username = value.to_string();
+        } else if URL_KEYS.contains(&key.as_str()) && entry.url.is_empty() {
+            entry.url = value.to_string();
+        } else if entry.otp.is_empty() && Otp::parse(line).is_ok() {
+            entry.otp = line.trim().to_string();
+        } else {
+            notes.push(line);
+        }
+    }
+    entry.notes = notes.join("\n").trim().to_string();
+
+    let name = path
+        .file_stem()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let folder: Vec<String> = path
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect();
+
+    if entry.username.is_empty() {
+        entry.username = name;
+        entry.title = folder.last().cloned().unwrap_or_default();
+    } else {
+        entry.title = name;
+    }
+
+    if !folder.is_empty() {
+        entry.tags.push(folder.join("/"));
+    }
+
+    entry
+}
+
+/// Collects the `.gpg` files under `dir`, sorted.
+fn find_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|dir_entry| dir_entry.map(|dir_entry| dir_entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    paths.sort();
+
+    for path in paths {
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+
+        if hidden {
+            continue;
+        }
+
+        if path.is_dir() {
+            find_files(&path, files)?;
+        } else if path.extension().is_some_and(|extension| extension == "gpg") {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Decrypts a file with `gpg`, the way `pass` does. Fails with `NotFound`
+/// when `gpg` is not installed.
+fn decrypt(file: &Path) -> io::Result<String> {
+    let output = Command::new("gpg")
+        .args([
+            "--decrypt",
+            "--quiet",
+            "--yes",
+            "--batch",
+            "--use-agent",
+            "--",
+        ])
+        .arg(file)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| io::Error::new(e.kind(), format!("Cannot run gpg: {e}")))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        let reason = error.lines().last().unwrap_or("gpg failed");
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            reason.trim_start_matches("gpg: ").to_string(),
+        ));
+    }
+
+    String::from_utf8(output.stdout)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Not UTF-8 text"))
+}
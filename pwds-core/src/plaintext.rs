@@ -0,0 +1,189 @@
+//! Unencrypted exports of entries, for handing credentials to another
+//! system. Nothing protects the files but their permissions.
+//!
+//! ```
+//! use pwds_core::plaintext::{self, Format};
+//! use pwds_core::Entry;
+//!
+//! let entry = Entry {
+//!     title: "Mail".to_string(),
+//!     username: "jdoe".to_string(),
+//!     password: "hunter2".to_string(),
+//!     tags: vec!["work".to_string(), "email".to_string()],
+//!     ..Default::default()
+//! };
+//!
+//! let fields = ["username", "password", "tags"];
+//! let csv = plaintext::render(&[entry.clone()], &fields, Format::Csv)?;
+//! assert_eq!(csv, "username,password,tags\njdoe,hunter2,\"work,email\"\n");
+//!
+//! /* Fields keep the order they are given in */
+//! let json = plaintext::render(&[entry.clone()], &["password", "username"], Format::Json)?;
+//! assert_eq!(
+//!     json,
+//!     "[\n  {\n    \"password\": \"hunter2\",\n    \"username\": \"jdoe\"\n  }\n]\n"
+//! );
+//!
+//! let yaml = plaintext::render(&[entry], &fields, Format::Yaml)?;
+//! assert_eq!(
+//!     yaml,
+//!     "- username: \"jdoe\"\n  password: \"hunter2\"\n  tags: [\"work\", \"email\"]\n"
+//! );
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use crate::pwds::{Entry, FIELD_NAMES};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+    Yaml,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Csv, Format::Json, Format::Yaml];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Csv => "CSV",
+            Format::Json => "JSON",
+            Format::Yaml => "YAML",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+        }
+    }
+}
+
+/// Writes the given fields of every entry, in that order. Fields are named
+/// as in `FIELD_NAMES`. Tags are a list in JSON and YAML, and joined by
+/// commas in CSV like `Entry::field` does.
+pub fn render(entries: &[Entry], fields: &[&str], format: Format) -> io::Result<String> {
+    if fields.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "No field to export",
+        ));
+    }
+
+    if let Some(field) = fields.iter().find(|field| !FIELD_NAMES.contains(field)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unknown field {field}"),
+        ));
+    }
+
+    match format {
+        Format::Csv => render_csv(entries, fields),
+        Format::Json => render_json(entries, fields),
+        Format::Yaml => Ok(render_yaml(entries, fields)),
+    }
+}
+
+/// Writes `render` to `path`, a new file only its owner can read, or over
+/// an existing one with `overwrite`.
+pub fn save(
+    path: impl AsRef<Path>,
+    entries: &[Entry],
+    fields: &[&str],
+    format: Format,
+    overwrite: bool,
+) -> io::Result<()> {
+    let text = render(entries, fields, format)?;
+
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options.open(path)?;
+    /* The mode only applies to new files */
+    #[cfg(unix)]
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+
+    file.write_all(text.as_bytes())
+}
+
+fn render_csv(entries: &[Entry], fields: &[&str]) -> io::Result<String> {
+    let mut writer = ::csv::Writer::from_writer(Vec::new());
+    writer.write_record(fields)?;
+
+    for entry in entries {
+        writer.write_record(
+            fields
+                .iter()
+                .map(|field| entry.field(field).unwrap_or_default()),
+        )?;
+    }
+
+    let data = writer.into_inner().map_err(|e| e.into_error())?;
+    String::from_utf8(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn render_json(entries: &[Entry], fields: &[&str]) -> io::Result<String> {
+    let objects: Vec<Value> = entries
+        .iter()
+        .map(|entry| {
+            let members: Map<String, Value> = fields
+                .iter()
+                .map(|field| (field.to_string(), value(entry, field)))
+                .collect();
+            Value::Object(members)
+        })
+        .collect();
+
+    let mut text = serde_json::to_string_pretty(&objects).map_err(io::Error::other)?;
+    text.push('\n');
+    Ok(text)
+}
+
+/// The values are JSON strings, which YAML reads as double-quoted scalars.
+fn render_yaml(entries: &[Entry], fields: &[&str]) -> String {
+    if entries.is_empty() {
+        return "[]\n".to_string();
+    }
+
+    let mut text = String::new();
+    for entry in entries {
+        for (index, field) in fields.iter().enumerate() {
+            let indent = if index == 0 { "- " } else { "  " };
+            let value = match value(entry, field) {
+                Value::Array(items) => {
+                    let items: Vec<String> = items.iter().map(Value::to_string).collect();
+                    format!("[{}]", items.join(", "))
+                }
+                value => value.to_string(),
+            };
+            text.push_str(&format!("{indent}{field}: {value}\n"));
+        }
+    }
+    text
+}
+
+/// A field as a JSON value: a string, or a list of strings for the tags.
+fn value(entry: &Entry, field: &str) -> Value {
+    if field == "tags" {
+        return Value::from(entry.tags.clone());
+    }
+
+    Value::from(entry.field(field).unwrap_or_default())
+}
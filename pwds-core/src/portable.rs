@@ -0,0 +1,258 @@
+//! Encrypted JSON exports of a vault. They are protected by a passphrase of
+//! their own and do not depend on the format of the database, so that any
+//! later pwds can import them.
+//!
+//! ```
+//! use pwds_core::{portable, Entry};
+//!
+//! let entry = Entry {
+//!     title: "Mail".to_string(),
+//!     username: "jdoe".to_string(),
+//!     password: "hunter2".to_string(),
+//!     ..Default::default()
+//! };
+//!
+//! let path = std::env::temp_dir().join(format!("pwds-export-{}.json", std::process::id()));
+//! portable::save(&path, &[entry], "passphrase")?;
+//!
+//! let entries = portable::open(&path, "passphrase")?;
+//! assert_eq!(entries[0].password, "hunter2");
+//!
+//! let wrong = portable::open(&path, "guess").unwrap_err();
+//! assert_eq!(wrong.kind(), std::io::ErrorKind::PermissionDenied);
+//! # std::fs::remove_file(&path)?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::pwds::Entry;
+
+const FORMAT: &str = "pwds-export";
+/// Version of the exports written by `save`. `open` reads this one and
+/// every earlier one.
+pub const VERSION: u32 = 1;
+
+const KDF_ARGON2ID: &str = "argon2id";
+const CIPHER_AES256_GCM: &str = "aes-256-gcm";
+
+/* Argon2id settings of new exports, the memory in kibibytes */
+const ARGON2_MEMORY: u32 = 64 * 1024;
+const ARGON2_ITERATIONS: u32 = 3;
+const ARGON2_PARALLELISM: u32 = 1;
+
+/// The file: everything needed to derive the key, in clear, and the
+/// encrypted `Content`.
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    format: String,
+    version: u32,
+    kdf: Kdf,
+    cipher: String,
+    nonce: String,
+    data: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Kdf {
+    algorithm: String,
+    salt: String,
+    memory: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Content {
+    /// Seconds since the Unix epoch.
+    created: u64,
+    generator: String,
+    entries: Vec<ExportedEntry>,
+}
+
+/// An entry as written in exports, kept apart from `Entry` so that the
+/// format only changes with `VERSION`.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ExportedEntry {
+    id: String,
+    title: String,
+    username: String,
+    password: String,
+    url: String,
+    tags: Vec<String>,
+    notes: String,
+    /// Missing from the exports of older pwds.
+    otp: String,
+}
+
+/// Writes `entries` to `path`, encrypted with a key derived from
+/// `passphrase` by Argon2id.
+pub fn save(path: impl AsRef<Path>, entries: &[Entry], passphrase: &str) -> io::Result<()> {
+    let content = Content {
+        created: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs()),
+        generator: format!("pwds {}", env!("CARGO_PKG_VERSION")),
+        entries: entries
+            .iter()
+            .map(|entry| ExportedEntry {
+                id: entry.id.clone(),
+                title: entry.title.clone(),
+                username: entry.username.clone(),
+                password: entry.password.clone(),
+                url: entry.url.clone(),
+                tags: entry.tags.clone(),
+                notes: entry.notes.clone(),
+                otp: entry.otp.clone(),
+            })
+            .collect(),
+    };
+    let plaintext = serde_json::to_vec(&content).map_err(io::Error::other)?;
+
+    let kdf = Kdf {
+        algorithm: KDF_ARGON2ID.to_string(),
+        salt: BASE64.encode(rand::random::<[u8; 32]>()),
+        memory: ARGON2_MEMORY,
+        iterations: ARGON2_ITERATIONS,
+        parallelism: ARGON2_PARALLELISM,
+    };
+    let key = derive_key(&kdf, passphrase)?;
+    let nonce: [u8; 12] = rand::random();
+
+    let data = Aes256Gcm::new(&key)
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &plaintext,
+                aad: associated_data(VERSION).as_bytes(),
+            },
+        )
+        .map_err(|_| io::Error::other("Encryption failed"))?;
+
+    let envelope = Envelope {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        kdf,
+        cipher: CIPHER_AES256_GCM.to_string(),
+        nonce: BASE64.encode(nonce),
+        data: BASE64.encode(data),
+    };
+
+    fs::write(
+        path,
+        serde_json::to_vec_pretty(&envelope).map_err(io::Error::other)?,
+    )
+}
+
+/// Reads the entries of an export made by `save`. Fails with
+/// `PermissionDenied` when the passphrase is wrong, `Unsupported` for
+/// exports of a later pwds, and `InvalidData` when the file is damaged.
+pub fn open(path: impl AsRef<Path>, passphrase: &str) -> io::Result<Vec<Entry>> {
+    let envelope: Envelope =
+        serde_json::from_slice(&fs::read(path)?).map_err(|_| invalid("Not a pwds export"))?;
+
+    if envelope.format != FORMAT {
+        return Err(invalid("Not a pwds export"));
+    }
+
+    if envelope.version > VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "Export version {} needs a later pwds, this one reads up to version {VERSION}",
+                envelope.version
+            ),
+        ));
+    }
+
+    if envelope.cipher != CIPHER_AES256_GCM {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Unsupported cipher {}", envelope.cipher),
+        ));
+    }
+
+    let key = derive_key(&envelope.kdf, passphrase)?;
+    let nonce = decode(&envelope.nonce)?;
+    if nonce.len() != 12 {
+        return Err(invalid("Invalid nonce"));
+    }
+
+    let plaintext = Aes256Gcm::new(&key)
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &decode(&envelope.data)?,
+                aad: associated_data(envelope.version).as_bytes(),
+            },
+        )
+        .map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "Wrong passphrase, or damaged export",
+            )
+        })?;
+
+    let content: Content =
+        serde_json::from_slice(&plaintext).map_err(|e| invalid(format!("Invalid content: {e}")))?;
+
+    Ok(content
+        .entries
+        .into_iter()
+        .map(|entry| Entry {
+            id: entry.id,
+            title: entry.title,
+            username: entry.username,
+            password: entry.password,
+            url: entry.url,
+            tags: entry.tags,
+            notes: entry.notes,
+            otp: entry.otp,
+        })
+        .collect())
+}
+
+fn derive_key(kdf: &Kdf, passphrase: &str) -> io::Result<Key<Aes256Gcm>> {
+    if kdf.algorithm != KDF_ARGON2ID {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Unsupported KDF {}", kdf.algorithm),
+        ));
+    }
+
+    let params = Params::new(kdf.memory, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| invalid(format!("Invalid Argon2 parameters: {e}")))?;
+
+    let mut key = Key::<Aes256Gcm>::default();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &decode(&kdf.salt)?, &mut key)
+        .map_err(|e| invalid(format!("Argon2 failed: {e}")))?;
+
+    Ok(key)
+}
+
+/// Authenticated with the content, so that the version cannot be changed.
+fn associated_data(version: u32) -> String {
+    format!("{FORMAT} {version}")
+}
+
+fn decode(text: &str) -> io::Result<Vec<u8>> {
+    BASE64
+        .decode(text)
+        .map_err(|e| invalid(format!("Invalid base64: {e}")))
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
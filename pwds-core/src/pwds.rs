@@ -0,0 +1,254 @@
+use crate::import::Import;
+use crate::otp::Otp;
+use crate::vault::{self, Vault};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+
+/// The database the frontends use, in the current directory. The functions
+/// below work on it; use `Vault` for any other database.
+pub const DB_PATH: &str = "pwds.enc";
+
+/// Names accepted by `Entry::field`.
+pub const FIELD_NAMES: [&str; 8] = [
+    "id", "title", "username", "password", "url", "tags", "notes", "otp",
+];
+
+/// A decrypted credential together with its optional metadata.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: String,
+    pub title: String,
+    pub username: String,
+    pub password: String,
+    pub url: String,
+    pub tags: Vec<String>,
+    pub notes: String,
+    /// An `otpauth://` URI, see `Otp`, empty when the entry has no
+    /// one-time passwords.
+    pub otp: String,
+}
+
+impl Entry {
+    /// Returns the fields that can be searched, the secrets excluded.
+    pub fn searchable_fields(&self) -> Vec<&str> {
+        let mut fields = vec![
+            self.title.as_str(),
+            self.username.as_str(),
+            self.url.as_str(),
+        ];
+        fields.extend(self.tags.iter().map(String::as_str));
+        fields.push(self.notes.as_str());
+        fields
+    }
+
+    /// Returns a field by name, the tags joined by commas.
+    pub fn field(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "id" => &self.id,
+            "title" => &self.title,
+            "username" => &self.username,
+            "password" => &self.password,
+            "url" => &self.url,
+            "tags" => return Some(self.tags.join(",")),
+            "notes" => &self.notes,
+            "otp" => &self.otp,
+            _ => return None,
+        };
+
+        Some(value.clone())
+    }
+
+    /// Checks that the entry can be stored in the line based database.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.username.is_empty() || self.password.is_empty() {
+            return Err("Please fill in both username and password fields.".to_string());
+        }
+
+        if self.username.contains(':') || self.username.contains('\n') {
+            return Err("The username cannot contain ':' or line breaks.".to_string());
+        }
+
+        if self.url.chars().any(char::is_whitespace) {
+            return Err("The URL cannot contain spaces.".to_string());
+        }
+
+        if !self.otp.is_empty() {
+            Otp::parse(&self.otp).map_err(|e| format!("Invalid one-time password URI: {e}."))?;
+        }
+
+        Ok(())
+    }
+
+    /// Two entries are duplicates when they share username and URL.
+    pub fn is_duplicate_of(&self, other: &Entry) -> bool {
+        self.id != other.id && self.is_same_login(other)
+    }
+
+    /// Whether both entries have the same username and URL, whatever their
+    /// ids, as an entry and its edited version.
+    pub fn is_same_login(&self, other: &Entry) -> bool {
+        self.username == other.username
+            && self.url.trim_end_matches('/') == other.url.trim_end_matches('/')
+    }
+
+    /// Merges `other` into this entry: non-empty fields of `other` win,
+    /// tags are joined and differing notes are appended. The id is kept.
+    pub fn merge(&mut self, other: &Entry) {
+        for (field, value) in [
+            (&mut self.title, &other.title),
+            (&mut self.password, &other.password),
+            (&mut self.url, &other.url),
+            (&mut self.otp, &other.otp),
+        ] {
+            if !value.is_empty() {
+                *field = value.clone();
+            }
+        }
+
+        for tag in &other.tags {
+            if !self.tags.contains(tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+
+        if !other.notes.is_empty() && !self.notes.contains(other.notes.as_str()) {
+            if !self.notes.is_empty() {
+                self.notes.push('\n');
+            }
+            self.notes.push_str(&other.notes);
+        }
+    }
+}
+
+/// Splits a comma separated list of tags, dropping empty ones.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Generates a new random entry id.
+pub fn new_id() -> String {
+    let bytes: [u8; 8] = rand::thread_rng().gen();
+    hex::encode(bytes)
+}
+
+fn default_vault(key: &str) -> io::Result<Vault> {
+    Vault::new(DB_PATH, key)
+}
+
+/// Checks if the encrypted password database file exists.
+pub fn is_db_file() -> bool {
+    Path::new(DB_PATH).exists()
+}
+
+/// See `Vault::verify_key`.
+pub fn verify_key(key: &str) -> io::Result<bool> {
+    default_vault(key)?.verify_key()
+}
+
+/// Overwrites the database file with zeros, then deletes it.
+pub fn wipe_db() -> io::Result<()> {
+    vault::wipe(Path::new(DB_PATH))
+}
+
+/// Encrypts and saves a password to the database file.
+/// save_password("user1", "password123", "your-encryption-key");
+pub fn save_password(username: &str, password: &str, key: &str) -> io::Result<()> {
+    let entry = Entry {
+        username: username.to_string(),
+        password: password.to_string(),
+        ..Default::default()
+    };
+
+    save_entry(&entry, key).map(|_| ())
+}
+
+/// Returns the ids of the stored entries, which are not encrypted.
+pub fn entry_ids() -> io::Result<Vec<String>> {
+    vault::entry_ids(Path::new(DB_PATH))
+}
+
+/// See `Vault::add`.
+pub fn save_entry(entry: &Entry, key: &str) -> io::Result<String> {
+    default_vault(key)?.add(entry)
+}
+
+/// See `Vault::update`.
+pub fn update_entry(id: &str, entry: &Entry, key: &str) -> io::Result<()> {
+    default_vault(key)?.update(id, entry)
+}
+
+/// See `Vault::find_duplicate`.
+pub fn find_duplicate(entry: &Entry, key: &str) -> io::Result<Option<Entry>> {
+    default_vault(key)?.find_duplicate(entry)
+}
+
+/// See `Vault::find_by_username`.
+pub fn find_by_username(username: &str, key: &str) -> io::Result<Vec<Entry>> {
+    default_vault(key)?.find_by_username(username)
+}
+
+/// See `Vault::entry`.
+pub fn get_entry(id: &str, key: &str) -> io::Result<Option<Entry>> {
+    default_vault(key)?.entry(id)
+}
+
+/// See `Vault::entries`.
+pub fn load_entries(key: &str) -> io::Result<Vec<Entry>> {
+    default_vault(key)?.entries()
+}
+
+/// See `Vault::entry_list`.
+pub fn load_entry_list(key: &str) -> io::Result<Vec<Entry>> {
+    default_vault(key)?.entry_list()
+}
+
+/// See `Vault::password`.
+pub fn get_password(id: &str, key: &str) -> io::Result<Option<String>> {
+    default_vault(key)?.password(id)
+}
+
+/// See `Vault::check`.
+pub fn check_vault(key: &str) -> io::Result<()> {
+    default_vault(key)?.check()
+}
+
+/// See `Vault::remove`.
+pub fn remove_password(id: &str, key: &str) -> io::Result<()> {
+    default_vault(key)?.remove(id)
+}
+
+/// See `Vault::otp`.
+pub fn get_otp(id: &str, key: &str) -> io::Result<Option<String>> {
+    default_vault(key)?.otp(id)
+}
+
+/// Returns the ids of the entries with one-time passwords.
+pub fn otp_ids() -> io::Result<Vec<String>> {
+    vault::otp_ids(Path::new(DB_PATH))
+}
+
+/// See `Vault::set_otp`.
+pub fn set_otp(id: &str, uri: &str, key: &str) -> io::Result<()> {
+    default_vault(key)?.set_otp(id, uri)
+}
+
+/// See `Vault::set_password`.
+pub fn modify_password(id: &str, new_password: &str, key: &str) -> io::Result<()> {
+    default_vault(key)?.set_password(id, new_password)
+}
+
+/// See `Import::apply`.
+pub fn apply_import(import: &Import, key: &str) -> io::Result<()> {
+    import.apply(&default_vault(key)?)
+}
+
+/// See `Vault::rekey`.
+pub fn rekey(key: &str, new_key: &str) -> io::Result<()> {
+    default_vault(key)?.rekey(new_key)
+}
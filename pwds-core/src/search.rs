@@ -0,0 +1,55 @@
+use crate::pwds::Entry;
+
+const MATCH_SCORE: i32 = 1;
+const CONSECUTIVE_BONUS: i32 = 4;
+const WORD_START_BONUS: i32 = 6;
+
+/// Scores `needle` as a case-insensitive subsequence of `haystack`.
+/// Returns `None` when not every character of the needle is found in order.
+pub fn fuzzy_score(needle: &str, haystack: &str) -> Option<i32> {
+    let haystack: Vec<char> = haystack.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for needle_char in needle.to_lowercase().chars() {
+        let found = haystack[position..]
+            .iter()
+            .position(|&c| c == needle_char)?
+            + position;
+
+        score += MATCH_SCORE;
+
+        if previous_match.is_some_and(|previous| previous + 1 == found) {
+            score += CONSECUTIVE_BONUS;
+        }
+
+        if found == 0 || !haystack[found - 1].is_alphanumeric() {
+            score += WORD_START_BONUS;
+        }
+
+        previous_match = Some(found);
+        position = found + 1;
+    }
+
+    Some(score)
+}
+
+/// Scores an entry against a whitespace separated query.
+/// Every word has to match at least one field; the best field counts.
+pub fn entry_score(query: &str, entry: &Entry) -> Option<i32> {
+    let fields = entry.searchable_fields();
+
+    query.split_whitespace().try_fold(0, |total, word| {
+        fields
+            .iter()
+            .filter_map(|field| fuzzy_score(word, field))
+            .max()
+            .map(|score| total + score)
+    })
+}
+
+/// Returns true when the entry matches the query. An empty query matches everything.
+pub fn matches(query: &str, entry: &Entry) -> bool {
+    entry_score(query, entry).is_some()
+}
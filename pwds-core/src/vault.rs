@@ -0,0 +1,614 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::crypto::{adjust_password_length, Cipher, CryptoManager};
+use crate::pwds::{new_id, parse_tags, Entry};
+
+/// Number of encrypted columns between the username and the id.
+const ENCRYPTED_COLUMNS: usize = 5;
+/// The encrypted `otpauth://` URI follows the id, on lines that have one.
+const OTP_COLUMN: usize = ENCRYPTED_COLUMNS + 1;
+
+/// A database file together with the key encrypting it.
+///
+/// Each line of the file is `username:password:title:url:tags:notes:id`,
+/// followed by `:otp` for entries with one-time passwords, every column but
+/// the username and the id being encrypted on its own.
+/// Nothing is cached: every call reads the file, and every change rewrites
+/// it, so that several processes can share a vault.
+#[derive(Clone)]
+pub struct Vault {
+    path: PathBuf,
+    key: String,
+}
+
+impl Vault {
+    /// Uses `key` as is, failing with `InvalidInput` unless it is 32 bytes
+    /// long, see `adjust_password_length`. The key is not checked against
+    /// the database, see `unlock`.
+    pub fn new(path: impl Into<PathBuf>, key: impl Into<String>) -> io::Result<Self> {
+        let key = key.into();
+        check_key_length(&key)?;
+
+        Ok(Self {
+            path: path.into(),
+            key,
+        })
+    }
+
+    /// Opens the vault at `path` with a key as typed by the user. Fails with
+    /// `NotFound` when there is no database and `PermissionDenied` when the
+    /// key does not decrypt it.
+    pub fn unlock(path: impl Into<PathBuf>, key: &str) -> io::Result<Self> {
+        let vault = Self::new(path, adjust_password_length(key))?;
+
+        if !vault.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No database at {}", vault.path.display()),
+            ));
+        }
+
+        if !vault.verify_key()? {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "Wrong encryption key",
+            ));
+        }
+
+        Ok(vault)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    fn cipher(&self) -> Cipher {
+        Cipher::new(&self.key)
+    }
+
+    /// Checks the key against the stored entries: it is right when it decrypts
+    /// at least one of them. An empty database accepts any key.
+    pub fn verify_key(&self) -> io::Result<bool> {
+        let cipher = self.cipher();
+
+        let enc_passwords = read_lines(&self.path)?;
+
+        if enc_passwords.is_empty() {
+            return Ok(true);
+        }
+
+        Ok(enc_passwords.iter().any(|(_, encrypted)| {
+            let encrypted_pwd = encrypted.split(':').next().unwrap_or_default();
+            cipher.decrypt(encrypted_pwd.to_string()).is_ok()
+        }))
+    }
+
+    /// Checks that every entry decrypts, which the key should be verified for
+    /// first, see `verify_key`. Fails with `InvalidData` naming the first
+    /// entry that does not.
+    pub fn check(&self) -> io::Result<()> {
+        let cipher = self.cipher();
+
+        let enc_passwords = read_lines(&self.path)?;
+        let count = enc_passwords.len();
+
+        let broken: Vec<String> = enc_passwords
+            .into_iter()
+            .filter(|(_, encrypted)| decrypt_columns(&cipher, encrypted, true).is_err())
+            .map(|(username, _)| username)
+            .collect();
+
+        match broken.first() {
+            None => Ok(()),
+            Some(username) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} of {} entries cannot be decrypted, starting with {}",
+                    broken.len(),
+                    count,
+                    username
+                ),
+            )),
+        }
+    }
+
+    /// Loads and decrypts every entry, metadata included.
+    /// Lines written before metadata existed only carry a username and password.
+    /// Fails with `InvalidData` when an entry does not decrypt, see `check`.
+    pub fn entries(&self) -> io::Result<Vec<Entry>> {
+        self.decrypt_entries(true)
+    }
+
+    /// Loads every entry with its metadata but leaves the passwords and
+    /// `otpauth://` URIs empty, so that a listing never holds secrets. See
+    /// `password`.
+    pub fn entry_list(&self) -> io::Result<Vec<Entry>> {
+        self.decrypt_entries(false)
+    }
+
+    fn decrypt_entries(&self, with_password: bool) -> io::Result<Vec<Entry>> {
+        let cipher = self.cipher();
+
+        let mut entries = Vec::new();
+
+        for (username, encrypted) in read_lines(&self.path)? {
+            let mut columns = decrypt_columns(&cipher, &encrypted, with_password)
+                .map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Cannot decrypt the entry of {username}: {e}"),
+                    )
+                })?
+                .into_iter();
+
+            let password = columns.next().unwrap_or_default();
+            let title = columns.next().unwrap_or_default();
+            let url = columns.next().unwrap_or_default();
+            let tags = columns
+                .next()
+                .map(|tags| parse_tags(&tags))
+                .unwrap_or_default();
+            let notes = columns.next().unwrap_or_default();
+            let otp = columns.next().unwrap_or_default();
+
+            entries.push(Entry {
+                id: line_id(&encrypted).to_string(),
+                title,
+                username,
+                password,
+                url,
+                tags,
+                notes,
+                otp,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    /// Loads and decrypts the entry with the given id.
+    pub fn entry(&self, id: &str) -> io::Result<Option<Entry>> {
+        Ok(self.entries()?.into_iter().find(|entry| entry.id == id))
+    }
+
+    /// Decrypts only the password of the entry with the given id.
+    pub fn password(&self, id: &str) -> io::Result<Option<String>> {
+        let encrypted_pwd = read_lines(&self.path)?
+            .into_iter()
+            .find(|(_, encrypted)| line_id(encrypted) == id)
+            .map(|(_, encrypted)| encrypted.split(':').next().unwrap_or_default().to_string());
+
+        match encrypted_pwd {
+            Some(encrypted_pwd) => self
+                .cipher()
+                .decrypt(encrypted_pwd)
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            None => Ok(None),
+        }
+    }
+
+    /// Decrypts only the `otpauth://` URI of the entry with the given id,
+    /// `None` when there is no such entry or it has no one-time passwords.
+    pub fn otp(&self, id: &str) -> io::Result<Option<String>> {
+        let encrypted_otp = read_lines(&self.path)?
+            .into_iter()
+            .find(|(_, encrypted)| line_id(encrypted) == id)
+            .and_then(|(_, encrypted)| {
+                encrypted
+                    .split(':')
+                    .nth(OTP_COLUMN)
+                    .filter(|column| !column.is_empty())
+                    .map(String::from)
+            });
+
+        match encrypted_otp {
+            Some(encrypted_otp) => self
+                .cipher()
+                .decrypt(encrypted_otp)
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the stored entry the given one would duplicate, if any.
+    pub fn find_duplicate(&self, entry: &Entry) -> io::Result<Option<Entry>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .find(|stored| entry.is_duplicate_of(stored)))
+    }
+
+    /// Loads every entry stored under `username`, usually a single one.
+    pub fn find_by_username(&self, username: &str) -> io::Result<Vec<Entry>> {
+        Ok(self
+            .entry_list()?
+            .into_iter()
+            .filter(|entry| entry.username == username)
+            .collect())
+    }
+
+    /// Encrypts and saves an entry, metadata included.
+    /// A new id is generated when the entry has none; the id used is returned.
+    pub fn add(&self, entry: &Entry) -> io::Result<String> {
+        let mut ids = self.add_all(std::slice::from_ref(entry))?;
+        Ok(ids.remove(0))
+    }
+
+    /// Saves several entries like `add`, writing the file once.
+    pub fn add_all(&self, entries: &[Entry]) -> io::Result<Vec<String>> {
+        self.save_all(&[], entries)
+    }
+
+    /// Replaces the entry with the given id, keeping the id.
+    /// Fails with `NotFound` if there is no such entry and with `AlreadyExists`
+    /// if a new username or URL would duplicate another entry, see
+    /// `Entry::is_duplicate_of`. Duplicates kept on purpose stay editable as
+    /// long as their username and URL do not change.
+    ///
+    /// ```
+    /// use pwds_core::pwds::Entry;
+    /// use pwds_core::vault::Vault;
+    ///
+    /// let path = std::env::temp_dir().join(format!("pwds-update-{}.enc", std::process::id()));
+    /// let vault = Vault::new(&path, "0".repeat(32))?;
+    ///
+    /// let entry = Entry {
+    ///     username: "alice".to_string(),
+    ///     password: "secret".to_string(),
+    ///     url: "https://example.com".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let ids = vault.add_all(&[entry.clone(), entry.clone()])?;
+    ///
+    /// let renamed = Entry { title: "Work".to_string(), ..entry.clone() };
+    /// vault.update(&ids[0], &renamed)?;
+    ///
+    /// let other = vault.add(&Entry { username: "bob".to_string(), ..entry })?;
+    /// let error = vault.update(&other, &renamed).unwrap_err();
+    /// assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+    ///
+    /// std::fs::remove_file(&path)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn update(&self, id: &str, entry: &Entry) -> io::Result<()> {
+        let mut entry = entry.clone();
+        entry.id = id.to_string();
+
+        self.save_all(std::slice::from_ref(&entry), &[]).map(|_| ())
+    }
+
+    /// Replaces the `updated` entries like `update`, matched by id, and adds
+    /// the `added` ones like `add_all`, writing the file once. Nothing is
+    /// written when one of the updates fails. Returns the ids of the added
+    /// entries.
+    pub fn save_all(&self, updated: &[Entry], added: &[Entry]) -> io::Result<Vec<String>> {
+        if !updated.is_empty() {
+            self.check_updates(updated)?;
+        }
+
+        let mut enc_passwords = read_lines(&self.path)?;
+        let cipher = self.cipher();
+
+        for entry in updated {
+            let Some(stored) = enc_passwords
+                .iter_mut()
+                .find(|(_, encrypted)| line_id(encrypted) == entry.id)
+            else {
+                return Err(no_such_entry(&entry.id));
+            };
+
+            *stored = (entry.username.clone(), encrypt_entry(entry, &cipher));
+        }
+
+        let mut ids = Vec::with_capacity(added.len());
+
+        for entry in added {
+            let mut entry = entry.clone();
+            if entry.id.is_empty() {
+                entry.id = new_id();
+            }
+
+            enc_passwords.push((entry.username.clone(), encrypt_entry(&entry, &cipher)));
+            ids.push(entry.id);
+        }
+
+        write_lines(&self.path, &enc_passwords)?;
+
+        Ok(ids)
+    }
+
+    /// Fails like `update` when one of `updated` is not stored, or when its
+    /// username or URL changes to those of another entry.
+    fn check_updates(&self, updated: &[Entry]) -> io::Result<()> {
+        let stored = self.entries()?;
+
+        let after: Vec<&Entry> = stored
+            .iter()
+            .map(|entry| {
+                updated
+                    .iter()
+                    .find(|update| update.id == entry.id)
+                    .unwrap_or(entry)
+            })
+            .collect();
+
+        for entry in updated {
+            let Some(before) = stored.iter().find(|stored| stored.id == entry.id) else {
+                return Err(no_such_entry(&entry.id));
+            };
+
+            if entry.is_same_login(before) {
+                continue;
+            }
+
+            if let Some(duplicate) = after.iter().find(|other| entry.is_duplicate_of(other)) {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!(
+                        "An entry for {} at {} already exists",
+                        duplicate.username, duplicate.url
+                    ),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Changes only the password of the entry with the given id.
+    pub fn set_password(&self, id: &str, new_password: &str) -> io::Result<()> {
+        let mut enc_passwords = read_lines(&self.path)?;
+
+        let Some((_, encrypted)) = enc_passwords
+            .iter_mut()
+            .find(|(_, encrypted)| line_id(encrypted) == id)
+        else {
+            return Err(no_such_entry(id));
+        };
+
+        let mut columns: Vec<String> = encrypted.split(':').map(String::from).collect();
+        columns[0] = self.cipher().encrypt(new_password.to_string());
+        *encrypted = columns.join(":");
+
+        write_lines(&self.path, &enc_passwords)
+    }
+
+    /// Changes only the `otpauth://` URI of the entry with the given id, as
+    /// when the counter of an HOTP generator moves on. An empty `uri`
+    /// removes it.
+    pub fn set_otp(&self, id: &str, uri: &str) -> io::Result<()> {
+        let mut enc_passwords = read_lines(&self.path)?;
+
+        let Some((_, encrypted)) = enc_passwords
+            .iter_mut()
+            .find(|(_, encrypted)| line_id(encrypted) == id)
+        else {
+            return Err(no_such_entry(id));
+        };
+
+        let mut columns: Vec<String> = encrypted.split(':').map(String::from).collect();
+        columns.truncate(OTP_COLUMN);
+        if !uri.is_empty() {
+            columns.push(self.cipher().encrypt(uri.to_string()));
+        }
+        *encrypted = columns.join(":");
+
+        write_lines(&self.path, &enc_passwords)
+    }
+
+    /// Removes the entry with the given id.
+    pub fn remove(&self, id: &str) -> io::Result<()> {
+        let mut enc_passwords = read_lines(&self.path)?;
+        let count = enc_passwords.len();
+
+        enc_passwords.retain(|(_, encrypted)| line_id(encrypted) != id);
+
+        if enc_passwords.len() == count {
+            return Err(no_such_entry(id));
+        }
+
+        write_lines(&self.path, &enc_passwords)
+    }
+
+    /// Re-encrypts the whole database with `new_key`, already 32 bytes long.
+    /// Nothing is written unless every entry decrypts with the current key.
+    pub fn rekey(&mut self, new_key: &str) -> io::Result<()> {
+        check_key_length(new_key)?;
+        let new_cipher = Cipher::new(new_key);
+
+        self.check()?;
+        let entries = self.entries()?;
+
+        let enc_passwords: Vec<(String, String)> = entries
+            .iter()
+            .map(|entry| (entry.username.clone(), encrypt_entry(entry, &new_cipher)))
+            .collect();
+
+        write_lines(&self.path, &enc_passwords)?;
+        self.key = new_key.to_string();
+
+        Ok(())
+    }
+}
+
+/// Returns the ids of the entries stored at `path`, which are not encrypted.
+pub fn entry_ids(path: &Path) -> io::Result<Vec<String>> {
+    Ok(read_lines(path)?
+        .iter()
+        .map(|(_, encrypted)| line_id(encrypted).to_string())
+        .collect())
+}
+
+/// Returns the ids of the entries stored at `path` that have one-time
+/// passwords, which is known without decrypting them.
+pub fn otp_ids(path: &Path) -> io::Result<Vec<String>> {
+    Ok(read_lines(path)?
+        .iter()
+        .filter(|(_, encrypted)| {
+            encrypted
+                .split(':')
+                .nth(OTP_COLUMN)
+                .is_some_and(|column| !column.is_empty())
+        })
+        .map(|(_, encrypted)| line_id(encrypted).to_string())
+        .collect())
+}
+
+/// Copies the database at `path`, still encrypted, to `output`. Fails with
+/// `AlreadyExists` when `output` exists, unless `overwrite` is set.
+pub fn export(path: &Path, output: &Path, overwrite: bool) -> io::Result<()> {
+    if output.exists() && !overwrite {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", output.display()),
+        ));
+    }
+
+    fs::copy(path, output).map(|_| ())
+}
+
+/// Overwrites the database at `path` with zeros, then deletes it.
+pub fn wipe(path: &Path) -> io::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let length = fs::metadata(path)?.len();
+
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    file.write_all(&vec![0u8; length as usize])?;
+    file.sync_all()?;
+
+    fs::remove_file(path)
+}
+
+/// AES-256 keys are 32 bytes long.
+fn check_key_length(key: &str) -> io::Result<()> {
+    if key.len() != 32 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Keys must be 32 bytes long, not {}", key.len()),
+        ));
+    }
+
+    Ok(())
+}
+
+fn no_such_entry(id: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("No entry with id {}", id))
+}
+
+/// Loads the raw lines of the database as `(username, columns)`, where the
+/// columns are still encrypted and end with the plaintext entry id.
+/// Lines written before ids existed get one here, and the file is rewritten.
+fn read_lines(path: &Path) -> io::Result<Vec<(String, String)>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let contents = fs::read_to_string(path)?;
+
+    let mut passwords = Vec::new();
+    let mut migrated = false;
+
+    for line in contents.lines() {
+        if let Some((username, encrypted_pwd)) = line.split_once(':') {
+            let mut columns: Vec<String> = encrypted_pwd.split(':').map(String::from).collect();
+
+            /* Empty columns stand for empty metadata */
+            if columns.len() <= ENCRYPTED_COLUMNS {
+                columns.resize(ENCRYPTED_COLUMNS, String::new());
+                columns.push(new_id());
+                migrated = true;
+            }
+
+            passwords.push((username.to_string(), columns.join(":")));
+        }
+    }
+
+    if migrated {
+        write_lines(path, &passwords)?;
+    }
+
+    Ok(passwords)
+}
+
+/// Overwrites the database file with already encrypted lines. The lines are
+/// written to a temporary file first, so that a crash cannot truncate the database.
+fn write_lines(path: &Path, enc_passwords: &[(String, String)]) -> io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+
+    let file = File::create(&tmp_path)?;
+    let mut writer = io::BufWriter::new(file);
+
+    for (user, enc_pwd) in enc_passwords {
+        writeln!(writer, "{}:{}", user, enc_pwd)?;
+    }
+
+    writer.into_inner()?.sync_all()?;
+    fs::rename(tmp_path, path)
+}
+
+/// Returns the plaintext id ending the columns of a line.
+fn line_id(encrypted: &str) -> &str {
+    encrypted
+        .split(':')
+        .nth(ENCRYPTED_COLUMNS)
+        .unwrap_or_default()
+}
+
+/// Encrypts every column of an entry but the username, then appends the id
+/// and the encrypted URI of the one-time passwords, if any.
+fn encrypt_entry(entry: &Entry, cipher: &Cipher) -> String {
+    let columns = [
+        entry.password.clone(),
+        entry.title.clone(),
+        entry.url.clone(),
+        entry.tags.join(","),
+        entry.notes.clone(),
+    ];
+
+    let mut columns: Vec<String> = columns
+        .into_iter()
+        .map(|column| cipher.encrypt(column))
+        .collect();
+    columns.push(entry.id.clone());
+
+    if !entry.otp.is_empty() {
+        columns.push(cipher.encrypt(entry.otp.clone()));
+    }
+
+    columns.join(":")
+}
+
+/// Decrypts the encrypted columns of a line, the id left out, leaving the
+/// password and the `otpauth://` URI empty unless `with_password` is set.
+fn decrypt_columns(
+    cipher: &Cipher,
+    encrypted: &str,
+    with_password: bool,
+) -> Result<Vec<String>, String> {
+    encrypted
+        .split(':')
+        .enumerate()
+        .filter(|(index, _)| *index != ENCRYPTED_COLUMNS)
+        .map(|(index, column)| match (index, column) {
+            (0 | OTP_COLUMN, _) if !with_password => Ok(String::new()),
+            (_, "") => Ok(String::new()),
+            _ => cipher.decrypt(column.to_string()),
+        })
+        .collect()
+}
@@ -0,0 +1,159 @@
+/* C interface to pwds vaults, generated by cbindgen from src/lib.rs. */
+
+#ifndef PWDS_H
+#define PWDS_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Outcome of a call. The values match the exit codes of the `pwds`
+// command where they overlap.
+typedef enum PwdsStatus {
+  PWDS_STATUS_OK = 0,
+  PWDS_STATUS_FAILURE = 1,
+  // A pointer is NULL, a string is not UTF-8 or an entry is invalid.
+  PWDS_STATUS_INVALID_ARGUMENT = 2,
+  // No such vault or entry.
+  PWDS_STATUS_NOT_FOUND = 3,
+  PWDS_STATUS_WRONG_KEY = 4,
+  // The vault must be unlocked first, see `pwds_vault_unlock`.
+  PWDS_STATUS_LOCKED = 5,
+  // Some entries cannot be decrypted with the right key.
+  PWDS_STATUS_CORRUPT_VAULT = 6,
+  // Another entry has the same username and URL.
+  PWDS_STATUS_ALREADY_EXISTS = 7,
+} PwdsStatus;
+
+// An opened vault, locked until `pwds_vault_unlock` succeeds.
+typedef struct PwdsVault PwdsVault;
+
+// An entry. Tags are comma separated. In entries given to the library,
+// NULL fields stand for empty ones.
+typedef struct PwdsEntry {
+  char *id;
+  char *title;
+  char *username;
+  char *password;
+  char *url;
+  char *tags;
+  char *notes;
+} PwdsEntry;
+
+// The entries of a vault, without their passwords.
+typedef struct PwdsEntryList {
+  struct PwdsEntry *entries;
+  size_t len;
+} PwdsEntryList;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Opens the vault at `path`, which must exist. The vault starts locked.
+//
+// # Safety
+//
+// `path` must be a NUL terminated string and `out` point to writable memory.
+PwdsStatus pwds_vault_open(const char *path, struct PwdsVault **out);
+
+// Unlocks the vault with the key as the user types it. Fails with
+// `PWDS_STATUS_WRONG_KEY` when the key does not decrypt the vault.
+//
+// # Safety
+//
+// `vault` must come from `pwds_vault_open` and `key` be a NUL terminated string.
+PwdsStatus pwds_vault_unlock(struct PwdsVault *vault, const char *key);
+
+// Forgets the key, the vault stays open.
+//
+// # Safety
+//
+// `vault` must come from `pwds_vault_open`.
+PwdsStatus pwds_vault_lock(struct PwdsVault *vault);
+
+// Locks and frees the vault. NULL is ignored.
+//
+// # Safety
+//
+// `vault` must come from `pwds_vault_open` and not be used afterwards.
+void pwds_vault_close(struct PwdsVault *vault);
+
+// Lists the entries, their passwords left NULL, see `pwds_vault_get`.
+// Free the list with `pwds_entry_list_free`.
+//
+// # Safety
+//
+// `vault` must come from `pwds_vault_open` and `out` point to writable memory.
+PwdsStatus pwds_vault_list(const struct PwdsVault *vault, struct PwdsEntryList *out);
+
+// Fetches the entry with the given id, password included. Free it with
+// `pwds_entry_free`.
+//
+// # Safety
+//
+// `vault` must come from `pwds_vault_open`, `id` be a NUL terminated string
+// and `out` point to writable memory.
+PwdsStatus pwds_vault_get(const struct PwdsVault *vault, const char *id, struct PwdsEntry *out);
+
+// Adds an entry, its `id` field being ignored. The id of the new entry is
+// written to `id_out` unless it is NULL; free it with `pwds_string_free`.
+//
+// # Safety
+//
+// `vault` must come from `pwds_vault_open`, `entry` hold NULL or NUL
+// terminated strings and `id_out` be NULL or point to writable memory.
+PwdsStatus pwds_vault_add(const struct PwdsVault *vault,
+                          const struct PwdsEntry *entry,
+                          char **id_out);
+
+// Replaces every field of the entry with the given id, except the
+// one-time passwords, which `PwdsEntry` does not carry.
+//
+// # Safety
+//
+// `vault` must come from `pwds_vault_open`, `id` be a NUL terminated string
+// and `entry` hold NULL or NUL terminated strings.
+PwdsStatus pwds_vault_update(const struct PwdsVault *vault,
+                             const char *id,
+                             const struct PwdsEntry *entry);
+
+// Deletes the entry with the given id.
+//
+// # Safety
+//
+// `vault` must come from `pwds_vault_open` and `id` be a NUL terminated string.
+PwdsStatus pwds_vault_delete(const struct PwdsVault *vault, const char *id);
+
+// Overwrites and frees the fields of an entry, then sets them to NULL.
+//
+// # Safety
+//
+// `entry` must be NULL or filled by `pwds_vault_get`.
+void pwds_entry_free(struct PwdsEntry *entry);
+
+// Frees the entries of a list, then empties it.
+//
+// # Safety
+//
+// `list` must be NULL or filled by `pwds_vault_list`.
+void pwds_entry_list_free(struct PwdsEntryList *list);
+
+// Overwrites and frees a string returned by the library.
+//
+// # Safety
+//
+// `text` must be NULL or come from the library, and not be used afterwards.
+void pwds_string_free(char *text);
+
+// Describes the last failure on the calling thread, or returns NULL after
+// a success. The string stays valid until the next call on this thread.
+const char *pwds_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PWDS_H */
@@ -0,0 +1,818 @@
+//! C interface to pwds vaults, declared in `include/pwds.h`.
+//!
+//! Every function returns a `PwdsStatus`, and `pwds_last_error` describes
+//! the last failure of the calling thread. Strings are UTF-8 and NUL
+//! terminated. What the library hands out must be given back to the
+//! matching `pwds_*_free` function, which overwrites it before freeing it.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+use pwds_core::pwds::parse_tags;
+use pwds_core::{Entry, Vault};
+use zeroize::Zeroize;
+
+/// Outcome of a call. The values match the exit codes of the `pwds`
+/// command where they overlap.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PwdsStatus {
+    Ok = 0,
+    Failure = 1,
+    /// A pointer is NULL, a string is not UTF-8 or an entry is invalid.
+    InvalidArgument = 2,
+    /// No such vault or entry.
+    NotFound = 3,
+    WrongKey = 4,
+    /// The vault must be unlocked first, see `pwds_vault_unlock`.
+    Locked = 5,
+    /// Some entries cannot be decrypted with the right key.
+    CorruptVault = 6,
+    /// Another entry has the same username and URL.
+    AlreadyExists = 7,
+}
+
+/// An opened vault, locked until `pwds_vault_unlock` succeeds.
+pub struct PwdsVault {
+    path: PathBuf,
+    vault: Option<Vault>,
+}
+
+/// An entry. Tags are comma separated. In entries given to the library,
+/// NULL fields stand for empty ones.
+#[repr(C)]
+pub struct PwdsEntry {
+    pub id: *mut c_char,
+    pub title: *mut c_char,
+    pub username: *mut c_char,
+    pub password: *mut c_char,
+    pub url: *mut c_char,
+    pub tags: *mut c_char,
+    pub notes: *mut c_char,
+}
+
+/// The entries of a vault, without their passwords.
+#[repr(C)]
+pub struct PwdsEntryList {
+    pub entries: *mut PwdsEntry,
+    pub len: usize,
+}
+
+struct Error {
+    status: PwdsStatus,
+    message: String,
+}
+
+impl Error {
+    fn new(status: PwdsStatus, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn invalid(message: impl Into<String>) -> Self {
+        Self::new(PwdsStatus::InvalidArgument, message)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        let status = match e.kind() {
+            io::ErrorKind::NotFound => PwdsStatus::NotFound,
+            io::ErrorKind::PermissionDenied => PwdsStatus::WrongKey,
+            io::ErrorKind::InvalidData => PwdsStatus::CorruptVault,
+            io::ErrorKind::AlreadyExists => PwdsStatus::AlreadyExists,
+            io::ErrorKind::InvalidInput => PwdsStatus::InvalidArgument,
+            _ => PwdsStatus::Failure,
+        };
+
+        Self::new(status, e.to_string())
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Runs the body of an exported function, recording its error, if any.
+/// Panics must not unwind into C, they become `PWDS_STATUS_FAILURE`.
+fn call(body: impl FnOnce() -> Result<(), Error>) -> PwdsStatus {
+    let result = panic::catch_unwind(AssertUnwindSafe(body))
+        .unwrap_or_else(|_| Err(Error::new(PwdsStatus::Failure, "Internal error")));
+
+    let (status, message) = match result {
+        Ok(()) => (PwdsStatus::Ok, None),
+        Err(error) => (
+            error.status,
+            Some(CString::new(error.message.replace('\0', "")).unwrap_or_default()),
+        ),
+    };
+
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+    status
+}
+
+/// Borrows a string argument.
+unsafe fn str_arg<'a>(pointer: *const c_char, name: &str) -> Result<&'a str, Error> {
+    if pointer.is_null() {
+        return Err(Error::invalid(format!("{name} is NULL")));
+    }
+
+    CStr::from_ptr(pointer)
+        .to_str()
+        .map_err(|_| Error::invalid(format!("{name} is not UTF-8")))
+}
+
+/// Borrows an optional string argument, NULL being empty.
+unsafe fn optional_str_arg<'a>(pointer: *const c_char, name: &str) -> Result<&'a str, Error> {
+    if pointer.is_null() {
+        return Ok("");
+    }
+    str_arg(pointer, name)
+}
+
+/// Borrows an output argument, checked before anything is changed.
+unsafe fn out_arg<'a, T>(pointer: *mut T, name: &str) -> Result<&'a mut T, Error> {
+    pointer
+        .as_mut()
+        .ok_or_else(|| Error::invalid(format!("{name} is NULL")))
+}
+
+/// Borrows the unlocked vault behind a handle.
+unsafe fn unlocked<'a>(vault: *const PwdsVault) -> Result<&'a Vault, Error> {
+    let vault = vault
+        .as_ref()
+        .ok_or_else(|| Error::invalid("vault is NULL"))?;
+
+    vault
+        .vault
+        .as_ref()
+        .ok_or_else(|| Error::new(PwdsStatus::Locked, "The vault is locked"))
+}
+
+fn new_string(text: &str) -> Result<*mut c_char, Error> {
+    CString::new(text)
+        .map(CString::into_raw)
+        .map_err(|_| Error::new(PwdsStatus::Failure, "A field holds a NUL byte"))
+}
+
+/// Overwrites and frees a string made by `new_string`.
+unsafe fn free_string(text: &mut *mut c_char) {
+    if text.is_null() {
+        return;
+    }
+
+    CString::from_raw(*text).into_bytes_with_nul().zeroize();
+    *text = ptr::null_mut();
+}
+
+/// Copies an entry for C, overwriting the password of the Rust copy.
+fn new_entry(mut entry: Entry) -> Result<PwdsEntry, Error> {
+    let mut c_entry = PwdsEntry::empty();
+
+    let result = (|| {
+        c_entry.id = new_string(&entry.id)?;
+        c_entry.title = new_string(&entry.title)?;
+        c_entry.username = new_string(&entry.username)?;
+        c_entry.password = new_string(&entry.password)?;
+        c_entry.url = new_string(&entry.url)?;
+        c_entry.tags = new_string(&entry.tags.join(","))?;
+        c_entry.notes = new_string(&entry.notes)?;
+        Ok(())
+    })();
+    entry.password.zeroize();
+
+    match result {
+        Ok(()) => Ok(c_entry),
+        Err(error) => {
+            /* SAFETY: the fields were made by new_string or are NULL */
+            unsafe { c_entry.free() };
+            Err(error)
+        }
+    }
+}
+
+/// Reads an entry given by C.
+unsafe fn entry_arg(entry: *const PwdsEntry) -> Result<Entry, Error> {
+    let entry = entry
+        .as_ref()
+        .ok_or_else(|| Error::invalid("entry is NULL"))?;
+
+    let entry = Entry {
+        id: String::new(),
+        title: optional_str_arg(entry.title, "title")?.to_string(),
+        username: optional_str_arg(entry.username, "username")?.to_string(),
+        password: optional_str_arg(entry.password, "password")?.to_string(),
+        url: optional_str_arg(entry.url, "url")?.to_string(),
+        tags: parse_tags(optional_str_arg(entry.tags, "tags")?),
+        notes: optional_str_arg(entry.notes, "notes")?.to_string(),
+        otp: String::new(),
+    };
+
+    entry.validate().map_err(Error::invalid)?;
+    Ok(entry)
+}
+
+impl PwdsEntry {
+    fn empty() -> Self {
+        Self {
+            id: ptr::null_mut(),
+            title: ptr::null_mut(),
+            username: ptr::null_mut(),
+            password: ptr::null_mut(),
+            url: ptr::null_mut(),
+            tags: ptr::null_mut(),
+            notes: ptr::null_mut(),
+        }
+    }
+
+    unsafe fn free(&mut self) {
+        for field in [
+            &mut self.id,
+            &mut self.title,
+            &mut self.username,
+            &mut self.password,
+            &mut self.url,
+            &mut self.tags,
+            &mut self.notes,
+        ] {
+            free_string(field);
+        }
+    }
+}
+
+/// Opens the vault at `path`, which must exist. The vault starts locked.
+///
+/// # Safety
+///
+/// `path` must be a NUL terminated string and `out` point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn pwds_vault_open(
+    path: *const c_char,
+    out: *mut *mut PwdsVault,
+) -> PwdsStatus {
+    call(|| {
+        let path = str_arg(path, "path")?;
+        let out = out_arg(out, "out")?;
+
+        if !Path::new(path).exists() {
+            return Err(Error::new(
+                PwdsStatus::NotFound,
+                format!("No database at {path}"),
+            ));
+        }
+
+        *out = Box::into_raw(Box::new(PwdsVault {
+            path: PathBuf::from(path),
+            vault: None,
+        }));
+        Ok(())
+    })
+}
+
+/// Unlocks the vault with the key as the user types it. Fails with
+/// `PWDS_STATUS_WRONG_KEY` when the key does not decrypt the vault.
+///
+/// # Safety
+///
+/// `vault` must come from `pwds_vault_open` and `key` be a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pwds_vault_unlock(
+    vault: *mut PwdsVault,
+    key: *const c_char,
+) -> PwdsStatus {
+    call(|| {
+        let vault = out_arg(vault, "vault")?;
+        let key = str_arg(key, "key")?;
+
+        let unlocked = Vault::unlock(&vault.path, key)?;
+        unlocked.check()?;
+        vault.vault = Some(unlocked);
+        Ok(())
+    })
+}
+
+/// Forgets the key, the vault stays open.
+///
+/// # Safety
+///
+/// `vault` must come from `pwds_vault_open`.
+#[no_mangle]
+pub unsafe extern "C" fn pwds_vault_lock(vault: *mut PwdsVault) -> PwdsStatus {
+    call(|| {
+        out_arg(vault, "vault")?.vault = None;
+        Ok(())
+    })
+}
+
+/// Locks and frees the vault. NULL is ignored.
+///
+/// # Safety
+///
+/// `vault` must come from `pwds_vault_open` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn pwds_vault_close(vault: *mut PwdsVault) {
+    if !vault.is_null() {
+        drop(Box::from_raw(vault));
+    }
+}
+
+/// Lists the entries, their passwords left NULL, see `pwds_vault_get`.
+/// Free the list with `pwds_entry_list_free`.
+///
+/// # Safety
+///
+/// `vault` must come from `pwds_vault_open` and `out` point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn pwds_vault_list(
+    vault: *const PwdsVault,
+    out: *mut PwdsEntryList,
+) -> PwdsStatus {
+    call(|| {
+        let vault = unlocked(vault)?;
+        let out = out_arg(out, "out")?;
+
+        let mut entries = Vec::new();
+        for entry in vault.entry_list()? {
+            match new_entry(entry) {
+                Ok(mut entry) => {
+                    free_string(&mut entry.password);
+                    entries.push(entry);
+                }
+                Err(error) => {
+                    entries.iter_mut().for_each(|entry| entry.free());
+                    return Err(error);
+                }
+            }
+        }
+
+        let entries = Box::into_raw(entries.into_boxed_slice());
+        *out = PwdsEntryList {
+            len: entries.len(),
+            entries: entries.cast(),
+        };
+        Ok(())
+    })
+}
+
+/// Fetches the entry with the given id, password included. Free it with
+/// `pwds_entry_free`.
+///
+/// # Safety
+///
+/// `vault` must come from `pwds_vault_open`, `id` be a NUL terminated string
+/// and `out` point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn pwds_vault_get(
+    vault: *const PwdsVault,
+    id: *const c_char,
+    out: *mut PwdsEntry,
+) -> PwdsStatus {
+    call(|| {
+        let vault = unlocked(vault)?;
+        let id = str_arg(id, "id")?;
+        let out = out_arg(out, "out")?;
+
+        let entry = vault
+            .entry(id)?
+            .ok_or_else(|| Error::new(PwdsStatus::NotFound, format!("No entry with id {id}")))?;
+
+        *out = new_entry(entry)?;
+        Ok(())
+    })
+}
+
+/// Adds an entry, its `id` field being ignored. The id of the new entry is
+/// written to `id_out` unless it is NULL; free it with `pwds_string_free`.
+///
+/// # Safety
+///
+/// `vault` must come from `pwds_vault_open`, `entry` hold NULL or NUL
+/// terminated strings and `id_out` be NULL or point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn pwds_vault_add(
+    vault: *const PwdsVault,
+    entry: *const PwdsEntry,
+    id_out: *mut *mut c_char,
+) -> PwdsStatus {
+    call(|| {
+        let vault = unlocked(vault)?;
+        let mut entry = entry_arg(entry)?;
+
+        if let Some(duplicate) = vault.find_duplicate(&entry)? {
+            entry.password.zeroize();
+            return Err(Error::new(
+                PwdsStatus::AlreadyExists,
+                format!("Entry {} has the same username and URL", duplicate.id),
+            ));
+        }
+
+        let id = vault.add(&entry);
+        entry.password.zeroize();
+        let id = id?;
+
+        if let Some(id_out) = id_out.as_mut() {
+            *id_out = new_string(&id)?;
+        }
+        Ok(())
+    })
+}
+
+/// Replaces every field of the entry with the given id, except the
+/// one-time passwords, which `PwdsEntry` does not carry.
+///
+/// # Safety
+///
+/// `vault` must come from `pwds_vault_open`, `id` be a NUL terminated string
+/// and `entry` hold NULL or NUL terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn pwds_vault_update(
+    vault: *const PwdsVault,
+    id: *const c_char,
+    entry: *const PwdsEntry,
+) -> PwdsStatus {
+    call(|| {
+        let vault = unlocked(vault)?;
+        let id = str_arg(id, "id")?;
+        let mut entry = entry_arg(entry)?;
+        entry.otp = vault.otp(id)?.unwrap_or_default();
+
+        let result = vault.update(id, &entry);
+        entry.password.zeroize();
+        Ok(result?)
+    })
+}
+
+/// Deletes the entry with the given id.
+///
+/// # Safety
+///
+/// `vault` must come from `pwds_vault_open` and `id` be a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pwds_vault_delete(
+    vault: *const PwdsVault,
+    id: *const c_char,
+) -> PwdsStatus {
+    call(|| {
+        let vault = unlocked(vault)?;
+        let id = str_arg(id, "id")?;
+
+        Ok(vault.remove(id)?)
+    })
+}
+
+/// Overwrites and frees the fields of an entry, then sets them to NULL.
+///
+/// # Safety
+///
+/// `entry` must be NULL or filled by `pwds_vault_get`.
+#[no_mangle]
+pub unsafe extern "C" fn pwds_entry_free(entry: *mut PwdsEntry) {
+    if let Some(entry) = entry.as_mut() {
+        entry.free();
+    }
+}
+
+/// Frees the entries of a list, then empties it.
+///
+/// # Safety
+///
+/// `list` must be NULL or filled by `pwds_vault_list`.
+#[no_mangle]
+pub unsafe extern "C" fn pwds_entry_list_free(list: *mut PwdsEntryList) {
+    let Some(list) = list.as_mut() else {
+        return;
+    };
+
+    if !list.entries.is_null() {
+        let mut entries = Box::from_raw(ptr::slice_from_raw_parts_mut(list.entries, list.len));
+        entries.iter_mut().for_each(|entry| entry.free());
+    }
+
+    list.entries = ptr::null_mut();
+    list.len = 0;
+}
+
+/// Overwrites and frees a string returned by the library.
+///
+/// # Safety
+///
+/// `text` must be NULL or come from the library, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn pwds_string_free(text: *mut c_char) {
+    let mut text = text;
+    free_string(&mut text);
+}
+
+/// Describes the last failure on the calling thread, or returns NULL after
+/// a success. The string stays valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn pwds_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    /// An empty database in the temporary directory, removed when dropped.
+    struct TempVault(PathBuf);
+
+    impl TempVault {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("pwds-ffi-{}-{name}.enc", std::process::id()));
+            fs::write(&path, "").unwrap();
+            Self(path)
+        }
+
+        fn c_path(&self) -> CString {
+            CString::new(self.0.to_str().unwrap()).unwrap()
+        }
+    }
+
+    impl Drop for TempVault {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn c(text: &str) -> CString {
+        CString::new(text).unwrap()
+    }
+
+    unsafe fn text(pointer: *const c_char) -> &'static str {
+        CStr::from_ptr(pointer).to_str().unwrap()
+    }
+
+    unsafe fn last_error() -> &'static str {
+        text(pwds_last_error())
+    }
+
+    /// Opens and unlocks the database with the key "secret".
+    unsafe fn open(temp: &TempVault) -> *mut PwdsVault {
+        let mut vault = ptr::null_mut();
+        assert_eq!(
+            pwds_vault_open(temp.c_path().as_ptr(), &mut vault),
+            PwdsStatus::Ok
+        );
+        assert_eq!(
+            pwds_vault_unlock(vault, c("secret").as_ptr()),
+            PwdsStatus::Ok
+        );
+        vault
+    }
+
+    #[test]
+    fn round_trip() {
+        let temp = TempVault::new("round-trip");
+
+        let (title, username, password, url, tags) = (
+            c("Mail"),
+            c("alice"),
+            c("hunter2"),
+            c("https://mail.example"),
+            c("work,mail"),
+        );
+        let mut entry = PwdsEntry::empty();
+        entry.title = title.as_ptr().cast_mut();
+        entry.username = username.as_ptr().cast_mut();
+        entry.password = password.as_ptr().cast_mut();
+        entry.url = url.as_ptr().cast_mut();
+        entry.tags = tags.as_ptr().cast_mut();
+
+        unsafe {
+            let vault = open(&temp);
+
+            let mut id = ptr::null_mut();
+            assert_eq!(pwds_vault_add(vault, &entry, &mut id), PwdsStatus::Ok);
+            assert!(pwds_last_error().is_null());
+            let id_text = text(id).to_string();
+
+            assert_eq!(
+                pwds_vault_add(vault, &entry, ptr::null_mut()),
+                PwdsStatus::AlreadyExists
+            );
+
+            let mut list = PwdsEntryList {
+                entries: ptr::null_mut(),
+                len: 0,
+            };
+            assert_eq!(pwds_vault_list(vault, &mut list), PwdsStatus::Ok);
+            assert_eq!(list.len, 1);
+            let listed = &*list.entries;
+            assert_eq!(text(listed.id), id_text);
+            assert_eq!(text(listed.username), "alice");
+            assert_eq!(text(listed.tags), "work,mail");
+            assert!(listed.password.is_null());
+            pwds_entry_list_free(&mut list);
+            assert!(list.entries.is_null());
+            assert_eq!(list.len, 0);
+
+            let mut fetched = PwdsEntry::empty();
+            assert_eq!(pwds_vault_get(vault, id, &mut fetched), PwdsStatus::Ok);
+            assert_eq!(text(fetched.password), "hunter2");
+            assert_eq!(text(fetched.url), "https://mail.example");
+            pwds_entry_free(&mut fetched);
+            assert!(fetched.password.is_null());
+
+            let new_title = c("Webmail");
+            entry.title = new_title.as_ptr().cast_mut();
+            entry.notes = ptr::null_mut();
+            assert_eq!(pwds_vault_update(vault, id, &entry), PwdsStatus::Ok);
+
+            let mut fetched = PwdsEntry::empty();
+            assert_eq!(pwds_vault_get(vault, id, &mut fetched), PwdsStatus::Ok);
+            assert_eq!(text(fetched.title), "Webmail");
+            assert_eq!(text(fetched.notes), "");
+            pwds_entry_free(&mut fetched);
+
+            assert_eq!(pwds_vault_delete(vault, id), PwdsStatus::Ok);
+            assert_eq!(
+                pwds_vault_get(vault, id, &mut fetched),
+                PwdsStatus::NotFound
+            );
+            assert_eq!(pwds_vault_delete(vault, id), PwdsStatus::NotFound);
+
+            pwds_string_free(id);
+            pwds_vault_close(vault);
+        }
+    }
+
+    #[test]
+    fn update_keeps_otp() {
+        let temp = TempVault::new("otp");
+        let uri = "otpauth://totp/pwds:dave?secret=JBSWY3DPEHPK3PXP";
+
+        unsafe {
+            let vault = open(&temp);
+            let (username, password) = (c("dave"), c("pw"));
+            let mut entry = PwdsEntry::empty();
+            entry.username = username.as_ptr().cast_mut();
+            entry.password = password.as_ptr().cast_mut();
+
+            let mut id = ptr::null_mut();
+            assert_eq!(pwds_vault_add(vault, &entry, &mut id), PwdsStatus::Ok);
+
+            let stored = (*vault).vault.as_ref().unwrap();
+            stored.set_otp(text(id), uri).unwrap();
+
+            let new_password = c("pw2");
+            entry.password = new_password.as_ptr().cast_mut();
+            assert_eq!(pwds_vault_update(vault, id, &entry), PwdsStatus::Ok);
+            assert_eq!(stored.otp(text(id)).unwrap().as_deref(), Some(uri));
+
+            pwds_string_free(id);
+            pwds_vault_close(vault);
+        }
+    }
+
+    #[test]
+    fn locking() {
+        let temp = TempVault::new("locking");
+
+        unsafe {
+            let vault = open(&temp);
+            let mut list = PwdsEntryList {
+                entries: ptr::null_mut(),
+                len: 0,
+            };
+
+            assert_eq!(pwds_vault_lock(vault), PwdsStatus::Ok);
+            assert_eq!(pwds_vault_list(vault, &mut list), PwdsStatus::Locked);
+
+            assert_eq!(
+                pwds_vault_unlock(vault, c("secret").as_ptr()),
+                PwdsStatus::Ok
+            );
+            let mut entry = PwdsEntry::empty();
+            let (username, password) = (c("bob"), c("pw"));
+            entry.username = username.as_ptr().cast_mut();
+            entry.password = password.as_ptr().cast_mut();
+            assert_eq!(
+                pwds_vault_add(vault, &entry, ptr::null_mut()),
+                PwdsStatus::Ok
+            );
+            pwds_vault_close(vault);
+
+            let mut vault = ptr::null_mut();
+            assert_eq!(
+                pwds_vault_open(temp.c_path().as_ptr(), &mut vault),
+                PwdsStatus::Ok
+            );
+            assert_eq!(
+                pwds_vault_unlock(vault, c("wrong").as_ptr()),
+                PwdsStatus::WrongKey
+            );
+            assert_eq!(pwds_vault_list(vault, &mut list), PwdsStatus::Locked);
+            assert!(!pwds_last_error().is_null());
+            pwds_vault_close(vault);
+        }
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let temp = TempVault::new("invalid");
+        let not_utf8 = c"\xff\xfe";
+
+        unsafe {
+            let mut vault = ptr::null_mut();
+            assert_eq!(
+                pwds_vault_open(ptr::null(), &mut vault),
+                PwdsStatus::InvalidArgument
+            );
+            assert_eq!(last_error(), "path is NULL");
+            assert!(vault.is_null());
+
+            assert_eq!(
+                pwds_vault_open(not_utf8.as_ptr(), &mut vault),
+                PwdsStatus::InvalidArgument
+            );
+            assert_eq!(last_error(), "path is not UTF-8");
+
+            assert_eq!(
+                pwds_vault_open(temp.c_path().as_ptr(), ptr::null_mut()),
+                PwdsStatus::InvalidArgument
+            );
+            assert_eq!(
+                pwds_vault_open(c("/nonexistent/pwds.enc").as_ptr(), &mut vault),
+                PwdsStatus::NotFound
+            );
+
+            assert_eq!(
+                pwds_vault_unlock(ptr::null_mut(), c("secret").as_ptr()),
+                PwdsStatus::InvalidArgument
+            );
+            assert_eq!(
+                pwds_vault_list(ptr::null(), ptr::null_mut()),
+                PwdsStatus::InvalidArgument
+            );
+
+            let vault = open(&temp);
+            assert_eq!(
+                pwds_vault_unlock(vault, not_utf8.as_ptr()),
+                PwdsStatus::InvalidArgument
+            );
+            assert_eq!(last_error(), "key is not UTF-8");
+            assert_eq!(
+                pwds_vault_list(vault, ptr::null_mut()),
+                PwdsStatus::InvalidArgument
+            );
+            assert_eq!(
+                pwds_vault_get(vault, ptr::null(), &mut PwdsEntry::empty()),
+                PwdsStatus::InvalidArgument
+            );
+            assert_eq!(
+                pwds_vault_delete(vault, not_utf8.as_ptr()),
+                PwdsStatus::InvalidArgument
+            );
+            assert_eq!(
+                pwds_vault_add(vault, ptr::null(), ptr::null_mut()),
+                PwdsStatus::InvalidArgument
+            );
+            assert_eq!(last_error(), "entry is NULL");
+
+            /* Entries without a username or with a non UTF-8 field */
+            let mut entry = PwdsEntry::empty();
+            assert_eq!(
+                pwds_vault_add(vault, &entry, ptr::null_mut()),
+                PwdsStatus::InvalidArgument
+            );
+            let username = c("carol");
+            entry.username = username.as_ptr().cast_mut();
+            entry.notes = not_utf8.as_ptr().cast_mut();
+            assert_eq!(
+                pwds_vault_add(vault, &entry, ptr::null_mut()),
+                PwdsStatus::InvalidArgument
+            );
+            assert_eq!(last_error(), "notes is not UTF-8");
+
+            let mut list = PwdsEntryList {
+                entries: ptr::null_mut(),
+                len: 0,
+            };
+            assert_eq!(pwds_vault_list(vault, &mut list), PwdsStatus::Ok);
+            assert_eq!(list.len, 0);
+            pwds_entry_list_free(&mut list);
+
+            /* The free functions ignore NULL */
+            pwds_entry_free(ptr::null_mut());
+            pwds_entry_list_free(ptr::null_mut());
+            pwds_string_free(ptr::null_mut());
+            pwds_vault_close(ptr::null_mut());
+            pwds_vault_close(vault);
+        }
+    }
+}
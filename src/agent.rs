@@ -0,0 +1,440 @@
+use std::env;
+use std::fs::{self, DirBuilder};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::pwds::Entry;
+use crate::vault::Vault;
+
+/// Longest request or answer accepted, enough for a large import.
+const MAX_MESSAGE_LEN: u64 = 64 << 20;
+
+/// How long a client waits for the agent, and the agent for a client.
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A request, sent as one JSON line along with the path of the database
+/// the client works on, see `Message`. Apart from `Unlock`, `Status` and
+/// `Lock`, requests are answered with the vault the agent holds, as the
+/// `Vault` method of the same name does.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Request {
+    /// Opens the database with a key the client verified. The key is
+    /// never sent back.
+    Unlock {
+        key: String,
+    },
+    /// Whether the database is unlocked, with a key that still decrypts it.
+    Status,
+    /// Closes the vault, whichever database it is.
+    Lock,
+    Check,
+    Entries,
+    EntryList,
+    Entry {
+        id: String,
+    },
+    Password {
+        id: String,
+    },
+    Otp {
+        id: String,
+    },
+    FindDuplicate {
+        entry: Entry,
+    },
+    FindByUsername {
+        username: String,
+    },
+    Add {
+        entry: Entry,
+    },
+    Update {
+        id: String,
+        entry: Entry,
+    },
+    SaveAll {
+        updated: Vec<Entry>,
+        added: Vec<Entry>,
+    },
+    SetPassword {
+        id: String,
+        password: String,
+    },
+    SetOtp {
+        id: String,
+        uri: String,
+    },
+    Remove {
+        id: String,
+    },
+    Rekey {
+        key: String,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct Message {
+    /// Absolute path of the database, so that clients working in other
+    /// directories never see the entries of another one.
+    path: PathBuf,
+    #[serde(flatten)]
+    request: Request,
+}
+
+/// The answer to a request, sent as one JSON line.
+#[derive(Serialize, Deserialize, Default)]
+struct Response {
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    result: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<Failure>,
+}
+
+/// An `io::Error` of the agent, kept apart from the errors of the socket.
+#[derive(Serialize, Deserialize)]
+struct Failure {
+    kind: FailureKind,
+    message: String,
+}
+
+/// The `io::ErrorKind`s the vault fails with, which clients tell apart.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum FailureKind {
+    NotFound,
+    AlreadyExists,
+    InvalidInput,
+    InvalidData,
+    PermissionDenied,
+    Other,
+}
+
+impl From<io::Error> for Failure {
+    fn from(e: io::Error) -> Self {
+        let kind = match e.kind() {
+            io::ErrorKind::NotFound => FailureKind::NotFound,
+            io::ErrorKind::AlreadyExists => FailureKind::AlreadyExists,
+            io::ErrorKind::InvalidInput => FailureKind::InvalidInput,
+            io::ErrorKind::InvalidData => FailureKind::InvalidData,
+            io::ErrorKind::PermissionDenied => FailureKind::PermissionDenied,
+            _ => FailureKind::Other,
+        };
+
+        Self {
+            kind,
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<Failure> for io::Error {
+    fn from(failure: Failure) -> Self {
+        let kind = match failure.kind {
+            FailureKind::NotFound => io::ErrorKind::NotFound,
+            FailureKind::AlreadyExists => io::ErrorKind::AlreadyExists,
+            FailureKind::InvalidInput => io::ErrorKind::InvalidInput,
+            FailureKind::InvalidData => io::ErrorKind::InvalidData,
+            FailureKind::PermissionDenied => io::ErrorKind::PermissionDenied,
+            FailureKind::Other => io::ErrorKind::Other,
+        };
+
+        io::Error::new(kind, failure.message)
+    }
+}
+
+/// The unlocked vault, closed `ttl` after its last use.
+struct Cache {
+    vault: Option<Vault>,
+    last_use: Instant,
+    ttl: Duration,
+}
+
+impl Cache {
+    fn expire(&mut self) {
+        if !self.ttl.is_zero() && self.last_use.elapsed() >= self.ttl {
+            self.vault = None;
+        }
+    }
+
+    fn answer(&mut self, path: PathBuf, request: Request) -> io::Result<Value> {
+        let vault = match request {
+            Request::Unlock { key } => {
+                let vault = Vault::new(path, key)?;
+                if !vault.verify_key()? {
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        "Wrong encryption key",
+                    ));
+                }
+
+                self.vault = Some(vault);
+                self.last_use = Instant::now();
+                return Ok(Value::Null);
+            }
+            Request::Status => {
+                let Some(vault) = self.vault.as_ref().filter(|vault| vault.path() == path) else {
+                    return Ok(Value::Bool(false));
+                };
+
+                /* The database may have been re-encrypted or replaced since */
+                if !vault.verify_key().unwrap_or(false) {
+                    self.vault = None;
+                    return Ok(Value::Bool(false));
+                }
+
+                self.last_use = Instant::now();
+                return Ok(Value::Bool(true));
+            }
+            Request::Lock => {
+                self.vault = None;
+                return Ok(Value::Null);
+            }
+            _ => self
+                .vault
+                .as_mut()
+                .filter(|vault| vault.path() == path)
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::PermissionDenied, "The agent is locked")
+                })?,
+        };
+        self.last_use = Instant::now();
+
+        let result = match request {
+            Request::Check => serde_json::to_value(vault.check()?),
+            Request::Entries => serde_json::to_value(vault.entries()?),
+            Request::EntryList => serde_json::to_value(vault.entry_list()?),
+            Request::Entry { id } => serde_json::to_value(vault.entry(&id)?),
+            Request::Password { id } => serde_json::to_value(vault.password(&id)?),
+            Request::Otp { id } => serde_json::to_value(vault.otp(&id)?),
+            Request::FindDuplicate { entry } => serde_json::to_value(vault.find_duplicate(&entry)?),
+            Request::FindByUsername { username } => {
+                serde_json::to_value(vault.find_by_username(&username)?)
+            }
+            Request::Add { entry } => serde_json::to_value(vault.add(&entry)?),
+            Request::Update { id, entry } => serde_json::to_value(vault.update(&id, &entry)?),
+            Request::SaveAll { updated, added } => {
+                serde_json::to_value(vault.save_all(&updated, &added)?)
+            }
+            Request::SetPassword { id, password } => {
+                serde_json::to_value(vault.set_password(&id, &password)?)
+            }
+            Request::SetOtp { id, uri } => serde_json::to_value(vault.set_otp(&id, &uri)?),
+            Request::Remove { id } => serde_json::to_value(vault.remove(&id)?),
+            Request::Rekey { key } => serde_json::to_value(vault.rekey(&key)?),
+            Request::Unlock { .. } | Request::Status | Request::Lock => Ok(Value::Null),
+        }?;
+
+        Ok(result)
+    }
+}
+
+/// Socket of the agent of the current user, in `$XDG_RUNTIME_DIR/pwds`,
+/// else in `/tmp/pwds-<uid>`. Only the user can enter either directory.
+pub fn socket_path() -> PathBuf {
+    let dir = match env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime_dir) => PathBuf::from(runtime_dir).join("pwds"),
+        None => PathBuf::from(format!("/tmp/pwds-{}", current_uid())),
+    };
+
+    dir.join("agent.sock")
+}
+
+fn current_uid() -> u32 {
+    /* SAFETY: getuid cannot fail */
+    unsafe { libc::getuid() }
+}
+
+/// Runs the agent until killed. A client that unlocked the database hands
+/// it the key, then the agent answers the entry requests of every pwds
+/// process, see `Request`, so that they skip the prompt. The key is never
+/// sent back. The vault is closed `ttl` after its last use, 0 to keep it.
+///
+/// The socket and the peer uid check keep other users out, not other
+/// processes of the same user: those can ask for every entry, password
+/// included.
+pub fn serve(ttl: Duration) -> io::Result<()> {
+    let path = socket_path();
+    let dir = path.parent().unwrap_or(&path);
+
+    DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+
+    /* Refuse a directory someone else could write into */
+    let metadata = fs::metadata(dir)?;
+    if metadata.uid() != current_uid() || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} must belong to you with mode 0700", dir.display()),
+        ));
+    }
+
+    if path.exists() {
+        if UnixStream::connect(&path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("An agent is already listening on {}", path.display()),
+            ));
+        }
+        fs::remove_file(&path)?;
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    println!("[+] Agent listening on {}", path.display());
+
+    let cache = Arc::new(Mutex::new(Cache {
+        vault: None,
+        last_use: Instant::now(),
+        ttl,
+    }));
+
+    /* Close the vault on time even when no request comes */
+    let expiring_cache = Arc::clone(&cache);
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(1));
+        if let Ok(mut cache) = expiring_cache.lock() {
+            cache.expire();
+        }
+    });
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("[-] Error while accepting a connection: {e}");
+                continue;
+            }
+        };
+
+        if let Err(e) = handle(stream, &cache) {
+            eprintln!("[-] Error while answering a request: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+fn handle(mut stream: UnixStream, cache: &Mutex<Cache>) -> io::Result<()> {
+    let peer_uid = peer_uid(&stream)?;
+    if peer_uid != current_uid() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("Refused a request from uid {peer_uid}"),
+        ));
+    }
+
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    let mut line = String::new();
+    BufReader::new((&stream).take(MAX_MESSAGE_LEN)).read_line(&mut line)?;
+
+    let mut cache = cache
+        .lock()
+        .map_err(|_| io::Error::other("The vault cache is poisoned"))?;
+    cache.expire();
+
+    let result = match serde_json::from_str::<Message>(&line) {
+        Ok(message) => cache.answer(message.path, message.request),
+        Err(e) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid request: {e}"),
+        )),
+    };
+    drop(cache);
+
+    let response = match result {
+        Ok(result) => Response {
+            result,
+            ..Default::default()
+        },
+        Err(e) => Response {
+            error: Some(e.into()),
+            ..Default::default()
+        },
+    };
+
+    writeln!(stream, "{}", serde_json::to_string(&response)?)
+}
+
+/// Returns the uid of the process on the other end of the socket.
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let mut credentials = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut length = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+
+    /* SAFETY: the buffer and its length match the SO_PEERCRED option */
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut credentials as *mut libc::ucred as *mut libc::c_void,
+            &mut length,
+        )
+    };
+
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(credentials.uid)
+}
+
+/// Sends a request about the database at `path`, an absolute path, and
+/// returns the answer. Fails with the error of the vault in the agent, or
+/// with that of the socket when no agent is running.
+pub fn call<T: DeserializeOwned>(path: &Path, request: Request) -> io::Result<T> {
+    let mut stream = UnixStream::connect(socket_path())?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    let message = Message {
+        path: path.to_path_buf(),
+        request,
+    };
+    writeln!(stream, "{}", serde_json::to_string(&message)?)?;
+
+    let mut line = String::new();
+    BufReader::new(stream.take(MAX_MESSAGE_LEN)).read_line(&mut line)?;
+
+    let response: Response = serde_json::from_str(&line)?;
+    match response.error {
+        Some(failure) => Err(failure.into()),
+        None => Ok(serde_json::from_value(response.result)?),
+    }
+}
+
+/// Whether the agent holds the database at `path` unlocked, failing when
+/// no agent is running.
+pub fn status(path: &Path) -> io::Result<bool> {
+    call(path, Request::Status)
+}
+
+/// Hands a verified key to the agent, if one is running.
+pub fn unlock(vault: &Vault) {
+    let _ = call::<()>(
+        vault.path(),
+        Request::Unlock {
+            key: vault.key().to_string(),
+        },
+    );
+}
+
+/// Makes the agent close the vault. Fails when no agent is running.
+pub fn lock() -> io::Result<()> {
+    call(Path::new(""), Request::Lock)
+}
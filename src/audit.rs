@@ -0,0 +1,45 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const AUDIT_PATH: &str = "pwds.audit";
+
+/// Appends an event to `pwds.audit`, next to the database, as a line with
+/// the UTC time and the id of the user, e.g.
+/// `2026-10-19T07:30:00Z uid=1000 plaintext-export ...`.
+pub fn record(event: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .mode(0o600)
+        .open(AUDIT_PATH)?;
+
+    /* SAFETY: getuid cannot fail */
+    let uid = unsafe { libc::getuid() };
+
+    writeln!(file, "{} uid={uid} {event}", utc_now())
+}
+
+fn utc_now() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs()) as libc::time_t;
+
+    /* SAFETY: tm is plain data, and both pointers are valid for the call */
+    let tm = unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::gmtime_r(&now, &mut tm);
+        tm
+    };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec
+    )
+}
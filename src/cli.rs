@@ -0,0 +1,1650 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::os::fd::BorrowedFd;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+
+use crate::agent;
+use crate::audit::{self, AUDIT_PATH};
+use crate::bitwarden;
+use crate::config::Config;
+use crate::crypto::{adjust_password_length, gen_pwd_with_length};
+use crate::csv::{self, Field, Mapping, Preset, Table};
+use crate::git_credential::{self, Operation};
+use crate::import::{self, Import};
+use crate::kdbx::{self, Database, GroupMapping};
+use crate::otp::{self, Kind, Otp};
+use crate::pass;
+use crate::plaintext::{self, Format};
+use crate::portable;
+use crate::pwds::{self, parse_tags, Entry, FIELD_NAMES};
+use crate::search;
+#[cfg(feature = "gui")]
+use crate::secret_service;
+use crate::session::Session;
+use crate::template;
+use crate::throttle::{self, Refusal, Throttle};
+use crate::tui;
+use crate::vault;
+
+/// Simple pwds manager. Without a subcommand the graphical interface is started.
+#[derive(Parser)]
+#[command(name = "pwds", version)]
+pub struct Cli {
+    #[command(flatten)]
+    pub key_source: KeySource,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Where the encryption key is read from. By default it is prompted for
+/// on the terminal, without echo.
+#[derive(Args)]
+pub struct KeySource {
+    /// Read the encryption key from the first line of stdin
+    #[arg(long, global = true, conflicts_with = "key_fd")]
+    key_stdin: bool,
+
+    /// Read the encryption key from the first line of this file descriptor
+    #[arg(long, global = true, value_name = "FD", value_parser = parse_key_fd)]
+    key_fd: Option<i32>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Create an empty database
+    Init,
+    /// List the entries, optionally filtered by a fuzzy search
+    Ls {
+        query: Option<String>,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Show an entry, given by id, username or title
+    Show {
+        entry: String,
+        /// Print only the password, or include it in the JSON output
+        #[arg(long)]
+        password: bool,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Add an entry, the password is prompted for
+    Add {
+        username: String,
+        #[command(flatten)]
+        output: OutputArgs,
+        #[command(flatten)]
+        fields: FieldArgs,
+        #[command(flatten)]
+        password: PasswordArgs,
+        /// What to do when an entry with the same username and URL exists
+        #[arg(long, value_enum)]
+        on_duplicate: Option<OnDuplicate>,
+    },
+    /// Edit the fields of an entry, given by id, username or title
+    Edit {
+        entry: String,
+        #[arg(long)]
+        username: Option<String>,
+        #[command(flatten)]
+        fields: FieldArgs,
+        /// Prompt for a new password, implied by --generate and --password-stdin
+        #[arg(long)]
+        new_password: bool,
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+    /// Remove an entry, given by id, username or title
+    Rm { entry: String },
+    /// Print the current one-time password of an entry, given by id, username or title
+    Totp {
+        entry: String,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Print a random password
+    Generate {
+        #[arg(long, default_value_t = 14)]
+        length: usize,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Re-encrypt the database with a new key
+    Rekey,
+    /// Browse and edit the entries in a full-screen terminal interface
+    Tui,
+    /// Keep the database unlocked so that other pwds processes skip the prompt
+    Agent {
+        /// Seconds to keep it unlocked after its last use, 0 to keep it until locked
+        #[arg(long)]
+        ttl: Option<u64>,
+    },
+    /// Lock the database in the agent
+    Lock,
+    /// Serve the Secret Service D-Bus API so that libsecret clients use pwds
+    #[cfg(feature = "gui")]
+    SecretService,
+    /// Act as a git credential helper, see gitcredentials(7)
+    GitCredential {
+        #[arg(value_enum)]
+        operation: Operation,
+    },
+    /// Run a command with fields of entries in its environment
+    Run {
+        /// Set VAR to a field of an entry, by default its password
+        #[arg(long = "env", value_name = "VAR=ENTRY[/FIELD]", value_parser = parse_env)]
+        env: Vec<(String, String)>,
+        /// The command to run and its arguments, after --
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+    /// Print a file with its {{ pwds "entry" "field" }} references filled in
+    Template {
+        /// The file to render, stdin by default
+        file: Option<PathBuf>,
+    },
+    /// Add the entries of another password manager's database
+    Import {
+        #[command(subcommand)]
+        source: ImportSource,
+    },
+    /// Copy the encrypted database to a file, or convert it
+    Export {
+        output: PathBuf,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Pwds)]
+        format: ExportFormat,
+        /// Overwrite the output file
+        #[arg(long)]
+        force: bool,
+        /// Read the password of the new file from the first line of stdin, after the key with --key-stdin
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Write entries unencrypted to a file, after a confirmation and with the key typed again
+    ExportPlaintext {
+        output: PathBuf,
+        #[arg(long, value_enum, default_value_t = PlaintextFormat::Csv)]
+        format: PlaintextFormat,
+        /// Comma separated fields to write, all by default
+        #[arg(long, value_delimiter = ',', value_parser = FIELD_NAMES)]
+        fields: Vec<String>,
+        /// Overwrite the output file
+        #[arg(long)]
+        force: bool,
+        /// Do not ask for confirmation; the key is still read
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ImportSource {
+    /// A KeePass or KeePassXC database in the KDBX 4 format
+    Kdbx {
+        file: PathBuf,
+        /// How KeePass groups are kept, as tags
+        #[arg(long, value_enum, default_value_t = Groups::Folder)]
+        groups: Groups,
+        /// Read the master password from the first line of stdin, after the key with --key-stdin
+        #[arg(long)]
+        password_stdin: bool,
+        #[command(flatten)]
+        import: ImportArgs,
+    },
+    /// A Bitwarden export in the JSON format, plain or protected by a password
+    Bitwarden {
+        file: PathBuf,
+        /// Read the export password from the first line of stdin, after the key with --key-stdin
+        #[arg(long)]
+        password_stdin: bool,
+        #[command(flatten)]
+        import: ImportArgs,
+    },
+    /// An encrypted JSON export of pwds, see `export --format json`
+    Json {
+        file: PathBuf,
+        /// Read the passphrase from the first line of stdin, after the key with --key-stdin
+        #[arg(long)]
+        password_stdin: bool,
+        #[command(flatten)]
+        import: ImportArgs,
+    },
+    /// A pass password store, decrypted with gpg
+    Pass {
+        /// The store, $PASSWORD_STORE_DIR or ~/.password-store by default
+        dir: Option<PathBuf>,
+        #[command(flatten)]
+        import: ImportArgs,
+    },
+    /// A CSV file, like the exports of browsers and other password managers
+    Csv {
+        file: PathBuf,
+        /// Layout of the file, recognized from its header by default
+        #[arg(long, value_enum)]
+        preset: Option<CsvPreset>,
+        /// Import a column into a field, replacing the preset; columns not given are ignored
+        #[arg(long = "column", value_name = "FIELD=HEADER", value_parser = parse_column)]
+        columns: Vec<(Field, String)>,
+        #[command(flatten)]
+        import: ImportArgs,
+    },
+}
+
+#[derive(Args)]
+pub struct ImportArgs {
+    /// Print what would change without writing to the database
+    #[arg(long)]
+    dry_run: bool,
+    /// What to do with entries having the same username and URL as another, skipped by default
+    #[arg(long, value_enum)]
+    on_duplicate: Option<OnDuplicate>,
+}
+
+#[derive(Args)]
+pub struct OutputArgs {
+    /// Print the result, or the error, as JSON on stdout
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+pub struct FieldArgs {
+    #[arg(long)]
+    title: Option<String>,
+    #[arg(long)]
+    url: Option<String>,
+    /// Comma separated tags
+    #[arg(long)]
+    tags: Option<String>,
+    #[arg(long)]
+    notes: Option<String>,
+    /// An otpauth:// URI for one-time passwords, empty to remove them
+    #[arg(long, value_name = "URI")]
+    otp: Option<String>,
+}
+
+#[derive(Args)]
+pub struct PasswordArgs {
+    /// Generate a random password of this length instead of prompting
+    #[arg(long, value_name = "LENGTH", num_args = 0..=1, default_missing_value = "14")]
+    generate: Option<usize>,
+
+    /// Read the password from the first line of stdin instead of prompting
+    #[arg(long, conflicts_with = "generate")]
+    password_stdin: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OnDuplicate {
+    Merge,
+    Overwrite,
+    KeepBoth,
+}
+
+impl From<OnDuplicate> for import::OnDuplicate {
+    fn from(on_duplicate: OnDuplicate) -> Self {
+        match on_duplicate {
+            OnDuplicate::Merge => import::OnDuplicate::Merge,
+            OnDuplicate::Overwrite => import::OnDuplicate::Overwrite,
+            OnDuplicate::KeepBoth => import::OnDuplicate::KeepBoth,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// The encrypted database as it is
+    Pwds,
+    /// A KeePass database in the KDBX 4 format, protected by a new password
+    Kdbx,
+    /// An encrypted JSON document any later pwds can import, protected by its own passphrase
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum PlaintextFormat {
+    Csv,
+    Json,
+    Yaml,
+}
+
+impl From<PlaintextFormat> for Format {
+    fn from(format: PlaintextFormat) -> Self {
+        match format {
+            PlaintextFormat::Csv => Format::Csv,
+            PlaintextFormat::Json => Format::Json,
+            PlaintextFormat::Yaml => Format::Yaml,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Groups {
+    /// One tag holding the path of the group, like Internet/Email
+    Folder,
+    /// One tag per group on the path
+    Tags,
+    /// Drop the groups
+    None,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum CsvPreset {
+    Chrome,
+    Firefox,
+    Bitwarden,
+    #[value(name = "1password")]
+    OnePassword,
+    Lastpass,
+}
+
+impl From<CsvPreset> for Preset {
+    fn from(preset: CsvPreset) -> Self {
+        match preset {
+            CsvPreset::Chrome => Preset::Chrome,
+            CsvPreset::Firefox => Preset::Firefox,
+            CsvPreset::Bitwarden => Preset::Bitwarden,
+            CsvPreset::OnePassword => Preset::OnePassword,
+            CsvPreset::Lastpass => Preset::LastPass,
+        }
+    }
+}
+
+impl From<Groups> for GroupMapping {
+    fn from(groups: Groups) -> Self {
+        match groups {
+            Groups::Folder => GroupMapping::Folder,
+            Groups::Tags => GroupMapping::Tags,
+            Groups::None => GroupMapping::None,
+        }
+    }
+}
+
+/// Version of the JSON output, bumped on incompatible changes.
+const JSON_VERSION: u32 = 1;
+
+/// Why a subcommand failed. Each kind has its own exit code, part of the
+/// scripting interface like the JSON output. Exit code 2 is left to clap
+/// for invalid arguments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Invalid input, I/O error or anything else.
+    Failure,
+    /// No entry matches the given reference.
+    NotFound,
+    WrongKey,
+    /// Too many failed unlock attempts, see `Throttle`.
+    Locked,
+    /// Some entries cannot be decrypted with the right key.
+    CorruptVault,
+}
+
+impl ErrorKind {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Failure => 1,
+            ErrorKind::NotFound => 3,
+            ErrorKind::WrongKey => 4,
+            ErrorKind::Locked => 5,
+            ErrorKind::CorruptVault => 6,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ErrorKind::Failure => "failure",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::WrongKey => "wrong_key",
+            ErrorKind::Locked => "locked",
+            ErrorKind::CorruptVault => "corrupt_vault",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl Error {
+    fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// Wraps a database error, `InvalidData` meaning a corrupt vault.
+    fn io(context: &str, e: io::Error) -> Self {
+        let kind = match e.kind() {
+            io::ErrorKind::InvalidData => ErrorKind::CorruptVault,
+            io::ErrorKind::NotFound => ErrorKind::NotFound,
+            _ => ErrorKind::Failure,
+        };
+
+        Self::new(kind, format!("{context}: {e}"))
+    }
+
+    /// Prints the error on stderr, or as JSON on stdout.
+    pub fn report(&self, json: bool) {
+        if !json {
+            eprintln!("[-] {self}");
+            return;
+        }
+
+        print_json(&JsonError {
+            version: JSON_VERSION,
+            error: JsonErrorBody {
+                kind: self.kind.name(),
+                exit_code: self.kind.exit_code(),
+                message: &self.message,
+            },
+        });
+    }
+}
+
+impl From<Refusal> for Error {
+    fn from(refusal: Refusal) -> Self {
+        let kind = match &refusal {
+            Refusal::LockedOut | Refusal::Wiped | Refusal::TooSoon(_) => ErrorKind::Locked,
+            Refusal::WrongKey(_) => ErrorKind::WrongKey,
+            Refusal::Failed(e) if e.kind() == io::ErrorKind::InvalidData => ErrorKind::CorruptVault,
+            Refusal::Failed(e) if e.kind() == io::ErrorKind::NotFound => ErrorKind::NotFound,
+            Refusal::Failed(_) | Refusal::WipeFailed(_) => ErrorKind::Failure,
+        };
+
+        Self::new(kind, refusal.to_string())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Self::new(ErrorKind::Failure, message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Self::new(ErrorKind::Failure, message)
+    }
+}
+
+#[derive(Serialize)]
+struct JsonError<'a> {
+    version: u32,
+    error: JsonErrorBody<'a>,
+}
+
+#[derive(Serialize)]
+struct JsonErrorBody<'a> {
+    kind: &'static str,
+    exit_code: i32,
+    message: &'a str,
+}
+
+#[derive(Serialize)]
+struct JsonList<'a> {
+    version: u32,
+    count: usize,
+    entries: Vec<JsonEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonShow<'a> {
+    version: u32,
+    entry: JsonEntry<'a>,
+}
+
+#[derive(Serialize)]
+struct JsonAdded<'a> {
+    version: u32,
+    id: &'a str,
+}
+
+#[derive(Serialize)]
+struct JsonPassword<'a> {
+    version: u32,
+    password: &'a str,
+}
+
+#[derive(Serialize)]
+struct JsonCode<'a> {
+    version: u32,
+    code: &'a str,
+    /// Seconds the code is valid for, absent for HOTP.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remaining: Option<u64>,
+}
+
+/// An entry as printed by `--json`. The password and the `otpauth://` URI
+/// are only there when asked for.
+#[derive(Serialize)]
+struct JsonEntry<'a> {
+    id: &'a str,
+    title: &'a str,
+    username: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password: Option<&'a str>,
+    url: &'a str,
+    tags: &'a [String],
+    notes: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    otp: Option<&'a str>,
+}
+
+impl<'a> JsonEntry<'a> {
+    fn new(entry: &'a Entry, with_password: bool) -> Self {
+        Self {
+            id: &entry.id,
+            title: &entry.title,
+            username: &entry.username,
+            password: with_password.then_some(entry.password.as_str()),
+            url: &entry.url,
+            tags: &entry.tags,
+            notes: &entry.notes,
+            otp: (with_password && !entry.otp.is_empty()).then_some(entry.otp.as_str()),
+        }
+    }
+}
+
+fn print_json(value: &impl Serialize) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{json}"),
+        Err(e) => eprintln!("[-] Error while writing JSON: {e}"),
+    }
+}
+
+impl Command {
+    /// Whether the output, errors included, should be JSON.
+    pub fn json(&self) -> bool {
+        match self {
+            Command::Ls { output, .. }
+            | Command::Show { output, .. }
+            | Command::Add { output, .. }
+            | Command::Totp { output, .. }
+            | Command::Generate { output, .. } => output.json,
+            _ => false,
+        }
+    }
+}
+
+/// Runs a subcommand.
+pub fn run(command: Command, key_source: &KeySource) -> Result<(), Error> {
+    match command {
+        Command::Init => init(),
+        Command::Ls { query, output } => ls(&unlock(key_source)?, query.as_deref(), output.json),
+        Command::Show {
+            entry,
+            password,
+            output,
+        } => show(&unlock(key_source)?, &entry, password, output.json),
+        Command::Add {
+            username,
+            output,
+            fields,
+            password,
+            on_duplicate,
+        } => add(
+            &unlock(key_source)?,
+            username,
+            &fields,
+            &password,
+            on_duplicate,
+            output.json,
+        ),
+        Command::Edit {
+            entry,
+            username,
+            fields,
+            new_password,
+            password,
+        } => {
+            let change_password =
+                new_password || password.generate.is_some() || password.password_stdin;
+
+            edit(
+                &unlock(key_source)?,
+                &entry,
+                username,
+                &fields,
+                change_password.then_some(&password),
+            )
+        }
+        Command::Rm { entry } => rm(&unlock(key_source)?, &entry),
+        Command::Totp { entry, output } => totp(&unlock(key_source)?, &entry, output.json),
+        Command::Generate { length, output } => {
+            let password = gen_pwd_with_length(length)?;
+
+            if output.json {
+                print_json(&JsonPassword {
+                    version: JSON_VERSION,
+                    password: &password,
+                });
+            } else {
+                println!("{password}");
+            }
+            Ok(())
+        }
+        Command::Rekey => rekey(unlock(key_source)?),
+        Command::Tui => tui::run(unlock(key_source)?)
+            .map_err(|e| format!("Error in the terminal interface: {e}").into()),
+        Command::Agent { ttl } => {
+            let ttl = ttl.unwrap_or_else(|| Config::load().agent_ttl);
+            agent::serve(Duration::from_secs(ttl)).map_err(|e| format!("Agent error: {e}").into())
+        }
+        Command::Lock => {
+            agent::lock().map_err(|e| format!("Cannot reach the agent: {e}"))?;
+            println!("[+] Agent locked.");
+            Ok(())
+        }
+        #[cfg(feature = "gui")]
+        Command::SecretService => {
+            secret_service::run().map_err(|e| format!("Secret Service error: {e}").into())
+        }
+        Command::GitCredential { operation } => {
+            /* git writes the credential on stdin */
+            if key_source.key_stdin {
+                return Err("--key-stdin cannot be used with git-credential.".into());
+            }
+
+            git_credential::run(operation, &unlock(key_source)?)
+                .map_err(|e| Error::io("Error in the git credential helper", e))
+        }
+        Command::Run { env, command } => run_with_env(&unlock(key_source)?, &env, &command),
+        Command::Template { file } => render_template(&unlock(key_source)?, file.as_deref()),
+        Command::Import { source } => match source {
+            ImportSource::Kdbx {
+                file,
+                groups,
+                password_stdin,
+                import,
+            } => {
+                let session = unlock(key_source)?;
+                import_kdbx(&session, &file, groups.into(), password_stdin, &import)
+            }
+            ImportSource::Bitwarden {
+                file,
+                password_stdin,
+                import,
+            } => {
+                let session = unlock(key_source)?;
+                import_bitwarden(&session, &file, password_stdin, &import)
+            }
+            ImportSource::Json {
+                file,
+                password_stdin,
+                import,
+            } => {
+                let session = unlock(key_source)?;
+                import_json(&session, &file, password_stdin, &import)
+            }
+            ImportSource::Pass { dir, import } => {
+                let session = unlock(key_source)?;
+                import_pass(&session, dir, &import)
+            }
+            ImportSource::Csv {
+                file,
+                preset,
+                columns,
+                import,
+            } => {
+                let session = unlock(key_source)?;
+                import_csv(&session, &file, preset.map(Into::into), &columns, &import)
+            }
+        },
+        Command::Export {
+            output,
+            format: ExportFormat::Pwds,
+            force,
+            ..
+        } => export(&output, force),
+        Command::Export {
+            output,
+            format: ExportFormat::Kdbx,
+            force,
+            password_stdin,
+        } => export_kdbx(&unlock(key_source)?, &output, force, password_stdin),
+        Command::Export {
+            output,
+            format: ExportFormat::Json,
+            force,
+            password_stdin,
+        } => export_json(&unlock(key_source)?, &output, force, password_stdin),
+        Command::ExportPlaintext {
+            output,
+            format,
+            fields,
+            force,
+            yes,
+        } => export_plaintext(key_source, &output, format.into(), &fields, force, yes),
+    }
+}
+
+/// Reads the key and checks it, with the same throttling as the GUI.
+/// A running agent answers instead if another process unlocked the vault.
+/// The vault is checked too, so that commands never work on part of it.
+fn unlock(key_source: &KeySource) -> Result<Session, Error> {
+    check_key(key_source, true)
+}
+
+/// Like `unlock`, but the key is read even when the agent holds it, for
+/// commands that must not rely on an earlier unlock.
+fn reenter_key(key_source: &KeySource) -> Result<Session, Error> {
+    check_key(key_source, false)
+}
+
+fn check_key(key_source: &KeySource, use_agent: bool) -> Result<Session, Error> {
+    if !pwds::is_db_file() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            "No database found, create one with `pwds init`.",
+        ));
+    }
+
+    /* Refuse before prompting for a key */
+    if Throttle::load().is_locked_out(Config::load().max_unlock_attempts) {
+        return Err(Refusal::LockedOut.into());
+    }
+
+    if let Some(session) = use_agent.then(Session::from_agent).flatten() {
+        session
+            .check()
+            .map_err(|e| Error::io("The database is corrupt", e))?;
+        return Ok(session);
+    }
+
+    let key = read_key(key_source)?;
+
+    match throttle::unlock(&key) {
+        /* Wait out the back-off instead of failing, scripts retry anyway */
+        Err(Refusal::TooSoon(delay)) => {
+            eprintln!("[-] Too many failed attempts, waiting {delay}s.");
+            thread::sleep(Duration::from_secs(delay));
+            throttle::unlock(&key)
+        }
+        result => result,
+    }
+    .map_err(Error::from)
+}
+
+fn read_key(key_source: &KeySource) -> Result<String, Error> {
+    let key = if key_source.key_stdin {
+        read_line(io::stdin().lock())
+    } else if let Some(fd) = key_source.key_fd {
+        /* SAFETY: the caller keeps the descriptor open while we run; it stays
+         * theirs, we only read from a duplicate of it */
+        let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+        fd.try_clone_to_owned()
+            .and_then(|fd| read_line(BufReader::new(File::from(fd))))
+    } else {
+        rpassword::prompt_password("Encryption key: ")
+    }
+    .map_err(|e| format!("Cannot read the encryption key: {e}"))?;
+
+    if key.is_empty() {
+        return Err("Invalid password provided.".into());
+    }
+
+    Ok(key)
+}
+
+/// Reads one line, without its line ending.
+fn read_line(mut reader: impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Gets the password of a new or edited entry.
+fn read_password(password: &PasswordArgs) -> Result<String, Error> {
+    if let Some(length) = password.generate {
+        return Ok(gen_pwd_with_length(length)?.to_string());
+    }
+
+    if password.password_stdin {
+        return read_line(io::stdin().lock())
+            .map_err(|e| format!("Cannot read the password: {e}").into());
+    }
+
+    let first = rpassword::prompt_password("Password: ")
+        .map_err(|e| format!("Cannot read the password: {e}"))?;
+    let second = rpassword::prompt_password("Repeat password: ")
+        .map_err(|e| format!("Cannot read the password: {e}"))?;
+
+    if first != second {
+        return Err("The passwords do not match.".into());
+    }
+
+    Ok(first)
+}
+
+/// Finds the entry a reference points to: an id, else a username or title.
+fn resolve(session: &Session, reference: &str) -> Result<Entry, Error> {
+    let entries = session
+        .entries()
+        .map_err(|e| Error::io("Error while reading database", e))?;
+
+    find_entry(&entries, reference).cloned()
+}
+
+/// Finds the entry a reference points to among loaded entries.
+fn find_entry<'a>(entries: &'a [Entry], reference: &str) -> Result<&'a Entry, Error> {
+    if let Some(entry) = entries.iter().find(|entry| entry.id == reference) {
+        return Ok(entry);
+    }
+
+    let matches: Vec<&Entry> = entries
+        .iter()
+        .filter(|entry| entry.username == reference || entry.title == reference)
+        .collect();
+
+    match matches.as_slice() {
+        [entry] => Ok(entry),
+        [] => Err(Error::new(
+            ErrorKind::NotFound,
+            format!("No entry matches {reference}."),
+        )),
+        _ => {
+            let ids: Vec<&str> = matches.iter().map(|entry| entry.id.as_str()).collect();
+            Err(format!(
+                "Several entries match {reference}, use one of the ids: {}",
+                ids.join(", ")
+            )
+            .into())
+        }
+    }
+}
+
+/// Checks the `--key-fd` argument, which cannot be one of the standard
+/// streams: use `--key-stdin` for standard input.
+fn parse_key_fd(text: &str) -> Result<i32, String> {
+    match text.parse() {
+        Ok(fd) if fd > 2 => Ok(fd),
+        Ok(_) => Err("expected a descriptor above 2, use --key-stdin for stdin".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Splits a `VAR=ENTRY[/FIELD]` argument of `run`.
+fn parse_env(text: &str) -> Result<(String, String), String> {
+    match text.split_once('=') {
+        Some((name, reference)) if !name.is_empty() && !reference.is_empty() => {
+            Ok((name.to_string(), reference.to_string()))
+        }
+        _ => Err("expected VAR=ENTRY[/FIELD]".to_string()),
+    }
+}
+
+/// Splits a `FIELD=HEADER` argument of `import csv`.
+fn parse_column(text: &str) -> Result<(Field, String), String> {
+    let (field, header) = text
+        .split_once('=')
+        .filter(|(_, header)| !header.is_empty())
+        .ok_or("expected FIELD=HEADER")?;
+
+    let field = Field::from_name(field).ok_or_else(|| {
+        let names: Vec<_> = Field::ALL.iter().map(|field| field.name()).collect();
+        format!(
+            "unknown field {field}, expected one of {}",
+            names.join(", ")
+        )
+    })?;
+
+    Ok((field, header.to_string()))
+}
+
+/// Returns a field of the entry an `ENTRY[/FIELD]` reference points to.
+/// What follows the last slash is only a field when it names one, so that
+/// titles holding a slash can be used alone.
+fn lookup(entries: &[Entry], reference: &str) -> Result<String, Error> {
+    let (reference, field) = match reference.rsplit_once('/') {
+        Some((entry, field)) if FIELD_NAMES.contains(&field) => (entry, field),
+        _ => (reference, "password"),
+    };
+
+    lookup_field(entries, reference, field)
+}
+
+fn lookup_field(entries: &[Entry], reference: &str, field: &str) -> Result<String, Error> {
+    find_entry(entries, reference)?.field(field).ok_or_else(|| {
+        format!("Unknown field {field}, use one of {}.", FIELD_NAMES.join(", ")).into()
+    })
+}
+
+/// Runs a command with entry fields in its environment, then exits with
+/// its exit code. The secrets only live in the memory of both processes.
+fn run_with_env(
+    session: &Session,
+    env: &[(String, String)],
+    command: &[String],
+) -> Result<(), Error> {
+    let entries = session
+        .entries()
+        .map_err(|e| Error::io("Error while reading database", e))?;
+
+    let mut child = process::Command::new(&command[0]);
+    child.args(&command[1..]);
+    for (name, reference) in env {
+        child.env(name, lookup(&entries, reference)?);
+    }
+    drop(entries);
+
+    let status = child
+        .status()
+        .map_err(|e| format!("Cannot run {}: {e}", command[0]))?;
+
+    /* Report a signal the way shells do */
+    process::exit(
+        status
+            .code()
+            .unwrap_or_else(|| 128 + status.signal().unwrap_or_default()),
+    )
+}
+
+/// Prints a template with its references to entries filled in, see
+/// `template::render`. Nothing is printed when a reference is wrong.
+fn render_template(session: &Session, file: Option<&Path>) -> Result<(), Error> {
+    let text = match file {
+        Some(file) => fs::read_to_string(file),
+        None => io::read_to_string(io::stdin()),
+    }
+    .map_err(|e| format!("Cannot read the template: {e}"))?;
+
+    let entries = session
+        .entries()
+        .map_err(|e| Error::io("Error while reading database", e))?;
+
+    let mut kind = ErrorKind::Failure;
+    let rendered = template::render(&text, |reference, field| {
+        lookup_field(&entries, reference, field).map_err(|e| {
+            kind = e.kind;
+            e.message
+        })
+    })
+    .map_err(|message| Error::new(kind, message))?;
+
+    print!("{rendered}");
+    Ok(())
+}
+
+fn init() -> Result<(), Error> {
+    if pwds::is_db_file() {
+        return Err(format!("{} already exists.", pwds::DB_PATH).into());
+    }
+
+    File::create(pwds::DB_PATH).map_err(|e| format!("Error during database file creation: {e}"))?;
+    println!("[+] Database file created successfully.");
+
+    Ok(())
+}
+
+fn ls(session: &Session, query: Option<&str>, json: bool) -> Result<(), Error> {
+    let entries = session
+        .entry_list()
+        .map_err(|e| Error::io("Error while reading database", e))?;
+
+    let entries: Vec<&Entry> = entries
+        .iter()
+        .filter(|entry| search::matches(query.unwrap_or_default(), entry))
+        .collect();
+
+    if json {
+        print_json(&JsonList {
+            version: JSON_VERSION,
+            count: entries.len(),
+            entries: entries.iter().map(|entry| JsonEntry::new(entry, false)).collect(),
+        });
+        return Ok(());
+    }
+
+    for entry in entries {
+        println!(
+            "{}  {:<24}  {:<24}  {}",
+            entry.id, entry.title, entry.username, entry.url
+        );
+    }
+
+    Ok(())
+}
+
+fn show(session: &Session, reference: &str, password: bool, json: bool) -> Result<(), Error> {
+    let entry = resolve(session, reference)?;
+
+    if json {
+        print_json(&JsonShow {
+            version: JSON_VERSION,
+            entry: JsonEntry::new(&entry, password),
+        });
+        return Ok(());
+    }
+
+    if password {
+        println!("{}", entry.password);
+        return Ok(());
+    }
+
+    println!("id:       {}", entry.id);
+    println!("title:    {}", entry.title);
+    println!("username: {}", entry.username);
+    println!("password: ******** (use --password to print it)");
+    println!("url:      {}", entry.url);
+    println!("tags:     {}", entry.tags.join(", "));
+    println!("notes:    {}", entry.notes);
+
+    if let Ok(otp) = Otp::parse(&entry.otp) {
+        let kind = match otp.kind {
+            Kind::Totp { period } => format!("TOTP every {period}s"),
+            Kind::Hotp { counter } => format!("HOTP at counter {counter}"),
+        };
+        println!("otp:      {kind}, {} digits (use pwds totp)", otp.digits);
+    }
+
+    Ok(())
+}
+
+fn add(
+    session: &Session,
+    username: String,
+    fields: &FieldArgs,
+    password: &PasswordArgs,
+    on_duplicate: Option<OnDuplicate>,
+    json: bool,
+) -> Result<(), Error> {
+    let entry = Entry {
+        id: String::new(),
+        title: fields.title.clone().unwrap_or_default(),
+        username,
+        password: read_password(password)?,
+        url: fields.url.clone().unwrap_or_default(),
+        tags: parse_tags(fields.tags.as_deref().unwrap_or_default()),
+        notes: fields.notes.clone().unwrap_or_default(),
+        otp: fields.otp.clone().unwrap_or_default(),
+    };
+
+    entry.validate()?;
+
+    let duplicate = session
+        .find_duplicate(&entry)
+        .map_err(|e| Error::io("Error while reading database", e))?;
+
+    let result = match (duplicate, on_duplicate) {
+        (None, _) | (Some(_), Some(OnDuplicate::KeepBoth)) => session.add(&entry),
+        (Some(existing), Some(OnDuplicate::Merge)) => {
+            let mut merged = existing.clone();
+            merged.merge(&entry);
+            session.update(&existing.id, &merged).map(|()| existing.id)
+        }
+        (Some(existing), Some(OnDuplicate::Overwrite)) => {
+            session.update(&existing.id, &entry).map(|()| existing.id)
+        }
+        (Some(existing), None) => {
+            return Err(format!(
+                "Entry {} has the same username and URL, \
+                 use --on-duplicate merge, overwrite or keep-both.",
+                existing.id
+            )
+            .into())
+        }
+    };
+
+    let id = result.map_err(|e| Error::io("Error while saving the entry", e))?;
+
+    if json {
+        print_json(&JsonAdded {
+            version: JSON_VERSION,
+            id: &id,
+        });
+    } else {
+        println!("[+] Password successfully added to database: {id}");
+    }
+
+    Ok(())
+}
+
+fn edit(
+    session: &Session,
+    reference: &str,
+    username: Option<String>,
+    fields: &FieldArgs,
+    password: Option<&PasswordArgs>,
+) -> Result<(), Error> {
+    let mut entry = resolve(session, reference)?;
+
+    if let Some(username) = username {
+        entry.username = username;
+    }
+    if let Some(title) = &fields.title {
+        entry.title = title.clone();
+    }
+    if let Some(url) = &fields.url {
+        entry.url = url.clone();
+    }
+    if let Some(tags) = &fields.tags {
+        entry.tags = parse_tags(tags);
+    }
+    if let Some(notes) = &fields.notes {
+        entry.notes = notes.clone();
+    }
+    if let Some(otp) = &fields.otp {
+        entry.otp = otp.clone();
+    }
+    if let Some(password) = password {
+        entry.password = read_password(password)?;
+    }
+
+    entry.validate()?;
+
+    session
+        .update(&entry.id, &entry)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => {
+                "Another entry already uses this username and URL.".into()
+            }
+            _ => Error::io("Error while saving the entry", e),
+        })?;
+    println!("[+] Entry successfully modified.");
+
+    Ok(())
+}
+
+fn rm(session: &Session, reference: &str) -> Result<(), Error> {
+    let entry = resolve(session, reference)?;
+
+    session
+        .remove(&entry.id)
+        .map_err(|e| Error::io("Error while removing the entry", e))?;
+    println!("[+] Successfully removed {}.", entry.id);
+
+    Ok(())
+}
+
+/// Prints the current one-time password of an entry. The counter of an
+/// HOTP generator is moved on and stored before its code is printed, so
+/// that no code is printed twice.
+fn totp(session: &Session, reference: &str, json: bool) -> Result<(), Error> {
+    let entry = resolve(session, reference)?;
+
+    if entry.otp.is_empty() {
+        return Err(format!(
+            "{reference} has no one-time passwords, add an otpauth:// URI with `pwds edit --otp`."
+        )
+        .into());
+    }
+
+    let mut otp =
+        Otp::parse(&entry.otp).map_err(|e| format!("Invalid one-time password URI: {e}."))?;
+    let time = otp::now();
+    let code = otp.code(time);
+
+    let remaining = match otp.kind {
+        Kind::Totp { .. } => Some(otp.remaining(time)),
+        Kind::Hotp { .. } => {
+            otp.advance();
+            session
+                .set_otp(&entry.id, &otp.to_uri())
+                .map_err(|e| Error::io("Error while saving the HOTP counter", e))?;
+            None
+        }
+    };
+
+    if json {
+        print_json(&JsonCode {
+            version: JSON_VERSION,
+            code: &code,
+            remaining,
+        });
+    } else {
+        println!("{code}");
+    }
+
+    Ok(())
+}
+
+fn rekey(mut session: Session) -> Result<(), Error> {
+    let new_key = rpassword::prompt_password("New encryption key: ")
+        .map_err(|e| format!("Cannot read the encryption key: {e}"))?;
+    let repeated = rpassword::prompt_password("Repeat new encryption key: ")
+        .map_err(|e| format!("Cannot read the encryption key: {e}"))?;
+
+    if new_key.is_empty() || new_key != repeated {
+        return Err("The new keys are empty or do not match.".into());
+    }
+
+    session
+        .rekey(&adjust_password_length(&new_key))
+        .map_err(|e| Error::io("Error while re-encrypting the database", e))?;
+    println!("[+] Database re-encrypted with the new key.");
+
+    Ok(())
+}
+
+/// Reads a KeePass database, then imports its entries.
+fn import_kdbx(
+    session: &Session,
+    file: &Path,
+    groups: GroupMapping,
+    password_stdin: bool,
+    args: &ImportArgs,
+) -> Result<(), Error> {
+    let password = read_master_password("KeePass", password_stdin, false)?;
+
+    let database = kdbx::open(file, &password).map_err(|e| read_error(file, e))?;
+
+    import_entries(session, database.entries(groups), args)
+}
+
+/// Reads a Bitwarden export, asking for its password when it has one,
+/// then imports its items.
+fn import_bitwarden(
+    session: &Session,
+    file: &Path,
+    password_stdin: bool,
+    args: &ImportArgs,
+) -> Result<(), Error> {
+    let export = bitwarden::open(file, || {
+        if password_stdin {
+            read_line(io::stdin().lock())
+        } else {
+            rpassword::prompt_password("Export password: ")
+        }
+    })
+    .map_err(|e| read_error(file, e))?;
+
+    import_entries(session, export.entries(), args)
+}
+
+/// Decrypts an export made by `export --format json`, then imports it.
+fn import_json(
+    session: &Session,
+    file: &Path,
+    password_stdin: bool,
+    args: &ImportArgs,
+) -> Result<(), Error> {
+    let passphrase = read_master_password("Export", password_stdin, false)?;
+
+    let entries = portable::open(file, &passphrase).map_err(|e| read_error(file, e))?;
+
+    import_entries(session, entries, args)
+}
+
+/// Decrypts the files of a pass store, then imports them. Files gpg cannot
+/// decrypt are listed and skipped.
+fn import_pass(session: &Session, dir: Option<PathBuf>, args: &ImportArgs) -> Result<(), Error> {
+    let dir = dir
+        .or_else(|| std::env::var_os("PASSWORD_STORE_DIR").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".password-store")))
+        .ok_or("No password store given and HOME is not set.")?;
+
+    let store = pass::open(&dir).map_err(|e| read_error(&dir, e))?;
+
+    for (file, reason) in &store.failed {
+        println!("skip    {}: {reason}", file.display());
+    }
+
+    import_entries(session, store.entries, args)
+}
+
+/// Reads a CSV file, then imports its rows. The columns are those given,
+/// or those of the preset, or of the layout recognized from the header.
+fn import_csv(
+    session: &Session,
+    file: &Path,
+    preset: Option<Preset>,
+    columns: &[(Field, String)],
+    args: &ImportArgs,
+) -> Result<(), Error> {
+    let table = Table::read(file).map_err(|e| read_error(file, e))?;
+
+    let mapping = if columns.is_empty() {
+        let preset = preset.or_else(|| Preset::detect(&table.headers)).ok_or_else(|| {
+            format!(
+                "Unknown layout, give it with --preset or map the columns with --column. The columns are: {}.",
+                table.headers.join(", ")
+            )
+        })?;
+        preset.mapping(&table.headers)
+    } else {
+        let mut mapping = Mapping(vec![None; table.headers.len()]);
+        for (field, header) in columns {
+            let index = csv::column(&table.headers, header)
+                .ok_or_else(|| format!("No column named {header} in {}.", file.display()))?;
+            mapping.0[index] = Some(*field);
+        }
+        mapping
+    };
+
+    if !mapping.has(Field::Password) {
+        return Err("No column is imported as the password.".into());
+    }
+
+    for (line, reason) in &table.malformed {
+        println!("skip    line {line}: {reason}");
+    }
+
+    import_entries(session, table.entries(&mapping), args)
+}
+
+/// The error of a file to import that cannot be read. Readers of protected
+/// files fail with `PermissionDenied` when their password is wrong, unlike
+/// the system, which gives an error code.
+fn read_error(path: &Path, e: io::Error) -> Error {
+    match e.kind() {
+        io::ErrorKind::PermissionDenied if e.raw_os_error().is_none() => {
+            Error::new(ErrorKind::WrongKey, format!("{e}."))
+        }
+        io::ErrorKind::NotFound => Error::new(
+            ErrorKind::NotFound,
+            format!("Cannot read {}: {e}", path.display()),
+        ),
+        _ => format!("Cannot read {}: {e}", path.display()).into(),
+    }
+}
+
+/// Reads the master password of a KeePass database or of an export, twice
+/// for a new one.
+fn read_master_password(owner: &str, password_stdin: bool, new: bool) -> Result<String, Error> {
+    if password_stdin {
+        return read_line(io::stdin().lock())
+            .map_err(|e| format!("Cannot read the master password: {e}").into());
+    }
+
+    let password = rpassword::prompt_password(format!("{owner} master password: "))
+        .map_err(|e| format!("Cannot read the master password: {e}"))?;
+
+    if new {
+        let repeated = rpassword::prompt_password("Repeat master password: ")
+            .map_err(|e| format!("Cannot read the master password: {e}"))?;
+
+        if password != repeated {
+            return Err("The passwords do not match.".into());
+        }
+    }
+
+    Ok(password)
+}
+
+/// Adds entries read from another password manager, see `Import::plan`.
+/// With --dry-run the changes are only listed.
+fn import_entries(session: &Session, entries: Vec<Entry>, args: &ImportArgs) -> Result<(), Error> {
+    let stored = session
+        .entries()
+        .map_err(|e| Error::io("Error while reading database", e))?;
+
+    let on_duplicate = args.on_duplicate.map(Into::into).unwrap_or_default();
+    let import = Import::plan(&stored, entries, on_duplicate);
+
+    if args.dry_run {
+        for entry in &import.added {
+            println!("add     {}", describe(entry));
+        }
+        for entry in &import.updated {
+            println!("update  {}", describe(entry));
+        }
+    }
+    for (entry, reason) in &import.skipped {
+        println!("skip    {}: {reason}", describe(entry));
+    }
+
+    if args.dry_run {
+        println!(
+            "[+] Dry run: {} entries would be added, {} updated and {} skipped.",
+            import.added.len(),
+            import.updated.len(),
+            import.skipped.len()
+        );
+        return Ok(());
+    }
+
+    session
+        .save_all(&import.updated, &import.added)
+        .map_err(|e| Error::io("Error while importing", e))?;
+    println!(
+        "[+] {} entries added, {} updated and {} skipped.",
+        import.added.len(),
+        import.updated.len(),
+        import.skipped.len()
+    );
+
+    Ok(())
+}
+
+/// Names an entry in listings: its title, or URL, and its username.
+fn describe(entry: &Entry) -> String {
+    let name = if entry.title.is_empty() {
+        &entry.url
+    } else {
+        &entry.title
+    };
+
+    if entry.username.is_empty() {
+        name.clone()
+    } else {
+        format!("{name} ({})", entry.username)
+    }
+}
+
+/// Writes every entry to a new KeePass database, see `Database::save`.
+fn export_kdbx(
+    session: &Session,
+    output: &Path,
+    force: bool,
+    password_stdin: bool,
+) -> Result<(), Error> {
+    if output.exists() && !force {
+        return Err(format!("{} already exists, use --force.", output.display()).into());
+    }
+
+    let password = read_master_password("KeePass", password_stdin, true)?;
+    if password.is_empty() {
+        return Err("The master password cannot be empty.".into());
+    }
+
+    let entries = session
+        .entries()
+        .map_err(|e| Error::io("Error while reading database", e))?;
+
+    Database::from_entries("pwds", &entries)
+        .save(output, &password)
+        .map_err(|e| format!("Error while exporting: {e}"))?;
+    println!(
+        "[+] {} entries exported to {}.",
+        entries.len(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+/// Writes every entry to an encrypted JSON export, see `portable::save`.
+fn export_json(
+    session: &Session,
+    output: &Path,
+    force: bool,
+    password_stdin: bool,
+) -> Result<(), Error> {
+    if output.exists() && !force {
+        return Err(format!("{} already exists, use --force.", output.display()).into());
+    }
+
+    let passphrase = read_master_password("Export", password_stdin, true)?;
+    if passphrase.is_empty() {
+        return Err("The master password cannot be empty.".into());
+    }
+
+    let entries = session
+        .entries()
+        .map_err(|e| Error::io("Error while reading database", e))?;
+
+    portable::save(output, &entries, &passphrase)
+        .map_err(|e| format!("Error while exporting: {e}"))?;
+    println!(
+        "[+] {} entries exported to {}.",
+        entries.len(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+/// Writes entries unencrypted, see `plaintext::save`, once the key is typed
+/// again and the export confirmed. The export is recorded in the audit log
+/// before anything is written, and refused if it cannot be.
+fn export_plaintext(
+    key_source: &KeySource,
+    output: &Path,
+    format: Format,
+    fields: &[String],
+    force: bool,
+    yes: bool,
+) -> Result<(), Error> {
+    if output.exists() && !force {
+        return Err(format!("{} already exists, use --force.", output.display()).into());
+    }
+
+    let session = reenter_key(key_source)?;
+    let entries = session
+        .entries()
+        .map_err(|e| Error::io("Error while reading database", e))?;
+
+    let fields: Vec<&str> = if fields.is_empty() {
+        FIELD_NAMES.to_vec()
+    } else {
+        fields.iter().map(String::as_str).collect()
+    };
+
+    eprintln!(
+        "[!] The {} of {} entries will be written unencrypted to {}. \
+         Anyone who can read the file can read them.",
+        fields.join(", "),
+        entries.len(),
+        output.display()
+    );
+
+    if !yes {
+        eprint!("Type yes to export: ");
+        let answer = read_line(io::stdin().lock())
+            .map_err(|e| format!("Cannot read the confirmation: {e}"))?;
+
+        if answer.trim() != "yes" {
+            return Err("Export cancelled.".into());
+        }
+    }
+
+    let path = std::path::absolute(output).unwrap_or_else(|_| output.to_path_buf());
+    audit::record(&format!(
+        "plaintext-export interface=cli format={} entries={} fields={} output={:?}",
+        format.extension(),
+        entries.len(),
+        fields.join(","),
+        path
+    ))
+    .map_err(|e| format!("Cannot write {AUDIT_PATH}, nothing was exported: {e}"))?;
+
+    plaintext::save(output, &entries, &fields, format, force)
+        .map_err(|e| format!("Error while exporting: {e}"))?;
+    println!(
+        "[+] {} entries exported to {}.",
+        entries.len(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+fn export(output: &Path, force: bool) -> Result<(), Error> {
+    if !pwds::is_db_file() {
+        return Err(Error::new(ErrorKind::NotFound, "No database found."));
+    }
+
+    vault::export(Path::new(pwds::DB_PATH), output, force).map_err(|e| match e.kind() {
+        io::ErrorKind::AlreadyExists => format!("{} already exists, use --force.", output.display()),
+        _ => format!("Error while exporting: {e}"),
+    })?;
+    println!("[+] Database exported to {}.", output.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes() {
+        let codes = [
+            (ErrorKind::Failure, 1),
+            (ErrorKind::NotFound, 3),
+            (ErrorKind::WrongKey, 4),
+            (ErrorKind::Locked, 5),
+            (ErrorKind::CorruptVault, 6),
+        ];
+        for (kind, code) in codes {
+            assert_eq!(kind.exit_code(), code, "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn refusal_kinds() {
+        let kind = |refusal| Error::from(refusal).kind;
+        let failed = |kind| Refusal::Failed(io::Error::new(kind, "test"));
+
+        assert_eq!(kind(Refusal::WrongKey(2)), ErrorKind::WrongKey);
+        assert_eq!(kind(Refusal::LockedOut), ErrorKind::Locked);
+        assert_eq!(kind(Refusal::Wiped), ErrorKind::Locked);
+        assert_eq!(kind(Refusal::TooSoon(8)), ErrorKind::Locked);
+        assert_eq!(
+            kind(failed(io::ErrorKind::InvalidData)),
+            ErrorKind::CorruptVault
+        );
+        assert_eq!(kind(failed(io::ErrorKind::NotFound)), ErrorKind::NotFound);
+        assert_eq!(kind(failed(io::ErrorKind::Other)), ErrorKind::Failure);
+    }
+
+    #[test]
+    fn database_error_kinds() {
+        let kind = |kind| Error::io("Error", io::Error::new(kind, "test")).kind;
+
+        assert_eq!(kind(io::ErrorKind::InvalidData), ErrorKind::CorruptVault);
+        assert_eq!(kind(io::ErrorKind::NotFound), ErrorKind::NotFound);
+        assert_eq!(kind(io::ErrorKind::PermissionDenied), ErrorKind::Failure);
+    }
+
+    #[test]
+    fn missing_entries() {
+        let entry = |id: &str, username: &str| Entry {
+            id: id.to_string(),
+            username: username.to_string(),
+            ..Default::default()
+        };
+        let entries = [entry("a1", "alice"), entry("b1", "bob"), entry("b2", "bob")];
+
+        assert_eq!(find_entry(&entries, "alice").unwrap().id, "a1");
+        assert_eq!(find_entry(&entries, "b2").unwrap().id, "b2");
+        assert_eq!(
+            find_entry(&entries, "carol").unwrap_err().kind,
+            ErrorKind::NotFound
+        );
+        /* Ambiguous references are no missing entry */
+        assert_eq!(
+            find_entry(&entries, "bob").unwrap_err().kind,
+            ErrorKind::Failure
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::Path;
+
+pub const CONFIG_PATH: &str = "pwds.conf";
+
+/// What happens once `max_unlock_attempts` is reached.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LockoutAction {
+    /// Refuse every unlock until `pwds.state` is removed.
+    #[default]
+    Lock,
+    /// Overwrite and delete the database.
+    Wipe,
+}
+
+/// User settings, read from `pwds.conf` as `key = value` lines.
+/// Lines starting with `#` are comments.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Seconds before a copied password is cleared from the clipboard, 0 to keep it.
+    pub clipboard_timeout: u32,
+    /// Minutes without activity before the vault gets locked, 0 to never lock.
+    pub autolock_minutes: u32,
+    /// Lock when the window is minimized.
+    pub lock_on_minimize: bool,
+    /// Hide the window on close instead of quitting; hiding locks the vault.
+    pub hide_on_close: bool,
+    /// Failed unlock attempts before `lockout_action` applies, 0 for no limit.
+    pub max_unlock_attempts: u32,
+    pub lockout_action: LockoutAction,
+    /// Seconds `pwds agent` keeps the database unlocked after its last use, 0 to keep it until locked.
+    pub agent_ttl: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            clipboard_timeout: 30,
+            autolock_minutes: 5,
+            lock_on_minimize: true,
+            hide_on_close: false,
+            max_unlock_attempts: 0,
+            lockout_action: LockoutAction::Lock,
+            agent_ttl: 600,
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration file, falling back to the defaults
+    /// for a missing file and for missing or invalid values.
+    pub fn load() -> Self {
+        let mut config = Self::default();
+
+        if !Path::new(CONFIG_PATH).exists() {
+            return config;
+        }
+
+        let contents = match fs::read_to_string(CONFIG_PATH) {
+            Ok(contents) => contents,
+            Err(e) => {
+                eprintln!("[-] Error while reading {CONFIG_PATH}: {e}");
+                return config;
+            }
+        };
+
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((name, value)) = line.split_once('=') else {
+                eprintln!("[-] Invalid line in {CONFIG_PATH}: {line}");
+                continue;
+            };
+
+            if let Err(e) = config.set(name.trim(), value.trim()) {
+                eprintln!("[-] Invalid value for {} in {CONFIG_PATH}: {e}", name.trim());
+            }
+        }
+
+        config
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "clipboard_timeout" => {
+                self.clipboard_timeout = value.parse().map_err(|e| format!("{e}"))?;
+            }
+            "autolock_minutes" => {
+                self.autolock_minutes = value.parse().map_err(|e| format!("{e}"))?;
+            }
+            "lock_on_minimize" => {
+                self.lock_on_minimize = value.parse().map_err(|e| format!("{e}"))?;
+            }
+            "hide_on_close" => {
+                self.hide_on_close = value.parse().map_err(|e| format!("{e}"))?;
+            }
+            "max_unlock_attempts" => {
+                self.max_unlock_attempts = value.parse().map_err(|e| format!("{e}"))?;
+            }
+            "lockout_action" => {
+                self.lockout_action = match value {
+                    "lock" => LockoutAction::Lock,
+                    "wipe" => LockoutAction::Wipe,
+                    _ => return Err("expected lock or wipe".to_string()),
+                };
+            }
+            "agent_ttl" => {
+                self.agent_ttl = value.parse().map_err(|e| format!("{e}"))?;
+            }
+            _ => return Err("unknown setting".to_string()),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use crate::pwds::{parse_tags, Entry};
+
+mod imp {
+    use std::cell::{Cell, RefCell};
+
+    use glib::prelude::*;
+    use glib::subclass::prelude::*;
+
+    /// List item for the Pwds page. Secrets are never stored here.
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::EntryObject)]
+    pub struct EntryObject {
+        #[property(get, set)]
+        id: RefCell<String>,
+        #[property(get, set)]
+        title: RefCell<String>,
+        #[property(get, set)]
+        username: RefCell<String>,
+        #[property(get, set)]
+        url: RefCell<String>,
+        #[property(get, set)]
+        tags: RefCell<String>,
+        #[property(get, set)]
+        notes: RefCell<String>,
+        /// Whether the entry has one-time passwords, not the secret itself.
+        #[property(get, set)]
+        has_otp: Cell<bool>,
+        /// Relevance to the search of the Pwds page, set by its filter.
+        #[property(get, set)]
+        score: Cell<i32>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for EntryObject {
+        const NAME: &'static str = "PwdsEntryObject";
+        type Type = super::EntryObject;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for EntryObject {}
+}
+
+glib::wrapper! {
+    pub struct EntryObject(ObjectSubclass<imp::EntryObject>);
+}
+
+impl EntryObject {
+    /// Builds a list item from an entry, dropping its password.
+    pub fn new(entry: &Entry) -> Self {
+        glib::Object::builder()
+            .property("id", &entry.id)
+            .property("title", &entry.title)
+            .property("username", &entry.username)
+            .property("url", &entry.url)
+            .property("tags", entry.tags.join(", "))
+            .property("notes", &entry.notes)
+            .build()
+    }
+
+    /// Returns the entry without its secrets, e.g. for searching.
+    pub fn to_entry(&self) -> Entry {
+        Entry {
+            id: self.id(),
+            title: self.title(),
+            username: self.username(),
+            password: String::new(),
+            url: self.url(),
+            tags: parse_tags(&self.tags()),
+            notes: self.notes(),
+            otp: String::new(),
+        }
+    }
+}
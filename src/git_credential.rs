@@ -0,0 +1,295 @@
+use std::io::{self, BufRead, Write};
+
+use clap::ValueEnum;
+
+use crate::pwds::Entry;
+use crate::session::Session;
+
+/// What git asks a credential helper to do.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Operation {
+    /// Print the credential matching the request, if any
+    Get,
+    /// Save a credential git used successfully
+    Store,
+    /// Forget a credential the remote refused
+    Erase,
+}
+
+/// A credential as git describes it, one `name=value` line per attribute.
+/// Attributes git did not send are `None`.
+#[derive(Default)]
+struct Credential {
+    protocol: Option<String>,
+    host: Option<String>,
+    /// Repository path, only sent with `credential.useHttpPath`.
+    path: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl Credential {
+    /// Reads attributes up to a blank line or the end of input. Unknown
+    /// attributes, like `capability[]` or `wwwauth[]`, are ignored.
+    fn read(reader: impl BufRead) -> io::Result<Self> {
+        let mut credential = Credential::default();
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.is_empty() {
+                break;
+            }
+
+            let Some((name, value)) = line.split_once('=') else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid credential line: {line}"),
+                ));
+            };
+
+            match name {
+                "protocol" => credential.protocol = Some(value.to_string()),
+                "host" => credential.host = Some(value.to_string()),
+                "path" => credential.path = Some(value.trim_matches('/').to_string()),
+                "username" => credential.username = Some(value.to_string()),
+                "password" => credential.password = Some(value.to_string()),
+                "url" => {
+                    let (protocol, host, path) = split_url(value);
+                    credential.protocol = protocol.map(String::from);
+                    credential.host = Some(host.to_string());
+                    credential.path = (!path.is_empty()).then(|| path.to_string());
+                }
+                _ => {}
+            }
+        }
+
+        Ok(credential)
+    }
+
+    /// Tells how well an entry matches, `None` when it does not. As with
+    /// git's own helpers, attributes missing from the request match anything.
+    /// An entry without a path holds the credential of the whole host, and
+    /// ranks below entries for the exact path.
+    fn rank(&self, entry: &Entry) -> Option<u8> {
+        let (protocol, host, path) = split_url(&entry.url);
+
+        let same_protocol = match (&self.protocol, protocol) {
+            (Some(wanted), Some(stored)) => wanted.eq_ignore_ascii_case(stored),
+            _ => true,
+        };
+        let same_host = self
+            .host
+            .as_ref()
+            .is_some_and(|wanted| wanted.eq_ignore_ascii_case(host));
+        let same_username = self
+            .username
+            .as_ref()
+            .is_none_or(|wanted| *wanted == entry.username);
+
+        if !same_protocol || !same_host || !same_username {
+            return None;
+        }
+
+        match &self.path {
+            None => Some(1),
+            Some(wanted) if *wanted == path => Some(1),
+            Some(_) if path.is_empty() => Some(0),
+            Some(_) => None,
+        }
+    }
+
+    /// The entry `get` answers with: the best ranked one, the first stored
+    /// on a tie.
+    fn find<'a>(&self, entries: &'a [Entry]) -> Option<&'a Entry> {
+        entries
+            .iter()
+            .filter_map(|entry| Some((self.rank(entry)?, entry)))
+            .rev()
+            .max_by_key(|(rank, _)| *rank)
+            .map(|(_, entry)| entry)
+    }
+
+    fn url(&self) -> String {
+        let mut url = format!(
+            "{}://{}",
+            self.protocol.as_deref().unwrap_or("https"),
+            self.host.as_deref().unwrap_or_default()
+        );
+        if let Some(path) = self.path.as_deref().filter(|path| !path.is_empty()) {
+            url.push('/');
+            url.push_str(path);
+        }
+        url
+    }
+}
+
+/// Splits an entry URL into protocol, host and path. URLs without a scheme,
+/// like `git.example.com`, match any protocol.
+fn split_url(url: &str) -> (Option<&str>, &str, &str) {
+    let (protocol, rest) = match url.split_once("://") {
+        Some((protocol, rest)) => (Some(protocol), rest),
+        None => (None, url),
+    };
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+
+    /* Credentials embedded in the URL are not part of the host */
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+
+    (protocol, host, path.trim_matches('/'))
+}
+
+/// Answers one request of git's credential helper protocol, read on stdin.
+pub fn run(operation: Operation, session: &Session) -> io::Result<()> {
+    let credential = Credential::read(io::stdin().lock())?;
+
+    match operation {
+        Operation::Get => get(&credential, session),
+        Operation::Store => store(&credential, session),
+        Operation::Erase => erase(&credential, session),
+    }
+}
+
+fn get(credential: &Credential, session: &Session) -> io::Result<()> {
+    let entries = session.entries()?;
+
+    /* Printing nothing lets git ask the user or the next helper */
+    let Some(entry) = credential.find(&entries) else {
+        return Ok(());
+    };
+
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "username={}", entry.username)?;
+    writeln!(stdout, "password={}", entry.password)?;
+    stdout.flush()
+}
+
+/// Updates the password of the entry `get` would answer with, or adds an
+/// entry for the credential.
+fn store(credential: &Credential, session: &Session) -> io::Result<()> {
+    let (Some(host), Some(username), Some(password)) =
+        (&credential.host, &credential.username, &credential.password)
+    else {
+        /* git only stores complete credentials, ignore anything else */
+        return Ok(());
+    };
+
+    let entries = session.entries()?;
+
+    if let Some(existing) = credential.find(&entries) {
+        if existing.password == *password {
+            return Ok(());
+        }
+
+        let mut updated = existing.clone();
+        updated.password = password.clone();
+        return session.update(&existing.id, &updated);
+    }
+
+    let entry = Entry {
+        title: host.clone(),
+        username: username.clone(),
+        password: password.clone(),
+        url: credential.url(),
+        tags: vec!["git".to_string()],
+        ..Default::default()
+    };
+    entry
+        .validate()
+        .map_err(|message| io::Error::new(io::ErrorKind::InvalidInput, message))?;
+
+    session.add(&entry).map(|_| ())
+}
+
+/// Removes the matching entries. When git sends the refused password, only
+/// entries still holding it go, so that a newer password survives.
+fn erase(credential: &Credential, session: &Session) -> io::Result<()> {
+    let entries = session.entries()?;
+
+    for entry in &entries {
+        let refused = credential
+            .password
+            .as_ref()
+            .is_none_or(|password| *password == entry.password);
+
+        if refused && credential.rank(entry).is_some() {
+            session.remove(&entry.id)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, username: &str, url: &str) -> Entry {
+        Entry {
+            id: id.to_string(),
+            username: username.to_string(),
+            password: "secret".to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reads_attributes() {
+        let input = "protocol=https\nhost=git.example.com\npath=/team/repo.git/\n\
+                     username=alice\ncapability[]=authtype\n\nhost=ignored\n";
+        let credential = Credential::read(input.as_bytes()).unwrap();
+
+        assert_eq!(credential.protocol.as_deref(), Some("https"));
+        assert_eq!(credential.host.as_deref(), Some("git.example.com"));
+        assert_eq!(credential.path.as_deref(), Some("team/repo.git"));
+        assert_eq!(credential.username.as_deref(), Some("alice"));
+        assert_eq!(credential.password, None);
+        assert_eq!(credential.url(), "https://git.example.com/team/repo.git");
+    }
+
+    #[test]
+    fn reads_url() {
+        let input = "url=ssh://bob@git.example.com/repo.git\n";
+        let credential = Credential::read(input.as_bytes()).unwrap();
+
+        assert_eq!(credential.protocol.as_deref(), Some("ssh"));
+        assert_eq!(credential.host.as_deref(), Some("git.example.com"));
+        assert_eq!(credential.path.as_deref(), Some("repo.git"));
+
+        let credential = Credential::read("url=https://git.example.com\n".as_bytes()).unwrap();
+        assert_eq!(credential.path, None);
+    }
+
+    #[test]
+    fn refuses_invalid_lines() {
+        let error = Credential::read("host\n".as_bytes()).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn finds_best_entry() {
+        let entries = [
+            entry("other", "alice", "https://example.org"),
+            entry("host", "alice", "git.example.com"),
+            entry("repo", "alice", "https://git.example.com/repo.git"),
+            entry("second", "alice", "https://git.example.com/repo.git"),
+            entry("bob", "bob", "https://git.example.com"),
+        ];
+        let find = |input: &str| {
+            let credential = Credential::read(input.as_bytes()).unwrap();
+            credential.find(&entries).map(|entry| entry.id.as_str())
+        };
+
+        /* The exact path first, the first stored on a tie */
+        assert_eq!(find("host=git.example.com\npath=repo.git\n"), Some("repo"));
+        /* Then the entry of the whole host */
+        assert_eq!(
+            find("protocol=https\nhost=git.example.com\npath=other.git\nusername=alice\n"),
+            Some("host")
+        );
+        assert_eq!(find("host=GIT.example.com\nusername=bob\n"), Some("bob"));
+        assert_eq!(find("protocol=http\nhost=example.org\n"), None);
+        assert_eq!(find("host=git.example.com\nusername=carol\n"), None);
+        assert_eq!(find("path=repo.git\n"), None);
+    }
+}
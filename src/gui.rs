@@ -1,13 +1,26 @@
 pub mod gui {
     use glib::clone;
-    use gtk::{gdk, prelude::*};
+    use gtk::{gdk, gio, prelude::*};
     use gtk::{Application, ApplicationWindow, Button, GestureClick, Image};
-    use rand::distributions::Alphanumeric;
-    use rand::Rng;
-    use std::cell::RefCell;
+    use std::cell::{Cell, RefCell};
+    use std::io;
     use std::rc::Rc;
-
-    use crate::pwds::{load_passwords, modify_password, remove_password, save_password};
+    use std::time::{Duration, Instant};
+
+    use crate::agent;
+    use crate::audit::{self, AUDIT_PATH};
+    use crate::config::Config;
+    use crate::crypto::gen_pwd;
+    use crate::csv::{Field, Mapping, Preset, Table};
+    use crate::entry_object::EntryObject;
+    use crate::import::{Import, OnDuplicate};
+    use crate::otp::{self, Kind, Otp};
+    use crate::plaintext::{self, Format};
+    use crate::pwds::{parse_tags, Entry, FIELD_NAMES};
+    use crate::throttle::{self, Refusal};
+    use crate::vault_model::{ReloadError, VaultModel};
+    use crate::search;
+    use crate::session::Session;
 
     pub fn load_css() {
         let provider = gtk::CssProvider::new();
@@ -31,31 +44,111 @@ pub mod gui {
         alert_dialog.show(Some(win));
     }
 
-    pub fn adjust_password_length(password: &str) -> String {
-        const MAX_LENGTH: usize = 32;
+    /// In-window notification shown at the bottom of the main view.
+    #[derive(Clone)]
+    struct Toast {
+        label: gtk::Label,
+        countdown: Rc<RefCell<Option<glib::SourceId>>>,
+    }
 
-        if password.len() > MAX_LENGTH {
-            password[0..MAX_LENGTH].to_string()
-        } else {
-            let mut adjusted = password.to_string();
-            adjusted.push_str(&"0".repeat(MAX_LENGTH - password.len()));
-            adjusted
+    impl Toast {
+        fn new() -> Self {
+            let label = gtk::Label::new(None);
+            label.add_css_class("toast");
+            label.set_halign(gtk::Align::Center);
+            label.set_valign(gtk::Align::End);
+            label.set_margin_bottom(24);
+            label.set_visible(false);
+
+            Self {
+                label,
+                countdown: Rc::new(RefCell::new(None)),
+            }
+        }
+
+        fn widget(&self) -> &gtk::Label {
+            &self.label
+        }
+
+        /// Shows `message(remaining)` every second for `seconds` seconds,
+        /// then hides the toast and runs `on_done`. Replaces any running countdown.
+        fn countdown(
+            &self,
+            seconds: u32,
+            message: impl Fn(u32) -> String + 'static,
+            on_done: impl Fn() + 'static,
+        ) {
+            if let Some(source) = self.countdown.borrow_mut().take() {
+                source.remove();
+            }
+
+            let remaining = Cell::new(seconds);
+            self.label.set_text(&message(seconds));
+            self.label.set_visible(true);
+
+            let toast = self.clone();
+            let source = glib::timeout_add_seconds_local(1, move || {
+                remaining.set(remaining.get().saturating_sub(1));
+
+                if remaining.get() > 0 {
+                    toast.label.set_text(&message(remaining.get()));
+                    return glib::ControlFlow::Continue;
+                }
+
+                toast.label.set_visible(false);
+                toast.countdown.borrow_mut().take();
+                on_done();
+                glib::ControlFlow::Break
+            });
+
+            *self.countdown.borrow_mut() = Some(source);
         }
     }
 
-    pub fn gen_pwd() -> Result<String, &'static str> {
-        const LENGTH: usize = 14;
+    /// Copies a secret to the clipboard, hinting clipboard managers not to
+    /// record it, and clears it after `timeout` seconds unless it was replaced.
+    fn copy_secret(secret: &str, what: &'static str, timeout: u32, toast: &Toast) {
+        let display = gdk::Display::default().unwrap();
+        let clipboard = display.clipboard();
+
+        /* Understood by KDE Klipper and other clipboard managers */
+        let provider = gdk::ContentProvider::new_union(&[
+            gdk::ContentProvider::for_value(&secret.to_value()),
+            gdk::ContentProvider::for_bytes(
+                "x-kde-passwordManagerHint",
+                &glib::Bytes::from_static(b"secret"),
+            ),
+        ]);
 
-        let mut rng = rand::thread_rng();
-        let pwd = std::iter::repeat_with(|| rng.sample(Alphanumeric))
-            .map(char::from)
-            .take(LENGTH)
-            .collect();
+        if clipboard.set_content(Some(&provider)).is_err() {
+            clipboard.set_text(secret);
+        }
 
-        Ok(pwd)
+        if timeout == 0 {
+            toast.countdown(3, move |_| format!("{what} copied to clipboard."), || {});
+            return;
+        }
+
+        toast.countdown(
+            timeout,
+            move |remaining| format!("{what} copied, clipboard cleared in {remaining}s."),
+            move || {
+                if clipboard.is_local() && clipboard.content().as_ref() == Some(&provider) {
+                    clipboard.set_text("");
+                }
+            },
+        );
     }
 
     pub fn build_ui(app: &Application) {
+        /* A window hidden on close is shown again, still locked */
+        if let Some(window) = app.active_window() {
+            window.present();
+            return;
+        }
+
+        let config = Config::load();
+
         let window = ApplicationWindow::builder()
             .application(app)
             .title("pwds")
@@ -63,8 +156,36 @@ pub mod gui {
             .default_height(350)
             .resizable(true)
             .modal(true)
+            .hide_on_close(config.hide_on_close)
             .build();
 
+        window.add_css_class("window");
+
+        if config.lock_on_minimize {
+            window.connect_suspended_notify(|window| {
+                if window.is_suspended() {
+                    lock(window);
+                }
+            });
+        }
+
+        window.connect_hide(lock);
+        watch_session_lock(&window);
+
+        key_prompt(&window);
+        window.present();
+    }
+
+    /// Replaces the content of the window with the encryption key prompt.
+    fn key_prompt(window: &ApplicationWindow) {
+        let window = window.clone();
+
+        /* Another pwds process may have unlocked the agent already */
+        if let Some(session) = Session::from_agent() {
+            main_ui(&window, session);
+            return;
+        }
+
         /* Get password */
         let get_pwd_box = gtk::Box::new(gtk::Orientation::Vertical, 10);
         let get_pwd_entry = gtk::Entry::builder()
@@ -74,22 +195,20 @@ pub mod gui {
         let get_pwd_button = gtk::Button::with_label("Get Password");
         get_pwd_button.add_css_class("mgr_button");
 
-        let enc_key = Rc::new(RefCell::new(String::new()));
+        let get_pwd_status = gtk::Label::new(None);
+        get_pwd_status.add_css_class("content");
+        get_pwd_status.set_visible(false);
 
         get_pwd_box.append(&get_pwd_entry);
         get_pwd_box.append(&get_pwd_button);
+        get_pwd_box.append(&get_pwd_status);
 
         get_pwd_box.set_valign(gtk::Align::Center);
         get_pwd_box.set_halign(gtk::Align::Center);
 
         window.set_child(Some(&get_pwd_box));
-        window.add_css_class("window");
-        window.present();
-
-        let enc_key_clone = Rc::clone(&enc_key);
-        get_pwd_button.connect_clicked(move |_| {
-            *enc_key_clone.borrow_mut() = adjust_password_length(get_pwd_entry.text().as_str());
 
+        get_pwd_button.connect_clicked(move |get_pwd_button| {
             if get_pwd_entry.text().as_str().is_empty() {
                 show_alert(
                     &window,
@@ -99,11 +218,365 @@ pub mod gui {
                 return;
             }
 
-            main_ui(&window, Rc::clone(&enc_key_clone));
+            match throttle::unlock(get_pwd_entry.text().as_str()) {
+                Ok(session) => main_ui(&window, session),
+                Err(refusal @ Refusal::WrongKey(delay)) => {
+                    /* Refuse new attempts until the back-off is over */
+                    get_pwd_button.set_sensitive(false);
+                    get_pwd_status.set_text(&refusal.to_string());
+                    get_pwd_status.set_visible(true);
+
+                    glib::timeout_add_seconds_local_once(
+                        delay.try_into().unwrap_or(u32::MAX),
+                        clone!(
+                            #[weak]
+                            get_pwd_button,
+                            #[weak]
+                            get_pwd_status,
+                            move || {
+                                get_pwd_button.set_sensitive(true);
+                                get_pwd_status.set_visible(false);
+                            }
+                        ),
+                    );
+                }
+                Err(refusal @ (Refusal::LockedOut | Refusal::Wiped)) => {
+                    show_alert(&window, "Locked Out", &refusal.to_string())
+                }
+                Err(refusal @ Refusal::TooSoon(_)) => {
+                    show_alert(&window, "Encryption Key Error", &refusal.to_string())
+                }
+                Err(refusal) => show_alert(&window, "Error", &refusal.to_string()),
+            }
         });
     }
 
-    fn main_ui(window: &ApplicationWindow, enc_key: Rc<RefCell<String>>) {
+    /// Asks for the encryption key in a window of its own, for requests of
+    /// the Secret Service. `on_done` gets the unlocked vault, or `None` if closed.
+    pub fn unlock_dialog(on_done: impl FnOnce(Option<Session>) + 'static) {
+        let on_done = RefCell::new(Some(on_done));
+        let finish = Rc::new(move |session: Option<Session>| {
+            let on_done = on_done.borrow_mut().take();
+            if let Some(on_done) = on_done {
+                on_done(session);
+            }
+        });
+
+        let window = gtk::Window::builder()
+            .title("Unlock pwds")
+            .default_width(360)
+            .build();
+        window.add_css_class("window");
+
+        let content = gtk::Box::new(gtk::Orientation::Vertical, 10);
+        content.set_margin_top(20);
+        content.set_margin_bottom(20);
+        content.set_margin_start(20);
+        content.set_margin_end(20);
+
+        let message = gtk::Label::new(Some("An application wants to use your passwords."));
+        message.add_css_class("content");
+
+        let key_entry = gtk::PasswordEntry::builder()
+            .placeholder_text("Encryption Key")
+            .show_peek_icon(true)
+            .build();
+
+        let status = gtk::Label::new(None);
+        status.add_css_class("content");
+        status.set_visible(false);
+
+        let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 10);
+        buttons.set_halign(gtk::Align::End);
+        let cancel_button = gtk::Button::with_label("Cancel");
+        cancel_button.add_css_class("mgr_button");
+        let unlock_button = gtk::Button::with_label("Unlock");
+        unlock_button.add_css_class("mgr_button");
+        buttons.append(&cancel_button);
+        buttons.append(&unlock_button);
+
+        content.append(&message);
+        content.append(&key_entry);
+        content.append(&status);
+        content.append(&buttons);
+        window.set_child(Some(&content));
+
+        cancel_button.connect_clicked(clone!(
+            #[weak]
+            window,
+            move |_| window.close()
+        ));
+
+        window.connect_close_request(clone!(
+            #[strong]
+            finish,
+            move |_| {
+                finish(None);
+                glib::Propagation::Proceed
+            }
+        ));
+
+        let try_unlock = clone!(
+            #[weak]
+            window,
+            #[weak]
+            key_entry,
+            #[weak]
+            status,
+            #[weak]
+            unlock_button,
+            move || {
+                status.set_visible(true);
+
+                if key_entry.text().is_empty() {
+                    status.set_text("Invalid password provided.");
+                    return;
+                }
+
+                let refusal = match throttle::unlock(key_entry.text().as_str()) {
+                    Ok(session) => {
+                        finish(Some(session));
+                        window.close();
+                        return;
+                    }
+                    Err(refusal) => refusal,
+                };
+
+                status.set_text(&refusal.to_string());
+
+                match refusal {
+                    Refusal::WrongKey(delay) => {
+                        unlock_button.set_sensitive(false);
+                        glib::timeout_add_seconds_local_once(
+                            delay.try_into().unwrap_or(u32::MAX),
+                            move || unlock_button.set_sensitive(true),
+                        );
+                    }
+                    Refusal::LockedOut | Refusal::Wiped => unlock_button.set_sensitive(false),
+                    _ => {}
+                }
+            }
+        );
+
+        let try_unlock = Rc::new(try_unlock);
+        unlock_button.connect_clicked(clone!(
+            #[strong]
+            try_unlock,
+            move |_| try_unlock()
+        ));
+        key_entry.connect_activate(move |_| try_unlock());
+
+        window.present();
+    }
+
+    /// Name of the unlocked view, used to tell whether the window is locked.
+    const MAIN_VIEW: &str = "main_view";
+
+    /// Locks the window: the unlocked view is dropped along with the
+    /// decrypted entries and the key, and the key prompt is shown again.
+    /// The dialogs and popovers opened from the view, which hold them too,
+    /// are closed first.
+    fn lock(window: &ApplicationWindow) {
+        let Some(view) = window
+            .child()
+            .filter(|child| child.widget_name() == MAIN_VIEW)
+        else {
+            return;
+        };
+
+        for dialog in dialogs_of(window) {
+            dialog.destroy();
+        }
+        close_popovers(&view);
+
+        /* Locking here locks the other pwds processes too */
+        let _ = agent::lock();
+        key_prompt(window);
+    }
+
+    /// Returns the windows opened over `window`, directly or over one of
+    /// its dialogs, like the entry editor.
+    fn dialogs_of(window: &ApplicationWindow) -> Vec<gtk::Window> {
+        let window = window.upcast_ref::<gtk::Window>();
+
+        gtk::Window::list_toplevels()
+            .into_iter()
+            .filter_map(|toplevel| toplevel.downcast::<gtk::Window>().ok())
+            .filter(|dialog| {
+                let mut parent = dialog.transient_for();
+                while let Some(current) = parent {
+                    if current == *window {
+                        return true;
+                    }
+                    parent = current.transient_for();
+                }
+                false
+            })
+            .collect()
+    }
+
+    /// Pops down every popover under `widget`, like the one-time password
+    /// popover, which then drops its content.
+    fn close_popovers(widget: &gtk::Widget) {
+        let mut child = widget.first_child();
+
+        while let Some(current) = child {
+            /* A closed popover may unparent itself */
+            child = current.next_sibling();
+
+            close_popovers(&current);
+            if let Some(popover) = current.downcast_ref::<gtk::Popover>() {
+                popover.popdown();
+            }
+        }
+    }
+
+    /// Locks after `minutes` without keyboard or pointer activity in `view`
+    /// or in the dialogs opened over the window.
+    fn watch_idle(window: &ApplicationWindow, view: &gtk::Overlay, minutes: u32) {
+        let last_activity = Rc::new(Cell::new(Instant::now()));
+        watch_activity(view, &last_activity);
+
+        let timeout = Duration::from_secs(u64::from(minutes) * 60);
+        let mut watched_dialogs: Vec<glib::WeakRef<gtk::Window>> = Vec::new();
+
+        glib::timeout_add_seconds_local(
+            10,
+            clone!(
+                #[weak]
+                window,
+                #[weak]
+                view,
+                #[upgrade_or]
+                glib::ControlFlow::Break,
+                move || {
+                    /* This view was replaced, the window is already locked */
+                    if view.parent().is_none() {
+                        return glib::ControlFlow::Break;
+                    }
+
+                    /* Dialogs opened since the last check count from now on */
+                    watched_dialogs.retain(|dialog| dialog.upgrade().is_some());
+                    for dialog in dialogs_of(&window) {
+                        let watched = watched_dialogs
+                            .iter()
+                            .any(|watched| watched.upgrade().as_ref() == Some(&dialog));
+                        if !watched {
+                            watch_activity(&dialog, &last_activity);
+                            watched_dialogs.push(dialog.downgrade());
+                        }
+                    }
+
+                    if last_activity.get().elapsed() < timeout {
+                        return glib::ControlFlow::Continue;
+                    }
+
+                    lock(&window);
+                    glib::ControlFlow::Break
+                }
+            ),
+        );
+    }
+
+    /// Records the time of keyboard and pointer events in `widget`.
+    fn watch_activity(widget: &impl IsA<gtk::Widget>, last_activity: &Rc<Cell<Instant>>) {
+        let key_controller = gtk::EventControllerKey::new();
+        key_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        key_controller.connect_key_pressed(clone!(
+            #[strong]
+            last_activity,
+            move |_, _, _, _| {
+                last_activity.set(Instant::now());
+                glib::Propagation::Proceed
+            }
+        ));
+        widget.add_controller(key_controller);
+
+        let motion_controller = gtk::EventControllerMotion::new();
+        motion_controller.connect_motion(clone!(
+            #[strong]
+            last_activity,
+            move |_, _, _| last_activity.set(Instant::now())
+        ));
+        widget.add_controller(motion_controller);
+
+        let click_gesture = GestureClick::new();
+        click_gesture.set_propagation_phase(gtk::PropagationPhase::Capture);
+        click_gesture.connect_pressed(clone!(
+            #[strong]
+            last_activity,
+            move |_, _, _, _| last_activity.set(Instant::now())
+        ));
+        widget.add_controller(click_gesture);
+    }
+
+    /// Locks when the session gets locked, as reported by logind on the
+    /// system bus or by the screensaver on the session bus.
+    fn watch_session_lock(window: &ApplicationWindow) {
+        match gio::bus_get_sync(gio::BusType::System, None::<&gio::Cancellable>) {
+            Ok(system_bus) => {
+                let session_path = std::env::var("XDG_SESSION_ID").ok().map(|id| {
+                    format!("/org/freedesktop/login1/session/{}", bus_path_escape(&id))
+                });
+
+                system_bus.signal_subscribe(
+                    Some("org.freedesktop.login1"),
+                    Some("org.freedesktop.login1.Session"),
+                    Some("Lock"),
+                    session_path.as_deref(),
+                    None,
+                    gio::DBusSignalFlags::NONE,
+                    clone!(
+                        #[weak]
+                        window,
+                        move |_, _, _, _, _, _| lock(&window)
+                    ),
+                );
+            }
+            Err(e) => eprintln!("[-] Cannot connect to the system bus: {e}"),
+        }
+
+        match gio::bus_get_sync(gio::BusType::Session, None::<&gio::Cancellable>) {
+            Ok(session_bus) => {
+                for interface in ["org.freedesktop.ScreenSaver", "org.gnome.ScreenSaver"] {
+                    session_bus.signal_subscribe(
+                        None,
+                        Some(interface),
+                        Some("ActiveChanged"),
+                        None,
+                        None,
+                        gio::DBusSignalFlags::NONE,
+                        clone!(
+                            #[weak]
+                            window,
+                            move |_, _, _, _, _, parameters| {
+                                if parameters.get::<(bool,)>() == Some((true,)) {
+                                    lock(&window);
+                                }
+                            }
+                        ),
+                    );
+                }
+            }
+            Err(e) => eprintln!("[-] Cannot connect to the session bus: {e}"),
+        }
+    }
+
+    /// Escapes a logind session id the way sd_bus_path_encode does.
+    fn bus_path_escape(id: &str) -> String {
+        id.bytes()
+            .enumerate()
+            .map(|(index, byte)| {
+                if byte.is_ascii_alphabetic() || (byte.is_ascii_digit() && index > 0) {
+                    (byte as char).to_string()
+                } else {
+                    format!("_{byte:02x}")
+                }
+            })
+            .collect()
+    }
+
+    fn main_ui(window: &ApplicationWindow, session: Session) {
         // Main box
         let main_box = gtk::Box::builder()
             .margin_start(10)
@@ -137,6 +610,36 @@ pub mod gui {
 
         let window_clone = window.clone();
 
+        /* Shared decrypted listing, every page follows its changes */
+        let vault = VaultModel::new(
+            session,
+            clone!(
+                #[weak]
+                window,
+                move |error| match error {
+                    /* Locked once the page that reloaded is done with the model */
+                    ReloadError::KeyRejected => {
+                        glib::idle_add_local_once(move || {
+                            lock(&window);
+                            show_alert(
+                                &window,
+                                "Locked",
+                                "The encryption key no longer decrypts the database.",
+                            );
+                        });
+                    }
+                    ReloadError::Failed(e) => show_alert(
+                        &window,
+                        "Error",
+                        &format!("Error while reloading the database: {e}"),
+                    ),
+                }
+            ),
+        );
+
+        let config = Config::load();
+        let toast = Toast::new();
+
         // Create the main content area
         let content_area = gtk::Box::new(gtk::Orientation::Vertical, 10);
         content_area.add_css_class("page");
@@ -144,7 +647,7 @@ pub mod gui {
         content_area.set_vexpand(true);
         main_box.append(&content_area);
 
-        let default_page = manager_page(enc_key.borrow().clone(), window_clone.clone());
+        let default_page = manager_page(Rc::clone(&vault), window_clone.clone());
         content_area.append(&default_page);
         content_area.set_margin_start(12);
         content_area.set_margin_bottom(12);
@@ -158,7 +661,9 @@ pub mod gui {
             let content_area_clone = content_area.clone();
             let current_view_clone = Rc::clone(&current_view);
 
-            let enc_key_clone = enc_key.clone();
+            let vault_clone = Rc::clone(&vault);
+            let toast_clone = toast.clone();
+            let config_clone = config.clone();
 
             let window_clone = window_clone.clone();
 
@@ -166,9 +671,6 @@ pub mod gui {
                 let content_area = &content_area_clone;
                 let mut current_view = current_view_clone.borrow_mut();
 
-                let enc_key = &enc_key_clone;
-                //println!("{}", enc_key);
-
                 /* Clean up previous view */
                 while let Some(child) = content_area.last_child() {
                     content_area.remove(&child);
@@ -176,24 +678,27 @@ pub mod gui {
 
                 match btn.label().unwrap().as_str() {
                     "manager" => {
-                        let mgr_page = manager_page(enc_key.borrow().clone(), window_clone.clone());
+                        let mgr_page = manager_page(Rc::clone(&vault_clone), window_clone.clone());
                         content_area.append(&mgr_page);
                         *current_view = Some(mgr_page);
                     }
                     "pwds" => {
-                        let scrolled_window = gtk::ScrolledWindow::new();
-                        let pwds_page = pwds_page(enc_key.borrow().clone(), window_clone.clone());
-                        scrolled_window.set_child(Some(&pwds_page));
-                        content_area.append(&scrolled_window);
+                        let pwds_page = pwds_page(
+                            Rc::clone(&vault_clone),
+                            window_clone.clone(),
+                            toast_clone.clone(),
+                            config_clone.clone(),
+                        );
+                        content_area.append(&pwds_page);
                         *current_view = Some(pwds_page);
                     }
                     "credits" => {
-                        let crds_page = credits_page(enc_key.borrow().clone());
+                        let crds_page = credits_page();
                         content_area.append(&crds_page);
                         *current_view = Some(crds_page);
                     }
                     _ => {
-                        let mgr_page = manager_page(enc_key.borrow().clone(), window_clone.clone());
+                        let mgr_page = manager_page(Rc::clone(&vault_clone), window_clone.clone());
                         content_area.append(&mgr_page);
                         *current_view = Some(mgr_page);
                     }
@@ -203,10 +708,20 @@ pub mod gui {
             sidebar.append(&button);
         }
 
-        window.set_child(Some(&main_box));
+        /* Toasts are drawn over the pages */
+        let overlay = gtk::Overlay::new();
+        overlay.set_widget_name(MAIN_VIEW);
+        overlay.set_child(Some(&main_box));
+        overlay.add_overlay(toast.widget());
+
+        if config.autolock_minutes > 0 {
+            watch_idle(window, &overlay, config.autolock_minutes);
+        }
+
+        window.set_child(Some(&overlay));
     }
 
-    fn manager_page(enc_key: String, window: ApplicationWindow) -> gtk::Box {
+    fn manager_page(vault: Rc<VaultModel>, window: ApplicationWindow) -> gtk::Box {
         let manager_box = gtk::Box::new(gtk::Orientation::Vertical, 7);
         manager_box.set_hexpand(true);
         manager_box.set_vexpand(true);
@@ -230,11 +745,15 @@ pub mod gui {
         let add_button = Button::with_label("Add Password");
         let modify_button = Button::with_label("Edit Password");
         let remove_button = Button::with_label("Remove Password");
+        let import_button = Button::with_label("Import CSV");
+        let export_button = Button::with_label("Export Plaintext");
 
         /* Css classes */
         add_button.add_css_class("mgr_button");
         modify_button.add_css_class("mgr_button");
         remove_button.add_css_class("mgr_button");
+        import_button.add_css_class("mgr_button");
+        export_button.add_css_class("mgr_button");
 
         let entries_box = gtk::Box::new(gtk::Orientation::Vertical, 7);
 
@@ -278,8 +797,39 @@ pub mod gui {
         password_box.append(&password_entry);
         password_box.append(&password_generate);
 
+        let title_entry = gtk::Entry::builder()
+            .css_name("entry")
+            .placeholder_text("Title (optional)")
+            .build();
+
+        let url_entry = gtk::Entry::builder()
+            .css_name("entry")
+            .placeholder_text("URL (optional)")
+            .build();
+
+        let tags_entry = gtk::Entry::builder()
+            .css_name("entry")
+            .placeholder_text("Tags, comma separated (optional)")
+            .build();
+
+        let notes_entry = gtk::Entry::builder()
+            .css_name("entry")
+            .placeholder_text("Notes (optional)")
+            .build();
+
+        let otp_entry = gtk::PasswordEntry::builder()
+            .css_name("entry")
+            .placeholder_text("otpauth:// URI of one-time passwords (optional)")
+            .show_peek_icon(true)
+            .build();
+
+        entries_box.append(&title_entry);
         entries_box.append(&username_entry);
         entries_box.append(&password_box);
+        entries_box.append(&url_entry);
+        entries_box.append(&tags_entry);
+        entries_box.append(&notes_entry);
+        entries_box.append(&otp_entry);
         entries_box.set_hexpand(true);
         entries_box.set_vexpand(true);
         entries_box.set_margin_bottom(12);
@@ -289,43 +839,66 @@ pub mod gui {
         manager_box.append(&entries_box);
         manager_box.append(&add_button);
         manager_box.append(&modify_button);
+        manager_box.append(&import_button);
+        manager_box.append(&export_button);
         //manager_box.append(&remove_button);
 
+        let window_clone = window.clone();
+        let vault_clone = Rc::clone(&vault);
+
+        import_button.connect_clicked(move |_| {
+            choose_csv(&window_clone, Rc::clone(&vault_clone));
+        });
+
+        let window_clone = window.clone();
+        let vault_clone = Rc::clone(&vault);
+
+        export_button.connect_clicked(move |_| {
+            plaintext_export_dialog(&window_clone, Rc::clone(&vault_clone));
+        });
+
         let username_entry_clone = username_entry.clone();
         let password_entry_clone = password_entry.clone();
         let window_clone = window.clone();
-        let enc_key_clone = enc_key.clone();
+        let vault_clone = Rc::clone(&vault);
 
         add_button.connect_clicked(move |_| {
-            if username_entry_clone.text().as_str().is_empty()
-                || password_entry_clone.text().as_str().is_empty()
-            {
-                show_alert(
-                    &window_clone,
-                    "Error!",
-                    "Please fill in both username and password fields.",
-                );
+            let entry = Entry {
+                id: String::new(),
+                title: title_entry.text().to_string(),
+                username: username_entry_clone.text().to_string(),
+                password: password_entry_clone.text().to_string(),
+                url: url_entry.text().to_string(),
+                tags: parse_tags(tags_entry.text().as_str()),
+                notes: notes_entry.text().to_string(),
+                otp: otp_entry.text().trim().to_string(),
+            };
+
+            if let Err(message) = entry.validate() {
+                show_alert(&window_clone, "Error!", &message);
                 return;
             }
 
-            save_password(
-                username_entry_clone.text().as_str(),
-                password_entry_clone.text().as_str(),
-                enc_key_clone.as_str(),
-            )
-            .expect("ENCRYPTION ERROR!");
+            match vault_clone.find_duplicate(&entry) {
+                Ok(Some(existing)) => {
+                    resolve_duplicate(&window_clone, Rc::clone(&vault_clone), entry, existing)
+                }
+                Ok(None) => {
+                    vault_clone.add(&entry).expect("ENCRYPTION ERROR!");
 
-            show_alert(
-                &window_clone,
-                "Success",
-                "Password successfully added to database.",
-            );
+                    show_alert(
+                        &window_clone,
+                        "Success",
+                        "Password successfully added to database.",
+                    );
+                }
+                Err(_) => show_alert(&window_clone, "Error", "Error while reading database."),
+            }
         });
 
         let username_entry_clone = username_entry.clone();
         let password_entry_clone = password_entry.clone();
         let window_clone = window.clone();
-        let enc_key_clone = enc_key.clone();
 
         modify_button.connect_clicked(move |_| {
             if username_entry_clone.text().as_str().is_empty()
@@ -339,25 +912,53 @@ pub mod gui {
                 return;
             }
 
-            if let Ok(_) = modify_password(
-                username_entry_clone.text().as_str(),
-                password_entry_clone.text().as_str(),
-                enc_key_clone.as_str(),
-            ) {
-                show_alert(&window_clone, "Success", "Password successfully modified.");
-            } else {
-                show_alert(&window_clone, "Error", "Error while modifying password.");
+            /* Usernames are not unique, only edit when there is no doubt */
+            let matches = match vault.find_by_username(username_entry_clone.text().as_str()) {
+                Ok(matches) => matches,
+                Err(_) => {
+                    show_alert(&window_clone, "Error", "Error while reading database.");
+                    return;
+                }
+            };
+
+            let id = match matches.as_slice() {
+                [entry] => entry.id.clone(),
+                [] => {
+                    show_alert(
+                        &window_clone,
+                        "Error",
+                        "No password is stored for this username.",
+                    );
+                    return;
+                }
+                _ => {
+                    show_alert(
+                        &window_clone,
+                        "Error",
+                        "Several entries use this username, edit it from the pwds page.",
+                    );
+                    return;
+                }
+            };
+
+            match vault.modify_password(&id, password_entry_clone.text().as_str()) {
+                Ok(()) => show_alert(&window_clone, "Success", "Password successfully modified."),
+                Err(_) => show_alert(&window_clone, "Error", "Error while modifying password."),
             }
         });
 
         manager_box
     }
 
-    fn pwds_page(enc_key: String, window: ApplicationWindow) -> gtk::Box {
+    fn pwds_page(
+        vault: Rc<VaultModel>,
+        window: ApplicationWindow,
+        toast: Toast,
+        config: Config,
+    ) -> gtk::Box {
         let pwds_box = gtk::Box::new(gtk::Orientation::Vertical, 7);
         pwds_box.set_hexpand(true);
         pwds_box.set_vexpand(true);
-        pwds_box.set_valign(gtk::Align::Start);
 
         let page_title = gtk::Label::new(Some("Pwds"));
         page_title.set_css_classes(&["title"]);
@@ -365,10 +966,6 @@ pub mod gui {
         page_title.set_valign(gtk::Align::Start);
         page_title.set_margin_bottom(24);
 
-        let spacer = gtk::Box::new(gtk::Orientation::Horizontal, 0);
-        spacer.set_vexpand(true);
-        pwds_box.append(&spacer);
-
         pwds_box.set_margin_top(12);
         pwds_box.set_margin_end(12);
         pwds_box.set_margin_bottom(12);
@@ -376,74 +973,228 @@ pub mod gui {
 
         pwds_box.append(&page_title);
 
-        struct Credentials {
-            username: String,
-            password: String,
-        }
+        /* Search bar, focused with Ctrl+F */
+        let search_entry = gtk::SearchEntry::builder()
+            .placeholder_text("Search title, username, URL, tags or notes")
+            .margin_bottom(7)
+            .build();
+        pwds_box.append(&search_entry);
+
+        let empty_label = gtk::Label::new(None);
+        empty_label.set_halign(gtk::Align::Center);
+        empty_label.add_css_class("content");
+        empty_label.set_visible(false);
+        pwds_box.append(&empty_label);
+
+        let search_shortcuts = gtk::ShortcutController::new();
+        search_shortcuts.set_scope(gtk::ShortcutScope::Global);
+        search_shortcuts.add_shortcut(gtk::Shortcut::new(
+            gtk::ShortcutTrigger::parse_string("<Control>f"),
+            Some(gtk::CallbackAction::new(clone!(
+                #[weak]
+                search_entry,
+                #[upgrade_or]
+                glib::Propagation::Proceed,
+                move |_, _| {
+                    search_entry.grab_focus();
+                    glib::Propagation::Stop
+                }
+            ))),
+        ));
+        pwds_box.add_controller(search_shortcuts);
+
+        /* Model: vault store -> search filter -> relevance and column sorters -> view */
+        let store = vault.store().clone();
+
+        let filter = gtk::CustomFilter::new(clone!(
+            #[weak]
+            search_entry,
+            #[upgrade_or]
+            true,
+            move |object| {
+                let entry_object = object
+                    .downcast_ref::<EntryObject>()
+                    .expect("Needs to be an EntryObject");
+
+                /* Scored once here rather than on every comparison of the sorter */
+                let query = search_entry.text();
+                match search::entry_score(query.as_str(), &entry_object.to_entry()) {
+                    Some(score) => {
+                        entry_object.set_score(score);
+                        true
+                    }
+                    None => false,
+                }
+            }
+        ));
+        let filter_model = gtk::FilterListModel::new(Some(store.clone()), Some(filter.clone()));
+
+        /* Best matches first while searching, the sorted column breaking ties.
+         * Every entry scores 0 for an empty search. */
+        let score_sorter = gtk::CustomSorter::new(|first, second| {
+            let score = |object: &glib::Object| {
+                object
+                    .downcast_ref::<EntryObject>()
+                    .expect("Needs to be an EntryObject")
+                    .score()
+            };
+            score(second).cmp(&score(first)).into()
+        });
+
+        search_entry.connect_search_changed(clone!(
+            #[weak]
+            score_sorter,
+            move |_| {
+                filter.changed(gtk::FilterChange::Different);
+                score_sorter.changed(gtk::SorterChange::Different);
+            }
+        ));
+
+        let update_empty_label = clone!(
+            #[weak]
+            empty_label,
+            #[weak]
+            store,
+            move |filter_model: &gtk::FilterListModel| {
+                if store.n_items() == 0 {
+                    empty_label.set_text("No passwords stored.");
+                } else {
+                    empty_label.set_text("No matching passwords.");
+                }
+                empty_label.set_visible(filter_model.n_items() == 0);
+            }
+        );
+        update_empty_label(&filter_model);
 
-        let credentials = load_passwords(&enc_key.as_str()).unwrap();
+        filter_model.connect_items_changed(move |filter_model, _, _, _| {
+            update_empty_label(filter_model);
+        });
 
-        if credentials.is_empty() {
-            let no_pwds_label = gtk::Label::new(Some("No passwords stored."));
-            no_pwds_label.set_halign(gtk::Align::Center);
-            no_pwds_label.set_valign(gtk::Align::Center);
-            no_pwds_label.add_css_class("content");
-            pwds_box.append(&no_pwds_label);
-            return pwds_box;
+        let column_view = gtk::ColumnView::new(None::<gtk::NoSelection>);
+        let sorter = gtk::MultiSorter::new();
+        sorter.append(score_sorter);
+        if let Some(column_sorter) = column_view.sorter() {
+            sorter.append(column_sorter);
         }
+        let sort_model = gtk::SortListModel::new(Some(filter_model), Some(sorter));
+        column_view.set_model(Some(&gtk::NoSelection::new(Some(sort_model))));
+        column_view.add_css_class("cred_list");
+
+        for (title, property) in [
+            ("Title", "title"),
+            ("Username", "username"),
+            ("URL", "url"),
+            ("Tags", "tags"),
+        ] {
+            column_view.append_column(&text_column(title, property));
+        }
+        column_view.append_column(&actions_column(vault, window, toast, config));
 
-        for creds in credentials.iter() {
-            let enc_key_clone = enc_key.clone();
+        let scrolled_window = gtk::ScrolledWindow::builder()
+            .child(&column_view)
+            .vexpand(true)
+            .build();
+        pwds_box.append(&scrolled_window);
 
-            let cred = Credentials {
-                username: creds.0.clone(),
-                password: creds.1.clone(),
-            };
+        pwds_box
+    }
+
+    /// Sortable column showing one string property of an `EntryObject`.
+    fn text_column(title: &str, property: &'static str) -> gtk::ColumnViewColumn {
+        let factory = gtk::SignalListItemFactory::new();
+
+        factory.connect_setup(|_, list_item| {
+            let label = gtk::Label::new(None);
+            label.set_halign(gtk::Align::Start);
+            label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+            label.add_css_class("content");
 
-            let pwds_box_clone = pwds_box.clone();
+            list_item
+                .downcast_ref::<gtk::ListItem>()
+                .expect("Needs to be a ListItem")
+                .set_child(Some(&label));
+        });
+
+        factory.connect_bind(move |_, list_item| {
+            let list_item = list_item
+                .downcast_ref::<gtk::ListItem>()
+                .expect("Needs to be a ListItem");
+            let entry_object = list_item
+                .item()
+                .and_downcast::<EntryObject>()
+                .expect("Needs to be an EntryObject");
+            let label = list_item
+                .child()
+                .and_downcast::<gtk::Label>()
+                .expect("Needs to be a Label");
+
+            label.set_text(&entry_object.property::<String>(property));
+        });
+
+        let sorter = gtk::StringSorter::new(Some(gtk::PropertyExpression::new(
+            EntryObject::static_type(),
+            None::<gtk::Expression>,
+            property,
+        )));
+
+        let column = gtk::ColumnViewColumn::new(Some(title), Some(factory));
+        column.set_sorter(Some(&sorter));
+        column.set_resizable(true);
+        column.set_expand(true);
+        column
+    }
+
+    /// Show, copy and remove buttons. The password is decrypted only when
+    /// one of them is used and is hidden again when the row gets recycled.
+    fn actions_column(
+        vault: Rc<VaultModel>,
+        window: ApplicationWindow,
+        toast: Toast,
+        config: Config,
+    ) -> gtk::ColumnViewColumn {
+        let factory = gtk::SignalListItemFactory::new();
+
+        factory.connect_setup(move |_, list_item| {
+            let list_item = list_item
+                .downcast_ref::<gtk::ListItem>()
+                .expect("Needs to be a ListItem");
 
             let cred_box = gtk::Box::new(gtk::Orientation::Horizontal, 7);
-            //cred_box.set_hexpand(true);
-            //cred_box.set_vexpand(true);
-            //cred_box.set_valign(gtk::Align::Start);
-            //cred_box.set_halign(gtk::Align::Center);
-            cred_box.add_css_class("cred_card");
-            cred_box.set_margin_top(7);
-
-            let cred_username = gtk::Label::new(Some(&cred.username));
-            cred_username.add_css_class("cred_username");
-            cred_username.set_margin_bottom(12);
-            cred_username.set_margin_top(12);
-            cred_username.set_margin_start(12);
-            cred_username.set_halign(gtk::Align::Start);
-            cred_username.set_valign(gtk::Align::Center);
-
-            cred_box.append(&cred_username);
-
-            let spacer = gtk::Box::new(gtk::Orientation::Horizontal, 0);
-            spacer.set_hexpand(true);
-            cred_box.append(&spacer);
 
             let cred_pwd = gtk::Label::new(Some("show"));
             cred_pwd.add_css_class("cred_pwd");
-            cred_pwd.set_margin_bottom(12);
-            cred_pwd.set_margin_top(12);
             cred_pwd.set_margin_end(2);
             cred_pwd.set_halign(gtk::Align::End);
             cred_pwd.set_valign(gtk::Align::Center);
 
             let gesture = GestureClick::new();
-            let cred_pwd_clone = cred_pwd.clone();
-            let cred_password_clone = cred.password.clone();
 
-            gesture.connect_pressed(move |_, _, _, _| match cred_pwd_clone.text().as_str() {
-                "show" => {
-                    cred_pwd_clone.set_text(&cred_password_clone);
-                }
-                _ => {
-                    cred_pwd_clone.set_text("show");
+            gesture.connect_pressed(clone!(
+                #[weak]
+                list_item,
+                #[weak]
+                cred_pwd,
+                #[weak]
+                window,
+                #[strong]
+                vault,
+                move |_, _, _, _| {
+                    if cred_pwd.text().as_str() != "show" {
+                        cred_pwd.set_text("show");
+                        return;
+                    }
+
+                    let Some(entry_object) = list_item.item().and_downcast::<EntryObject>()
+                    else {
+                        return;
+                    };
+
+                    match vault.get_password(&entry_object.id()) {
+                        Ok(Some(password)) => cred_pwd.set_text(&password),
+                        _ => show_alert(&window, "Error", "Failed to decrypt password."),
+                    }
                 }
-            });
+            ));
 
             cred_box.append(&cred_pwd);
             cred_pwd.add_controller(gesture);
@@ -451,70 +1202,998 @@ pub mod gui {
             let copy_pwd_icon = Image::from_file("icons/copy.png");
             copy_pwd_icon.set_size_request(16, 16);
 
-            let cred_copy_pwd = gtk::Button::builder()
-                .label("copy")
-                //.css_name("copy_pwd")
-                .build();
+            let cred_copy_pwd = gtk::Button::builder().label("copy").build();
             cred_copy_pwd.set_child(Some(&copy_pwd_icon));
-            //cred_copy_pwd.set_size_request(30, 12);
-            cred_copy_pwd.set_margin_bottom(7);
-            cred_copy_pwd.set_margin_top(7);
-
-            let cred_password_clone = cred.password.clone();
 
             cred_copy_pwd.connect_clicked(clone!(
+                #[weak]
+                list_item,
                 #[weak]
                 window,
+                #[strong]
+                vault,
+                #[strong]
+                toast,
+                #[strong]
+                config,
                 move |_| {
-                    let display = gdk::Display::default().unwrap();
-                    let clipboard = display.clipboard();
-
-                    clipboard.set_text(&cred_password_clone);
-                    show_alert(
-                        &window,
-                        "Success!",
-                        "Password successfully copied to clipboard.",
-                    );
+                    let Some(entry_object) = list_item.item().and_downcast::<EntryObject>()
+                    else {
+                        return;
+                    };
+
+                    match vault.get_password(&entry_object.id()) {
+                        Ok(Some(password)) => {
+                            copy_secret(&password, "Password", config.clipboard_timeout, &toast);
+                        }
+                        _ => show_alert(&window, "Error", "Failed to decrypt password."),
+                    }
                 }
             ));
 
             cred_box.append(&cred_copy_pwd);
 
+            let cred_otp = gtk::Button::from_icon_name("appointment-soon-symbolic");
+            cred_otp.set_tooltip_text(Some("One-time password"));
+
+            cred_otp.connect_clicked(clone!(
+                #[weak]
+                list_item,
+                #[weak]
+                window,
+                #[strong]
+                vault,
+                #[strong]
+                toast,
+                #[strong]
+                config,
+                move |cred_otp| {
+                    let Some(entry_object) = list_item.item().and_downcast::<EntryObject>()
+                    else {
+                        return;
+                    };
+
+                    let otp = match vault.get_otp(&entry_object.id()) {
+                        Ok(Some(uri)) => Otp::parse(&uri),
+                        _ => {
+                            show_alert(
+                                &window,
+                                "Error",
+                                "Failed to decrypt the one-time password.",
+                            );
+                            return;
+                        }
+                    };
+
+                    match otp {
+                        Ok(otp) => otp_popover(
+                            cred_otp,
+                            &vault,
+                            &entry_object.id(),
+                            otp,
+                            &toast,
+                            config.clipboard_timeout,
+                        ),
+                        Err(e) => show_alert(
+                            &window,
+                            "Error",
+                            &format!("Invalid one-time password URI: {e}."),
+                        ),
+                    }
+                }
+            ));
+
+            cred_box.append(&cred_otp);
+
+            let cred_edit = gtk::Button::from_icon_name("document-edit-symbolic");
+            cred_edit.set_tooltip_text(Some("Edit"));
+
+            cred_edit.connect_clicked(clone!(
+                #[weak]
+                list_item,
+                #[weak]
+                window,
+                #[strong]
+                vault,
+                move |_| {
+                    if let Some(entry_object) = list_item.item().and_downcast::<EntryObject>() {
+                        entry_editor(&window, Rc::clone(&vault), entry_object.id());
+                    }
+                }
+            ));
+
+            cred_box.append(&cred_edit);
+
             let del_pwd_icon = Image::from_file("icons/remove.png");
             del_pwd_icon.set_size_request(16, 16);
 
-            let cred_del_pwd = gtk::Button::builder()
-                .label("-")
-                //.css_name("del_pwd")
-                .build();
+            let cred_del_pwd = gtk::Button::builder().label("-").build();
             cred_del_pwd.set_child(Some(&del_pwd_icon));
-            //cred_del_pwd.set_size_request(30, 12);
-            cred_del_pwd.set_margin_bottom(7);
-            cred_del_pwd.set_margin_top(7);
-            cred_del_pwd.set_margin_end(12);
+
+            cred_del_pwd.connect_clicked(clone!(
+                #[weak]
+                list_item,
+                #[weak]
+                window,
+                #[strong]
+                vault,
+                move |_| {
+                    let Some(entry_object) = list_item.item().and_downcast::<EntryObject>()
+                    else {
+                        return;
+                    };
+
+                    if vault.remove(&entry_object.id()).is_ok() {
+                        show_alert(&window, "Success", "Successfully remove password.");
+                    } else {
+                        show_alert(&window, "Error", "Failed to remove password.");
+                    }
+                }
+            ));
 
             cred_box.append(&cred_del_pwd);
 
-            let cred_username_to_del = cred_username.label().clone();
-            let window_clone = window.clone();
+            list_item.set_child(Some(&cred_box));
+        });
+
+        factory.connect_bind(|_, list_item| {
+            let list_item = list_item
+                .downcast_ref::<gtk::ListItem>()
+                .expect("Needs to be a ListItem");
+            let cred_pwd = list_item
+                .child()
+                .and_then(|cred_box| cred_box.first_child())
+                .and_downcast::<gtk::Label>()
+                .expect("Needs to be a Label");
+
+            cred_pwd.set_text("show");
+
+            /* Show, copy, then the one-time password button */
+            let has_otp = list_item
+                .item()
+                .and_downcast::<EntryObject>()
+                .is_some_and(|entry_object| entry_object.has_otp());
+            if let Some(cred_otp) = cred_pwd.next_sibling().and_then(|copy| copy.next_sibling()) {
+                cred_otp.set_visible(has_otp);
+            }
+        });
+
+        gtk::ColumnViewColumn::new(None, Some(factory))
+    }
+
+    /// Shows the one-time password of an entry under `button`, with a copy
+    /// button. A TOTP code is renewed every period with a countdown until
+    /// the popover closes; an HOTP code is only shown once its counter has
+    /// moved on and been stored, so that it is never shown twice.
+    fn otp_popover(
+        button: &Button,
+        vault: &VaultModel,
+        id: &str,
+        mut otp: Otp,
+        toast: &Toast,
+        clipboard_timeout: u32,
+    ) {
+        let time = otp::now();
+        let code = otp.code(time);
+
+        let status = match otp.kind {
+            Kind::Totp { .. } => format!("Valid for {}s", otp.remaining(time)),
+            Kind::Hotp { counter } => {
+                otp.advance();
+                if vault.set_otp(id, &otp.to_uri()).is_err() {
+                    toast.countdown(
+                        3,
+                        |_| "Error while saving the HOTP counter.".to_string(),
+                        || {},
+                    );
+                    return;
+                }
+                format!("Counter {counter}")
+            }
+        };
+
+        let popover_box = gtk::Box::new(gtk::Orientation::Vertical, 7);
+
+        let code_label = gtk::Label::new(Some(&code));
+        code_label.add_css_class("title");
+        code_label.set_selectable(true);
+
+        let status_label = gtk::Label::new(Some(&status));
+        status_label.add_css_class("content");
+
+        let copy_button = Button::with_label("Copy");
+        copy_button.add_css_class("mgr_button");
+
+        copy_button.connect_clicked(clone!(
+            #[weak]
+            code_label,
+            #[strong]
+            toast,
+            move |_| {
+                copy_secret(
+                    code_label.text().as_str(),
+                    "One-time password",
+                    clipboard_timeout,
+                    &toast,
+                );
+            }
+        ));
+
+        popover_box.append(&code_label);
+        popover_box.append(&status_label);
+        popover_box.append(&copy_button);
+
+        let popover = gtk::Popover::new();
+        popover.set_child(Some(&popover_box));
+        popover.set_parent(button);
+
+        let countdown = matches!(otp.kind, Kind::Totp { .. }).then(|| {
+            glib::timeout_add_seconds_local(
+                1,
+                clone!(
+                    #[weak]
+                    code_label,
+                    #[weak]
+                    status_label,
+                    #[upgrade_or]
+                    glib::ControlFlow::Break,
+                    move || {
+                        let time = otp::now();
+                        code_label.set_text(&otp.code(time));
+                        status_label.set_text(&format!("Valid for {}s", otp.remaining(time)));
+                        glib::ControlFlow::Continue
+                    }
+                ),
+            )
+        });
+        let countdown = RefCell::new(countdown);
+
+        popover.connect_closed(move |popover| {
+            if let Some(source) = countdown.take() {
+                source.remove();
+            }
+            popover.unparent();
+        });
+
+        popover.popup();
+    }
+
+    /// Asks what to do with a new entry duplicating an existing one.
+    fn resolve_duplicate(
+        window: &ApplicationWindow,
+        vault: Rc<VaultModel>,
+        entry: Entry,
+        existing: Entry,
+    ) {
+        let alert_dialog = gtk::AlertDialog::builder()
+            .message("Duplicate entry")
+            .detail(format!(
+                "An entry for {} at {} already exists. \
+                 Merge the new fields into it, overwrite it or keep both?",
+                existing.username,
+                if existing.url.is_empty() { "no URL" } else { existing.url.as_str() },
+            ))
+            .buttons(["Cancel", "Merge", "Overwrite", "Keep both"])
+            .cancel_button(0)
+            .default_button(1)
+            .modal(true)
+            .build();
+
+        alert_dialog.choose(
+            Some(window),
+            None::<&gtk::gio::Cancellable>,
+            clone!(
+                #[weak]
+                window,
+                move |choice| {
+                    let result = match choice {
+                        Ok(1) => {
+                            let mut merged = existing.clone();
+                            merged.merge(&entry);
+                            vault.update(&existing.id, &merged)
+                        }
+                        Ok(2) => vault.update(&existing.id, &entry),
+                        Ok(3) => vault.add(&entry).map(|_| ()),
+                        _ => return,
+                    };
+
+                    match result {
+                        Ok(()) => show_alert(
+                            &window,
+                            "Success",
+                            "Password successfully added to database.",
+                        ),
+                        Err(_) => show_alert(&window, "Error", "Error while saving the entry."),
+                    }
+                }
+            ),
+        );
+    }
+
+    /// Asks for a CSV file, then for the field of each of its columns.
+    fn choose_csv(window: &ApplicationWindow, vault: Rc<VaultModel>) {
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("CSV files"));
+        filter.add_suffix("csv");
+
+        let filters = gio::ListStore::new::<gtk::FileFilter>();
+        filters.append(&filter);
+
+        let file_dialog = gtk::FileDialog::builder()
+            .title("Import CSV")
+            .filters(&filters)
+            .modal(true)
+            .build();
+
+        file_dialog.open(
+            Some(window),
+            None::<&gio::Cancellable>,
+            clone!(
+                #[weak]
+                window,
+                move |result| {
+                    let Some(path) = result.ok().and_then(|file| file.path()) else {
+                        return;
+                    };
+
+                    match Table::read(&path) {
+                        Ok(table) if table.headers.is_empty() => {
+                            show_alert(&window, "Error", "This file is empty.")
+                        }
+                        Ok(table) => csv_mapping_dialog(&window, vault, table),
+                        Err(e) => {
+                            show_alert(&window, "Error", &format!("Cannot read the file: {e}"))
+                        }
+                    }
+                }
+            ),
+        );
+    }
+
+    /// Modal dialog mapping each column of a CSV file to a field, preset
+    /// when the layout of the file is recognized.
+    fn csv_mapping_dialog(window: &ApplicationWindow, vault: Rc<VaultModel>, table: Table) {
+        let preset = Preset::detect(&table.headers);
+        let mapping = preset
+            .map(|preset| preset.mapping(&table.headers))
+            .unwrap_or_else(|| Mapping(vec![None; table.headers.len()]));
+
+        let dialog = gtk::Window::builder()
+            .title("Import CSV")
+            .transient_for(window)
+            .modal(true)
+            .default_width(520)
+            .default_height(480)
+            .build();
+        dialog.add_css_class("window");
+
+        let import_box = gtk::Box::new(gtk::Orientation::Vertical, 7);
+        import_box.set_margin_top(12);
+        import_box.set_margin_end(12);
+        import_box.set_margin_bottom(12);
+        import_box.set_margin_start(12);
+
+        let description = match preset {
+            Some(preset) => format!(
+                "{} rows in the {} layout. Check the field each column is imported into.",
+                table.rows.len(),
+                preset.name()
+            ),
+            None => format!(
+                "{} rows. Choose the field each column is imported into.",
+                table.rows.len()
+            ),
+        };
+        let description_label = gtk::Label::new(Some(&description));
+        description_label.add_css_class("content");
+        description_label.set_wrap(true);
+        description_label.set_halign(gtk::Align::Start);
+
+        let columns_grid = gtk::Grid::new();
+        columns_grid.set_row_spacing(7);
+        columns_grid.set_column_spacing(12);
+
+        let mut field_names = vec!["ignore"];
+        field_names.extend(Field::ALL.iter().map(|field| field.name()));
+
+        let sample = table
+            .rows
+            .first()
+            .map(|(_, row)| row.as_slice())
+            .unwrap_or_default();
+        let mut field_dropdowns = Vec::new();
+
+        for (index, header) in table.headers.iter().enumerate() {
+            let header_label = gtk::Label::new(Some(header));
+            header_label.set_halign(gtk::Align::Start);
+
+            let sample_label = gtk::Label::new(sample.get(index).map(String::as_str));
+            sample_label.add_css_class("content");
+            sample_label.set_halign(gtk::Align::Start);
+            sample_label.set_hexpand(true);
+            sample_label.set_max_width_chars(24);
+            sample_label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+
+            let field_dropdown = gtk::DropDown::from_strings(&field_names);
+            let selected = mapping.0[index]
+                .and_then(|field| Field::ALL.iter().position(|f| *f == field))
+                .map_or(0, |position| position + 1);
+            field_dropdown.set_selected(selected as u32);
+
+            let row = index as i32;
+            columns_grid.attach(&header_label, 0, row, 1, 1);
+            columns_grid.attach(&sample_label, 1, row, 1, 1);
+            columns_grid.attach(&field_dropdown, 2, row, 1, 1);
+
+            field_dropdowns.push(field_dropdown);
+        }
+
+        let columns_scroll = gtk::ScrolledWindow::builder()
+            .child(&columns_grid)
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .vexpand(true)
+            .build();
+
+        let duplicate_box = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+        let duplicate_label = gtk::Label::new(Some("Entries already stored"));
+        duplicate_label.set_hexpand(true);
+        duplicate_label.set_halign(gtk::Align::Start);
+
+        let duplicate_dropdown =
+            gtk::DropDown::from_strings(&["Skip", "Merge", "Overwrite", "Keep both"]);
+
+        duplicate_box.append(&duplicate_label);
+        duplicate_box.append(&duplicate_dropdown);
+
+        let error_label = gtk::Label::new(None);
+        error_label.add_css_class("content");
+        error_label.set_wrap(true);
+        error_label.set_visible(false);
+
+        let buttons_box = gtk::Box::new(gtk::Orientation::Horizontal, 7);
+        buttons_box.set_halign(gtk::Align::End);
+
+        let cancel_button = Button::with_label("Cancel");
+        let import_button = Button::with_label("Import");
+        cancel_button.add_css_class("mgr_button");
+        import_button.add_css_class("mgr_button");
+
+        buttons_box.append(&cancel_button);
+        buttons_box.append(&import_button);
+
+        import_box.append(&description_label);
+        import_box.append(&columns_scroll);
+        import_box.append(&duplicate_box);
+        import_box.append(&error_label);
+        import_box.append(&buttons_box);
+
+        dialog.set_child(Some(&import_box));
+
+        cancel_button.connect_clicked(clone!(
+            #[weak]
+            dialog,
+            move |_| dialog.close()
+        ));
+
+        let escape_shortcut = gtk::ShortcutController::new();
+        escape_shortcut.add_shortcut(gtk::Shortcut::new(
+            gtk::ShortcutTrigger::parse_string("Escape"),
+            Some(gtk::NamedAction::new("window.close")),
+        ));
+        dialog.add_controller(escape_shortcut);
+
+        import_button.connect_clicked(clone!(
+            #[weak]
+            dialog,
+            #[weak]
+            window,
+            move |_| {
+                let mapping = Mapping(
+                    field_dropdowns
+                        .iter()
+                        .map(|dropdown| match dropdown.selected() {
+                            0 => None,
+                            selected => Field::ALL.get(selected as usize - 1).copied(),
+                        })
+                        .collect(),
+                );
+
+                if !mapping.has(Field::Password) {
+                    error_label.set_text("Choose the column holding the passwords.");
+                    error_label.set_visible(true);
+                    return;
+                }
+
+                let on_duplicate = match duplicate_dropdown.selected() {
+                    1 => OnDuplicate::Merge,
+                    2 => OnDuplicate::Overwrite,
+                    3 => OnDuplicate::KeepBoth,
+                    _ => OnDuplicate::Skip,
+                };
+
+                let stored = match vault.entries() {
+                    Ok(stored) => stored,
+                    Err(_) => {
+                        error_label.set_text("Error while reading database.");
+                        error_label.set_visible(true);
+                        return;
+                    }
+                };
+
+                let import = Import::plan(&stored, table.entries(&mapping), on_duplicate);
+                dialog.close();
+                confirm_import(&window, Rc::clone(&vault), import, &table.malformed);
+            }
+        ));
+
+        dialog.present();
+    }
 
-            cred_del_pwd.connect_clicked(move |_| {
-                if let Ok(_) =
-                    remove_password(&cred_username_to_del.as_str(), enc_key_clone.as_str())
-                {
-                    show_alert(&window_clone, "Success", "Successfully remove password.");
+    /// Lists what an import would change, and writes it once confirmed.
+    fn confirm_import(
+        window: &ApplicationWindow,
+        vault: Rc<VaultModel>,
+        import: Import,
+        malformed: &[(u64, String)],
+    ) {
+        let mut detail = format!(
+            "{} entries will be added, {} updated and {} skipped.",
+            import.added.len(),
+            import.updated.len(),
+            import.skipped.len() + malformed.len()
+        );
+
+        let skipped = malformed
+            .iter()
+            .map(|(line, reason)| format!("Line {line}: {reason}"))
+            .chain(import.skipped.iter().map(|(entry, reason)| {
+                let name = if entry.title.is_empty() {
+                    &entry.url
                 } else {
-                    show_alert(&window_clone, "Error", "Failed to remove password.");
+                    &entry.title
+                };
+                format!("{name}: {reason}")
+            }))
+            .collect::<Vec<_>>();
+
+        /* Only the first ones, the dialog does not scroll */
+        for line in skipped.iter().take(10) {
+            detail.push('\n');
+            detail.push_str(line);
+        }
+        if skipped.len() > 10 {
+            detail.push_str(&format!("\nAnd {} more.", skipped.len() - 10));
+        }
+
+        let alert_dialog = gtk::AlertDialog::builder()
+            .message("Import entries")
+            .detail(detail)
+            .buttons(["Cancel", "Import"])
+            .cancel_button(0)
+            .default_button(1)
+            .modal(true)
+            .build();
+
+        alert_dialog.choose(
+            Some(window),
+            None::<&gio::Cancellable>,
+            clone!(
+                #[weak]
+                window,
+                move |choice| {
+                    if choice != Ok(1) {
+                        return;
+                    }
+
+                    match vault.import(&import) {
+                        Ok(()) => show_alert(&window, "Success", "Entries successfully imported."),
+                        Err(_) => {
+                            show_alert(&window, "Error", "Error while importing the entries.")
+                        }
+                    }
                 }
-            });
+            ),
+        );
+    }
 
-            pwds_box_clone.append(&cred_box);
+    /// Modal dialog exporting entries unencrypted: the fields and format are
+    /// chosen, the key typed again and the risk acknowledged before the
+    /// destination is asked for.
+    fn plaintext_export_dialog(window: &ApplicationWindow, vault: Rc<VaultModel>) {
+        let dialog = gtk::Window::builder()
+            .title("Export Plaintext")
+            .transient_for(window)
+            .modal(true)
+            .default_width(420)
+            .build();
+        dialog.add_css_class("window");
+
+        let export_box = gtk::Box::new(gtk::Orientation::Vertical, 7);
+        export_box.set_margin_top(12);
+        export_box.set_margin_end(12);
+        export_box.set_margin_bottom(12);
+        export_box.set_margin_start(12);
+
+        let warning_label = gtk::Label::new(Some(
+            "The chosen fields of every entry are written to a file without \
+             encryption. Only export to hand credentials to another system, \
+             and delete the file afterwards.",
+        ));
+        warning_label.add_css_class("content");
+        warning_label.set_wrap(true);
+        warning_label.set_halign(gtk::Align::Start);
+
+        let fields_grid = gtk::Grid::new();
+        fields_grid.set_row_spacing(7);
+        fields_grid.set_column_spacing(12);
+
+        let field_checks: Vec<gtk::CheckButton> = FIELD_NAMES
+            .iter()
+            .map(|field| {
+                let check = gtk::CheckButton::with_label(field);
+                check.set_active(true);
+                check
+            })
+            .collect();
+
+        for (index, check) in field_checks.iter().enumerate() {
+            let index = index as i32;
+            fields_grid.attach(check, index % 4, index / 4, 1, 1);
         }
 
-        pwds_box
+        let format_box = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+        let format_label = gtk::Label::new(Some("Format"));
+        format_label.set_hexpand(true);
+        format_label.set_halign(gtk::Align::Start);
+
+        let format_names: Vec<&str> = Format::ALL.iter().map(|format| format.name()).collect();
+        let format_dropdown = gtk::DropDown::from_strings(&format_names);
+
+        format_box.append(&format_label);
+        format_box.append(&format_dropdown);
+
+        let key_entry = gtk::PasswordEntry::builder()
+            .placeholder_text("Encryption Key")
+            .show_peek_icon(true)
+            .build();
+
+        let understood_check = gtk::CheckButton::with_label(
+            "I understand that anyone who can read the file can read these passwords",
+        );
+
+        let error_label = gtk::Label::new(None);
+        error_label.add_css_class("content");
+        error_label.set_wrap(true);
+        error_label.set_visible(false);
+
+        let buttons_box = gtk::Box::new(gtk::Orientation::Horizontal, 7);
+        buttons_box.set_halign(gtk::Align::End);
+
+        let cancel_button = Button::with_label("Cancel");
+        let export_button = Button::with_label("Export");
+        cancel_button.add_css_class("mgr_button");
+        export_button.add_css_class("mgr_button");
+        export_button.set_sensitive(false);
+
+        buttons_box.append(&cancel_button);
+        buttons_box.append(&export_button);
+
+        export_box.append(&warning_label);
+        export_box.append(&fields_grid);
+        export_box.append(&format_box);
+        export_box.append(&key_entry);
+        export_box.append(&understood_check);
+        export_box.append(&error_label);
+        export_box.append(&buttons_box);
+
+        dialog.set_child(Some(&export_box));
+
+        understood_check.connect_toggled(clone!(
+            #[weak]
+            export_button,
+            move |check| export_button.set_sensitive(check.is_active())
+        ));
+
+        cancel_button.connect_clicked(clone!(
+            #[weak]
+            dialog,
+            move |_| dialog.close()
+        ));
+
+        let escape_shortcut = gtk::ShortcutController::new();
+        escape_shortcut.add_shortcut(gtk::Shortcut::new(
+            gtk::ShortcutTrigger::parse_string("Escape"),
+            Some(gtk::NamedAction::new("window.close")),
+        ));
+        dialog.add_controller(escape_shortcut);
+
+        export_button.connect_clicked(clone!(
+            #[weak]
+            dialog,
+            #[weak]
+            window,
+            #[weak]
+            key_entry,
+            move |_| {
+                let fields: Vec<&'static str> = FIELD_NAMES
+                    .iter()
+                    .zip(&field_checks)
+                    .filter(|(_, check)| check.is_active())
+                    .map(|(field, _)| *field)
+                    .collect();
+
+                if fields.is_empty() {
+                    error_label.set_text("Choose at least one field.");
+                    error_label.set_visible(true);
+                    return;
+                }
+
+                /* The key is asked again even though the vault is unlocked */
+                if let Err(refusal) = throttle::unlock(key_entry.text().as_str()) {
+                    error_label.set_text(&refusal.to_string());
+                    error_label.set_visible(true);
+                    return;
+                }
+
+                let format = Format::ALL
+                    .get(format_dropdown.selected() as usize)
+                    .copied()
+                    .unwrap_or(Format::Csv);
+
+                dialog.close();
+                choose_plaintext_file(&window, Rc::clone(&vault), fields, format);
+            }
+        ));
+
+        dialog.present();
+    }
+
+    /// Asks where to write a plaintext export, records it in the audit log,
+    /// then writes it.
+    fn choose_plaintext_file(
+        window: &ApplicationWindow,
+        vault: Rc<VaultModel>,
+        fields: Vec<&'static str>,
+        format: Format,
+    ) {
+        let file_dialog = gtk::FileDialog::builder()
+            .title("Export Plaintext")
+            .initial_name(format!("pwds.{}", format.extension()))
+            .modal(true)
+            .build();
+
+        file_dialog.save(
+            Some(window),
+            None::<&gio::Cancellable>,
+            clone!(
+                #[weak]
+                window,
+                move |result| {
+                    let Some(path) = result.ok().and_then(|file| file.path()) else {
+                        return;
+                    };
+
+                    let entries = match vault.entries() {
+                        Ok(entries) => entries,
+                        Err(_) => {
+                            show_alert(&window, "Error", "Error while reading database.");
+                            return;
+                        }
+                    };
+
+                    let event = format!(
+                        "plaintext-export interface=gui format={} entries={} fields={} output={:?}",
+                        format.extension(),
+                        entries.len(),
+                        fields.join(","),
+                        path
+                    );
+                    if let Err(e) = audit::record(&event) {
+                        show_alert(
+                            &window,
+                            "Error",
+                            &format!("Cannot write {AUDIT_PATH}, nothing was exported: {e}"),
+                        );
+                        return;
+                    }
+
+                    /* The file dialog already asked before replacing a file */
+                    match plaintext::save(&path, &entries, &fields, format, true) {
+                        Ok(()) => show_alert(
+                            &window,
+                            "Success",
+                            &format!("{} entries exported to {}.", entries.len(), path.display()),
+                        ),
+                        Err(e) => {
+                            show_alert(&window, "Error", &format!("Error while exporting: {e}"))
+                        }
+                    }
+                }
+            ),
+        );
+    }
+
+    /// Modal editor prefilled with every field of the entry with the given id.
+    fn entry_editor(window: &ApplicationWindow, vault: Rc<VaultModel>, id: String) {
+        let entry = match vault.get_entry(&id) {
+            Ok(Some(entry)) => entry,
+            Ok(None) => {
+                show_alert(window, "Error", "This entry no longer exists.");
+                return;
+            }
+            Err(_) => {
+                show_alert(window, "Error", "Failed to decrypt the entry.");
+                return;
+            }
+        };
+
+        let dialog = gtk::Window::builder()
+            .title("Edit entry")
+            .transient_for(window)
+            .modal(true)
+            .default_width(420)
+            .build();
+        dialog.add_css_class("window");
+
+        let editor_box = gtk::Box::new(gtk::Orientation::Vertical, 7);
+        editor_box.set_margin_top(12);
+        editor_box.set_margin_end(12);
+        editor_box.set_margin_bottom(12);
+        editor_box.set_margin_start(12);
+
+        let title_entry = gtk::Entry::builder()
+            .css_name("entry")
+            .placeholder_text("Title (optional)")
+            .text(entry.title.as_str())
+            .build();
+
+        let username_entry = gtk::Entry::builder()
+            .css_name("entry")
+            .placeholder_text("Username")
+            .text(entry.username.as_str())
+            .build();
+
+        let password_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+
+        let password_entry = gtk::PasswordEntry::builder()
+            .css_name("entry")
+            .placeholder_text("Password")
+            .show_peek_icon(true)
+            .hexpand(true)
+            .build();
+        password_entry.set_text(&entry.password);
+
+        let gen_icon = Image::from_file("icons/gen_.png");
+        gen_icon.set_size_request(22, 22);
+
+        let password_generate = gtk::Button::builder()
+            .width_request(24)
+            .margin_start(7)
+            .build();
+        password_generate.set_child(Some(&gen_icon));
+
+        password_generate.connect_clicked(clone!(
+            #[weak]
+            password_entry,
+            move |_| {
+                if let Ok(pwd) = gen_pwd() {
+                    password_entry.set_text(&pwd);
+                }
+            }
+        ));
+
+        password_box.append(&password_entry);
+        password_box.append(&password_generate);
+
+        let url_entry = gtk::Entry::builder()
+            .css_name("entry")
+            .placeholder_text("URL (optional)")
+            .text(entry.url.as_str())
+            .build();
+
+        let tags_entry = gtk::Entry::builder()
+            .css_name("entry")
+            .placeholder_text("Tags, comma separated (optional)")
+            .text(entry.tags.join(", "))
+            .build();
+
+        let notes_view = gtk::TextView::builder()
+            .wrap_mode(gtk::WrapMode::WordChar)
+            .height_request(80)
+            .build();
+        notes_view.buffer().set_text(&entry.notes);
+
+        let otp_entry = gtk::PasswordEntry::builder()
+            .css_name("entry")
+            .placeholder_text("otpauth:// URI of one-time passwords (optional)")
+            .show_peek_icon(true)
+            .build();
+        otp_entry.set_text(&entry.otp);
+
+        let error_label = gtk::Label::new(None);
+        error_label.add_css_class("content");
+        error_label.set_wrap(true);
+        error_label.set_visible(false);
+
+        let buttons_box = gtk::Box::new(gtk::Orientation::Horizontal, 7);
+        buttons_box.set_halign(gtk::Align::End);
+
+        let cancel_button = Button::with_label("Cancel");
+        let save_button = Button::with_label("Save");
+        cancel_button.add_css_class("mgr_button");
+        save_button.add_css_class("mgr_button");
+
+        buttons_box.append(&cancel_button);
+        buttons_box.append(&save_button);
+
+        editor_box.append(&title_entry);
+        editor_box.append(&username_entry);
+        editor_box.append(&password_box);
+        editor_box.append(&url_entry);
+        editor_box.append(&tags_entry);
+        editor_box.append(&notes_view);
+        editor_box.append(&otp_entry);
+        editor_box.append(&error_label);
+        editor_box.append(&buttons_box);
+
+        dialog.set_child(Some(&editor_box));
+
+        cancel_button.connect_clicked(clone!(
+            #[weak]
+            dialog,
+            move |_| dialog.close()
+        ));
+
+        let escape_shortcut = gtk::ShortcutController::new();
+        escape_shortcut.add_shortcut(gtk::Shortcut::new(
+            gtk::ShortcutTrigger::parse_string("Escape"),
+            Some(gtk::NamedAction::new("window.close")),
+        ));
+        dialog.add_controller(escape_shortcut);
+
+        save_button.connect_clicked(clone!(
+            #[weak]
+            dialog,
+            move |_| {
+                let notes_buffer = notes_view.buffer();
+
+                let edited = Entry {
+                    id: id.clone(),
+                    title: title_entry.text().to_string(),
+                    username: username_entry.text().to_string(),
+                    password: password_entry.text().to_string(),
+                    url: url_entry.text().to_string(),
+                    tags: parse_tags(tags_entry.text().as_str()),
+                    notes: notes_buffer
+                        .text(&notes_buffer.start_iter(), &notes_buffer.end_iter(), false)
+                        .to_string(),
+                    otp: otp_entry.text().trim().to_string(),
+                };
+
+                let result = edited.validate().and_then(|()| {
+                    vault.update(&id, &edited).map_err(|e| match e.kind() {
+                        io::ErrorKind::NotFound => "This entry no longer exists.".to_string(),
+                        io::ErrorKind::AlreadyExists => {
+                            "Another entry already uses this username and URL.".to_string()
+                        }
+                        _ => "Error while saving the entry.".to_string(),
+                    })
+                });
+
+                match result {
+                    Ok(()) => dialog.close(),
+                    Err(message) => {
+                        error_label.set_text(&message);
+                        error_label.set_visible(true);
+                    }
+                }
+            }
+        ));
+
+        dialog.present();
     }
 
-    fn credits_page(_enc_key: String) -> gtk::Box {
+    fn credits_page() -> gtk::Box {
         let credits_box = gtk::Box::new(gtk::Orientation::Vertical, 7);
         credits_box.set_hexpand(true);
         credits_box.set_vexpand(true);
@@ -1,13 +1,38 @@
+#[cfg(feature = "gui")]
 use std::fs::File;
+#[cfg(feature = "gui")]
 use std::io;
+#[cfg(feature = "gui")]
 use std::io::Write;
+use clap::Parser;
+#[cfg(feature = "gui")]
 use gtk::prelude::*;
+#[cfg(feature = "gui")]
 use gtk::Application;
 
-mod pwds;
+use pwds_core::{
+    bitwarden, crypto, csv, import, kdbx, otp, pass, plaintext, portable, pwds, search, vault,
+};
+
+#[cfg(feature = "gui")]
 mod gui;
-mod crypto;
+#[cfg(feature = "gui")]
+mod entry_object;
+#[cfg(feature = "gui")]
+mod vault_model;
+mod config;
+mod throttle;
+mod cli;
+mod agent;
+mod session;
+mod audit;
+mod tui;
+#[cfg(feature = "gui")]
+mod secret_service;
+mod git_credential;
+mod template;
 
+#[cfg(feature = "gui")]
 const ASCII_ART: &str = r###"
           # #### ####
         ### \/#|### |/####                > PWDS <
@@ -25,9 +50,39 @@ const ASCII_ART: &str = r###"
                {
 "###;
 
+#[cfg(feature = "gui")]
 const APP_ID: &str = "org.gtk_rs.pwds";
 
 fn main() {
+    let cli = cli::Cli::parse();
+
+    if let Some(command) = cli.command {
+        let json = command.json();
+
+        if let Err(error) = cli::run(command, &cli.key_source) {
+            error.report(json);
+            std::process::exit(error.kind.exit_code());
+        }
+        return;
+    }
+
+    run_gui();
+}
+
+#[cfg(not(feature = "gui"))]
+fn run_gui() {
+    eprintln!("[-] pwds was built without the graphical interface. Use `pwds tui` instead.");
+    std::process::exit(1);
+}
+
+#[cfg(feature = "gui")]
+fn run_gui() {
+    /* Over SSH there is no display, point to the terminal interface instead */
+    if let Err(e) = gtk::init() {
+        eprintln!("[-] Cannot start the graphical interface: {e}. Use `pwds tui` instead.");
+        std::process::exit(1);
+    }
+
     println!("{ASCII_ART}");
     Write::flush(&mut io::stdout()).expect("[-] Error during flush.");
 
@@ -52,6 +107,7 @@ fn main() {
     app.connect_startup(|_| gui::gui::load_css());
     app.connect_activate(gui::gui::build_ui);
 
-    app.run();
+    /* The arguments were already parsed by clap */
+    app.run_with_args(&std::env::args().take(1).collect::<Vec<_>>());
 
 }
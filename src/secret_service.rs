@@ -0,0 +1,1099 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::rc::Rc;
+
+use glib::variant::ObjectPath;
+use gtk::gio;
+use gtk::prelude::*;
+
+use crate::agent;
+use crate::gui::gui::unlock_dialog;
+use crate::pwds::{self, Entry};
+use crate::session::{self, Session};
+
+const BUS_NAME: &str = "org.freedesktop.secrets";
+const SERVICE_PATH: &str = "/org/freedesktop/secrets";
+const COLLECTION_PATH: &str = "/org/freedesktop/secrets/collection/pwds";
+const SESSION_PATH: &str = "/org/freedesktop/secrets/session";
+const PROMPT_PATH: &str = "/org/freedesktop/secrets/prompt";
+/// Returned instead of a prompt when none is needed.
+const NO_PROMPT: &str = "/";
+
+const SERVICE_INTERFACE: &str = "org.freedesktop.Secret.Service";
+const COLLECTION_INTERFACE: &str = "org.freedesktop.Secret.Collection";
+const ITEM_INTERFACE: &str = "org.freedesktop.Secret.Item";
+const SESSION_INTERFACE: &str = "org.freedesktop.Secret.Session";
+const PROMPT_INTERFACE: &str = "org.freedesktop.Secret.Prompt";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+const ERROR_IS_LOCKED: &str = "org.freedesktop.Secret.Error.IsLocked";
+const ERROR_NO_SESSION: &str = "org.freedesktop.Secret.Error.NoSession";
+const ERROR_NO_SUCH_OBJECT: &str = "org.freedesktop.Secret.Error.NoSuchObject";
+const ERROR_NOT_SUPPORTED: &str = "org.freedesktop.DBus.Error.NotSupported";
+const ERROR_INVALID_ARGS: &str = "org.freedesktop.DBus.Error.InvalidArgs";
+const ERROR_FAILED: &str = "org.freedesktop.DBus.Error.Failed";
+const ERROR_UNKNOWN_PROPERTY: &str = "org.freedesktop.DBus.Error.UnknownProperty";
+const ERROR_READ_ONLY: &str = "org.freedesktop.DBus.Error.PropertyReadOnly";
+
+/// Item properties given to `CreateItem`.
+const LABEL_PROPERTY: &str = "org.freedesktop.Secret.Item.Label";
+const ATTRIBUTES_PROPERTY: &str = "org.freedesktop.Secret.Item.Attributes";
+
+const INTROSPECTION: &str = r#"
+<node>
+  <interface name="org.freedesktop.Secret.Service">
+    <method name="OpenSession">
+      <arg name="algorithm" type="s" direction="in"/>
+      <arg name="input" type="v" direction="in"/>
+      <arg name="output" type="v" direction="out"/>
+      <arg name="result" type="o" direction="out"/>
+    </method>
+    <method name="CreateCollection">
+      <arg name="properties" type="a{sv}" direction="in"/>
+      <arg name="alias" type="s" direction="in"/>
+      <arg name="collection" type="o" direction="out"/>
+      <arg name="prompt" type="o" direction="out"/>
+    </method>
+    <method name="SearchItems">
+      <arg name="attributes" type="a{ss}" direction="in"/>
+      <arg name="unlocked" type="ao" direction="out"/>
+      <arg name="locked" type="ao" direction="out"/>
+    </method>
+    <method name="Unlock">
+      <arg name="objects" type="ao" direction="in"/>
+      <arg name="unlocked" type="ao" direction="out"/>
+      <arg name="prompt" type="o" direction="out"/>
+    </method>
+    <method name="Lock">
+      <arg name="objects" type="ao" direction="in"/>
+      <arg name="locked" type="ao" direction="out"/>
+      <arg name="prompt" type="o" direction="out"/>
+    </method>
+    <method name="GetSecrets">
+      <arg name="items" type="ao" direction="in"/>
+      <arg name="session" type="o" direction="in"/>
+      <arg name="secrets" type="a{o(oayays)}" direction="out"/>
+    </method>
+    <method name="ReadAlias">
+      <arg name="name" type="s" direction="in"/>
+      <arg name="collection" type="o" direction="out"/>
+    </method>
+    <method name="SetAlias">
+      <arg name="name" type="s" direction="in"/>
+      <arg name="collection" type="o" direction="in"/>
+    </method>
+    <property name="Collections" type="ao" access="read"/>
+  </interface>
+  <interface name="org.freedesktop.Secret.Collection">
+    <method name="Delete">
+      <arg name="prompt" type="o" direction="out"/>
+    </method>
+    <method name="SearchItems">
+      <arg name="attributes" type="a{ss}" direction="in"/>
+      <arg name="results" type="ao" direction="out"/>
+    </method>
+    <method name="CreateItem">
+      <arg name="properties" type="a{sv}" direction="in"/>
+      <arg name="secret" type="(oayays)" direction="in"/>
+      <arg name="replace" type="b" direction="in"/>
+      <arg name="item" type="o" direction="out"/>
+      <arg name="prompt" type="o" direction="out"/>
+    </method>
+    <signal name="ItemCreated">
+      <arg name="item" type="o"/>
+    </signal>
+    <signal name="ItemDeleted">
+      <arg name="item" type="o"/>
+    </signal>
+    <signal name="ItemChanged">
+      <arg name="item" type="o"/>
+    </signal>
+    <property name="Items" type="ao" access="read"/>
+    <property name="Label" type="s" access="read"/>
+    <property name="Locked" type="b" access="read"/>
+    <property name="Created" type="t" access="read"/>
+    <property name="Modified" type="t" access="read"/>
+  </interface>
+  <interface name="org.freedesktop.Secret.Item">
+    <method name="Delete">
+      <arg name="prompt" type="o" direction="out"/>
+    </method>
+    <method name="GetSecret">
+      <arg name="session" type="o" direction="in"/>
+      <arg name="secret" type="(oayays)" direction="out"/>
+    </method>
+    <method name="SetSecret">
+      <arg name="secret" type="(oayays)" direction="in"/>
+    </method>
+    <property name="Locked" type="b" access="read"/>
+    <property name="Attributes" type="a{ss}" access="readwrite"/>
+    <property name="Label" type="s" access="readwrite"/>
+    <property name="Created" type="t" access="read"/>
+    <property name="Modified" type="t" access="read"/>
+  </interface>
+  <interface name="org.freedesktop.Secret.Session">
+    <method name="Close"/>
+  </interface>
+  <interface name="org.freedesktop.Secret.Prompt">
+    <method name="Prompt">
+      <arg name="window-id" type="s" direction="in"/>
+    </method>
+    <method name="Dismiss"/>
+    <signal name="Completed">
+      <arg name="dismissed" type="b"/>
+      <arg name="result" type="v"/>
+    </signal>
+  </interface>
+</node>
+"#;
+
+/// A secret as sent over D-Bus: session, parameters, value and content type.
+type Secret = (ObjectPath, Vec<u8>, Vec<u8>, String);
+
+/// Freedesktop Secret Service backed by the pwds database, so that libsecret
+/// clients can store and read their credentials in it.
+///
+/// The database is exposed as a single collection, also the `default` alias,
+/// with one item per entry. Item attributes are kept in the tags of the
+/// entry as `name=value`. Only the `plain` session algorithm is supported:
+/// secrets travel unencrypted over the session bus, which only the user can use.
+struct Service {
+    connection: gio::DBusConnection,
+    node: gio::DBusNodeInfo,
+    /// The unlocked database, see `unlocked`.
+    vault: Option<Session>,
+    /// Item objects, by entry id.
+    items: HashMap<String, gio::RegistrationId>,
+    sessions: HashMap<String, gio::RegistrationId>,
+    prompts: HashMap<String, gio::RegistrationId>,
+    next_object: u32,
+}
+
+type Shared = Rc<RefCell<Service>>;
+
+/// Serves the Secret Service on the session bus until the name is lost.
+pub fn run() -> Result<(), String> {
+    /* Prompts need a display, without one they are dismissed */
+    let has_display = gtk::init().is_ok();
+    if !has_display {
+        eprintln!("[-] No display, unlock prompts will be dismissed.");
+    }
+
+    let node = gio::DBusNodeInfo::for_xml(INTROSPECTION).map_err(|e| e.to_string())?;
+    let main_loop = glib::MainLoop::new(None, false);
+    let service: Rc<RefCell<Option<Shared>>> = Rc::new(RefCell::new(None));
+
+    let _owner_id = gio::bus_own_name(
+        gio::BusType::Session,
+        BUS_NAME,
+        gio::BusNameOwnerFlags::NONE,
+        glib::clone!(
+            #[strong]
+            service,
+            #[strong]
+            main_loop,
+            move |connection, _| {
+                match register(connection, node.clone(), has_display) {
+                    Ok(registered) => *service.borrow_mut() = Some(registered),
+                    Err(e) => {
+                        eprintln!("[-] Cannot register the Secret Service: {e}");
+                        main_loop.quit();
+                    }
+                }
+            }
+        ),
+        |_, name| println!("[+] Serving {name} on the session bus"),
+        glib::clone!(
+            #[strong]
+            main_loop,
+            move |_, name| {
+                eprintln!("[-] Lost {name}, is another secret service running?");
+                main_loop.quit();
+            }
+        ),
+    );
+
+    main_loop.run();
+    Ok(())
+}
+
+fn register(
+    connection: gio::DBusConnection,
+    node: gio::DBusNodeInfo,
+    has_display: bool,
+) -> Result<Shared, glib::Error> {
+    let service = Rc::new(RefCell::new(Service {
+        connection: connection.clone(),
+        node,
+        vault: None,
+        items: HashMap::new(),
+        sessions: HashMap::new(),
+        prompts: HashMap::new(),
+        next_object: 0,
+    }));
+
+    let interface = interface_info(&service, SERVICE_INTERFACE);
+    let weak = Rc::downgrade(&service);
+    connection
+        .register_object(SERVICE_PATH, &interface)
+        .method_call(glib::clone!(
+            #[strong]
+            weak,
+            move |_, _, _, interface, method, params, invocation| {
+                let Some(service) = weak.upgrade() else {
+                    return;
+                };
+
+                if interface == PROPERTIES_INTERFACE {
+                    properties_method(
+                        method,
+                        &params,
+                        invocation,
+                        &["Collections"],
+                        |_| Some(vec![collection_path()].to_variant()),
+                        |_, _| Err(ERROR_READ_ONLY),
+                    );
+                } else {
+                    service_method(&service, method, params, invocation, has_display);
+                }
+            }
+        ))
+        .build()?;
+
+    let interface = interface_info(&service, COLLECTION_INTERFACE);
+    connection
+        .register_object(COLLECTION_PATH, &interface)
+        .method_call(glib::clone!(
+            #[strong]
+            weak,
+            move |_, _, _, interface, method, params, invocation| {
+                let Some(service) = weak.upgrade() else {
+                    return;
+                };
+
+                if interface != PROPERTIES_INTERFACE {
+                    collection_method(&service, method, params, invocation, has_display);
+                    return;
+                }
+
+                properties_method(
+                    method,
+                    &params,
+                    invocation,
+                    &["Items", "Label", "Locked", "Created", "Modified"],
+                    |property| {
+                        Some(match property {
+                            "Items" => item_paths(&service).to_variant(),
+                            "Label" => "pwds".to_variant(),
+                            "Locked" => (!unlocked(&service)).to_variant(),
+                            _ => 0u64.to_variant(),
+                        })
+                    },
+                    |_, _| Err(ERROR_READ_ONLY),
+                );
+            }
+        ))
+        .build()?;
+
+    sync_items(&service);
+    Ok(service)
+}
+
+fn interface_info(service: &Shared, name: &str) -> gio::DBusInterfaceInfo {
+    service
+        .borrow()
+        .node
+        .lookup_interface(name)
+        .expect("Interface missing from the introspection data")
+}
+
+fn object_path(path: &str) -> ObjectPath {
+    ObjectPath::try_from(path).expect("Invalid object path")
+}
+
+fn collection_path() -> ObjectPath {
+    object_path(COLLECTION_PATH)
+}
+
+fn item_path(id: &str) -> ObjectPath {
+    object_path(&format!("{COLLECTION_PATH}/{id}"))
+}
+
+/// Returns the id of the entry behind an item path.
+fn item_id(path: &str) -> Option<&str> {
+    path.strip_prefix(COLLECTION_PATH)?.strip_prefix('/')
+}
+
+fn next_path(service: &Shared, prefix: &str) -> String {
+    let mut service = service.borrow_mut();
+    service.next_object += 1;
+    format!("{prefix}/{}", service.next_object)
+}
+
+/// Follows the agent, so that unlocking or locking pwds anywhere applies
+/// here too, and tells whether the vault is unlocked.
+fn unlocked(service: &Shared) -> bool {
+    let path = session::db_path();
+
+    match agent::status(&path) {
+        Ok(true) => service.borrow_mut().vault = Some(Session::Agent(path)),
+        Ok(false) => service.borrow_mut().vault = None,
+        /* No agent, the vault unlocked here is kept */
+        Err(_) => {}
+    }
+
+    service.borrow().vault.is_some()
+}
+
+fn vault(service: &Shared) -> Option<Session> {
+    unlocked(service)
+        .then(|| service.borrow().vault.clone())
+        .flatten()
+}
+
+fn item_paths(service: &Shared) -> Vec<ObjectPath> {
+    sync_items(service);
+
+    let service = service.borrow();
+    let mut ids: Vec<&String> = service.items.keys().collect();
+    ids.sort();
+    ids.into_iter().map(|id| item_path(id)).collect()
+}
+
+/// Registers an object per stored entry and drops the objects of removed
+/// entries. Entry ids are not encrypted, so this works while locked.
+fn sync_items(service: &Shared) {
+    let ids = match pwds::entry_ids() {
+        Ok(ids) => ids,
+        Err(e) => {
+            eprintln!("[-] Error while reading database: {e}");
+            return;
+        }
+    };
+
+    let removed: Vec<String> = service
+        .borrow()
+        .items
+        .keys()
+        .filter(|id| !ids.contains(id))
+        .cloned()
+        .collect();
+
+    for id in removed {
+        let registration = service.borrow_mut().items.remove(&id);
+        if let Some(registration) = registration {
+            let _ = service.borrow().connection.unregister_object(registration);
+        }
+    }
+
+    for id in ids {
+        if service.borrow().items.contains_key(&id) {
+            continue;
+        }
+
+        match register_item(service, &id) {
+            Ok(registration) => {
+                service.borrow_mut().items.insert(id, registration);
+            }
+            Err(e) => eprintln!("[-] Cannot register item {id}: {e}"),
+        }
+    }
+}
+
+fn register_item(service: &Shared, id: &str) -> Result<gio::RegistrationId, glib::Error> {
+    let interface = interface_info(service, ITEM_INTERFACE);
+    let connection = service.borrow().connection.clone();
+    let weak = Rc::downgrade(service);
+    let id = id.to_string();
+
+    connection
+        .register_object(item_path(&id).as_str(), &interface)
+        .method_call(glib::clone!(
+            #[strong]
+            weak,
+            #[strong]
+            id,
+            move |_, _, _, interface, method, params, invocation| {
+                let Some(service) = weak.upgrade() else {
+                    return;
+                };
+
+                if interface != PROPERTIES_INTERFACE {
+                    item_method(&service, &id, method, params, invocation);
+                    return;
+                }
+
+                properties_method(
+                    method,
+                    &params,
+                    invocation,
+                    &["Locked", "Attributes", "Label", "Created", "Modified"],
+                    |property| item_property(&service, &id, property),
+                    |property, value| set_item_property(&service, &id, property, value),
+                );
+            }
+        ))
+        .build()
+}
+
+fn service_method(
+    service: &Shared,
+    method: &str,
+    params: glib::Variant,
+    invocation: gio::DBusMethodInvocation,
+    has_display: bool,
+) {
+    match method {
+        "OpenSession" => {
+            let algorithm = params.child_value(0).get::<String>().unwrap_or_default();
+
+            if algorithm != "plain" {
+                invocation.return_dbus_error(
+                    ERROR_NOT_SUPPORTED,
+                    &format!("The {algorithm} algorithm is not supported, use plain"),
+                );
+                return;
+            }
+
+            match open_session(service) {
+                Ok(path) => invocation.return_value(Some(&("".to_variant(), path).to_variant())),
+                Err(e) => invocation.return_dbus_error(ERROR_FAILED, &e.to_string()),
+            }
+        }
+        "CreateCollection" => {
+            invocation.return_value(Some(
+                &(collection_path(), object_path(NO_PROMPT)).to_variant(),
+            ));
+        }
+        "SearchItems" => {
+            let (attributes,) = params
+                .get::<(HashMap<String, String>,)>()
+                .unwrap_or_default();
+            let shared = Rc::clone(service);
+
+            when_unlocked(service, has_display, move |unlocked| {
+                let found = if unlocked {
+                    search(&shared, &attributes)
+                } else {
+                    Vec::new()
+                };
+                invocation.return_value(Some(&(found, Vec::<ObjectPath>::new()).to_variant()));
+            });
+        }
+        "Unlock" => {
+            let (objects,) = params.get::<(Vec<ObjectPath>,)>().unwrap_or_default();
+
+            if unlocked(service) {
+                invocation.return_value(Some(&(objects, object_path(NO_PROMPT)).to_variant()));
+                return;
+            }
+
+            match create_prompt(service, objects, has_display) {
+                Ok(prompt) => {
+                    invocation.return_value(Some(&(Vec::<ObjectPath>::new(), prompt).to_variant()))
+                }
+                Err(e) => invocation.return_dbus_error(ERROR_FAILED, &e.to_string()),
+            }
+        }
+        "Lock" => {
+            let (objects,) = params.get::<(Vec<ObjectPath>,)>().unwrap_or_default();
+
+            service.borrow_mut().vault = None;
+            let _ = agent::lock();
+            invocation.return_value(Some(&(objects, object_path(NO_PROMPT)).to_variant()));
+        }
+        "GetSecrets" => {
+            let (items, session) = params
+                .get::<(Vec<ObjectPath>, ObjectPath)>()
+                .unwrap_or_else(|| (Vec::new(), object_path(NO_PROMPT)));
+
+            if !service.borrow().sessions.contains_key(session.as_str()) {
+                invocation.return_dbus_error(ERROR_NO_SESSION, "No such session");
+                return;
+            }
+
+            let Some(vault) = vault(service) else {
+                invocation.return_dbus_error(ERROR_IS_LOCKED, "The collection is locked");
+                return;
+            };
+
+            let mut secrets: HashMap<ObjectPath, Secret> = HashMap::new();
+            for item in items {
+                let Some(id) = item_id(&item) else {
+                    continue;
+                };
+
+                if let Ok(Some(password)) = vault.password(id) {
+                    secrets.insert(item.clone(), secret(&session, password));
+                }
+            }
+
+            invocation.return_value(Some(&(secrets,).to_variant()));
+        }
+        "ReadAlias" => {
+            let (name,) = params.get::<(String,)>().unwrap_or_default();
+            let path = if name == "default" {
+                collection_path()
+            } else {
+                object_path(NO_PROMPT)
+            };
+
+            invocation.return_value(Some(&(path,).to_variant()));
+        }
+        "SetAlias" => {
+            invocation.return_dbus_error(ERROR_NOT_SUPPORTED, "pwds has a single collection");
+        }
+        _ => invocation.return_dbus_error(ERROR_NOT_SUPPORTED, "Unknown method"),
+    }
+}
+
+fn collection_method(
+    service: &Shared,
+    method: &str,
+    params: glib::Variant,
+    invocation: gio::DBusMethodInvocation,
+    has_display: bool,
+) {
+    match method {
+        "Delete" => {
+            invocation.return_dbus_error(ERROR_NOT_SUPPORTED, "The collection cannot be deleted");
+        }
+        "SearchItems" => {
+            let (attributes,) = params
+                .get::<(HashMap<String, String>,)>()
+                .unwrap_or_default();
+            let shared = Rc::clone(service);
+
+            when_unlocked(service, has_display, move |unlocked| {
+                let found = if unlocked {
+                    search(&shared, &attributes)
+                } else {
+                    Vec::new()
+                };
+                invocation.return_value(Some(&(found,).to_variant()));
+            });
+        }
+        "CreateItem" => {
+            let Some((properties, secret, replace)) =
+                params.get::<(HashMap<String, glib::Variant>, Secret, bool)>()
+            else {
+                invocation.return_dbus_error(ERROR_INVALID_ARGS, "Invalid arguments");
+                return;
+            };
+
+            match create_item(service, &properties, secret, replace) {
+                Ok(path) => {
+                    invocation.return_value(Some(&(path, object_path(NO_PROMPT)).to_variant()))
+                }
+                Err((name, message)) => invocation.return_dbus_error(name, &message),
+            }
+        }
+        _ => invocation.return_dbus_error(ERROR_NOT_SUPPORTED, "Unknown method"),
+    }
+}
+
+fn item_method(
+    service: &Shared,
+    id: &str,
+    method: &str,
+    params: glib::Variant,
+    invocation: gio::DBusMethodInvocation,
+) {
+    let Some(vault) = vault(service) else {
+        invocation.return_dbus_error(ERROR_IS_LOCKED, "The collection is locked");
+        return;
+    };
+
+    match method {
+        "Delete" => match vault.remove(id) {
+            Ok(()) => {
+                sync_items(service);
+                emit_item_signal(service, "ItemDeleted", id);
+                invocation.return_value(Some(&(object_path(NO_PROMPT),).to_variant()));
+            }
+            Err(e) => return_io_error(invocation, e),
+        },
+        "GetSecret" => {
+            let (session,) = params
+                .get::<(ObjectPath,)>()
+                .unwrap_or_else(|| (object_path(NO_PROMPT),));
+
+            if !service.borrow().sessions.contains_key(session.as_str()) {
+                invocation.return_dbus_error(ERROR_NO_SESSION, "No such session");
+                return;
+            }
+
+            match vault.password(id) {
+                Ok(Some(password)) => {
+                    invocation.return_value(Some(&(secret(&session, password),).to_variant()))
+                }
+                Ok(None) => invocation.return_dbus_error(ERROR_NO_SUCH_OBJECT, "No such item"),
+                Err(e) => return_io_error(invocation, e),
+            }
+        }
+        "SetSecret" => {
+            let Some(((_, _, value, _),)) = params.get::<(Secret,)>() else {
+                invocation.return_dbus_error(ERROR_INVALID_ARGS, "Invalid secret");
+                return;
+            };
+
+            let Ok(password) = String::from_utf8(value) else {
+                invocation.return_dbus_error(ERROR_INVALID_ARGS, "The secret must be text");
+                return;
+            };
+
+            match vault.set_password(id, &password) {
+                Ok(()) => {
+                    emit_item_signal(service, "ItemChanged", id);
+                    invocation.return_value(None);
+                }
+                Err(e) => return_io_error(invocation, e),
+            }
+        }
+        _ => invocation.return_dbus_error(ERROR_NOT_SUPPORTED, "Unknown method"),
+    }
+}
+
+/// Answers `org.freedesktop.DBus.Properties` calls on an object exposing
+/// `names`. GLib hands them to the method handler when no property handler
+/// is registered, which we rely on as gio swaps the getter and the setter.
+fn properties_method(
+    method: &str,
+    params: &glib::Variant,
+    invocation: gio::DBusMethodInvocation,
+    names: &[&str],
+    get: impl Fn(&str) -> Option<glib::Variant>,
+    set: impl Fn(&str, glib::Variant) -> Result<(), &'static str>,
+) {
+    let name = params
+        .try_child_value(1)
+        .and_then(|name| name.get::<String>())
+        .unwrap_or_default();
+
+    if method != "GetAll" && !names.contains(&name.as_str()) {
+        invocation.return_dbus_error(ERROR_UNKNOWN_PROPERTY, &format!("No property {name}"));
+        return;
+    }
+
+    match method {
+        "Get" => match get(&name) {
+            Some(value) => invocation.return_value(Some(&(value,).to_variant())),
+            None => invocation.return_dbus_error(ERROR_FAILED, &format!("Cannot read {name}")),
+        },
+        "GetAll" => {
+            let values: HashMap<String, glib::Variant> = names
+                .iter()
+                .filter_map(|name| Some((name.to_string(), get(name)?)))
+                .collect();
+            invocation.return_value(Some(&(values,).to_variant()));
+        }
+        "Set" => {
+            let Some(value) = params
+                .try_child_value(2)
+                .and_then(|value| value.as_variant())
+            else {
+                invocation.return_dbus_error(ERROR_INVALID_ARGS, "Invalid arguments");
+                return;
+            };
+
+            match set(&name, value) {
+                Ok(()) => invocation.return_value(None),
+                Err(error) => invocation.return_dbus_error(error, &format!("Cannot set {name}")),
+            }
+        }
+        _ => invocation.return_dbus_error(ERROR_NOT_SUPPORTED, "Unknown method"),
+    }
+}
+
+fn item_property(service: &Shared, id: &str, property: &str) -> Option<glib::Variant> {
+    let entry = vault(service).and_then(|vault| vault.entry(id).ok().flatten());
+
+    Some(match (property, entry) {
+        ("Locked", entry) => entry.is_none().to_variant(),
+        ("Attributes", Some(entry)) => attributes(&entry).to_variant(),
+        ("Attributes", None) => HashMap::<String, String>::new().to_variant(),
+        ("Label", Some(entry)) => entry.title.to_variant(),
+        ("Label", None) => "".to_variant(),
+        ("Created" | "Modified", _) => 0u64.to_variant(),
+        _ => return None,
+    })
+}
+
+fn set_item_property(
+    service: &Shared,
+    id: &str,
+    property: &str,
+    value: glib::Variant,
+) -> Result<(), &'static str> {
+    let Some(vault) = vault(service) else {
+        return Err(ERROR_IS_LOCKED);
+    };
+    let Ok(Some(mut entry)) = vault.entry(id) else {
+        return Err(ERROR_NO_SUCH_OBJECT);
+    };
+
+    match property {
+        "Label" => match value.get::<String>() {
+            Some(label) => entry.title = label,
+            None => return Err(ERROR_INVALID_ARGS),
+        },
+        "Attributes" => match value.get::<HashMap<String, String>>() {
+            Some(attributes) => set_attributes(&mut entry, &attributes),
+            None => return Err(ERROR_INVALID_ARGS),
+        },
+        _ => return Err(ERROR_READ_ONLY),
+    }
+
+    entry.validate().map_err(|_| ERROR_INVALID_ARGS)?;
+    vault.update(id, &entry).map_err(|_| ERROR_FAILED)?;
+
+    emit_item_signal(service, "ItemChanged", id);
+    Ok(())
+}
+
+/// Runs `f` once the vault is unlocked, asking for the key if needed.
+/// `f` gets false when the vault stays locked.
+fn when_unlocked(service: &Shared, has_display: bool, f: impl FnOnce(bool) + 'static) {
+    if unlocked(service) {
+        f(true);
+        return;
+    }
+
+    if !has_display {
+        f(false);
+        return;
+    }
+
+    let service = Rc::downgrade(service);
+    unlock_dialog(move |vault| {
+        let unlocked = match (vault, service.upgrade()) {
+            (Some(vault), Some(service)) => {
+                service.borrow_mut().vault = Some(vault);
+                true
+            }
+            _ => false,
+        };
+        f(unlocked);
+    });
+}
+
+fn open_session(service: &Shared) -> Result<ObjectPath, glib::Error> {
+    let path = next_path(service, SESSION_PATH);
+    let interface = interface_info(service, SESSION_INTERFACE);
+    let connection = service.borrow().connection.clone();
+    let weak = Rc::downgrade(service);
+
+    let registration = connection
+        .register_object(&path, &interface)
+        .method_call(glib::clone!(
+            #[strong]
+            path,
+            move |connection, _, _, _, _, _, invocation| {
+                let registration = weak
+                    .upgrade()
+                    .and_then(|service| service.borrow_mut().sessions.remove(&path));
+                if let Some(registration) = registration {
+                    let _ = connection.unregister_object(registration);
+                }
+                invocation.return_value(None);
+            }
+        ))
+        .build()?;
+
+    service
+        .borrow_mut()
+        .sessions
+        .insert(path.clone(), registration);
+    Ok(object_path(&path))
+}
+
+/// Creates the prompt returned by `Unlock`, which asks for the key once
+/// the client calls `Prompt`.
+fn create_prompt(
+    service: &Shared,
+    objects: Vec<ObjectPath>,
+    has_display: bool,
+) -> Result<ObjectPath, glib::Error> {
+    let path = next_path(service, PROMPT_PATH);
+    let interface = interface_info(service, PROMPT_INTERFACE);
+    let connection = service.borrow().connection.clone();
+    let weak = Rc::downgrade(service);
+
+    let registration = connection
+        .register_object(&path, &interface)
+        .method_call(glib::clone!(
+            #[strong]
+            path,
+            move |_, _, _, _, method, _, invocation| {
+                invocation.return_value(None);
+
+                let Some(service) = weak.upgrade() else {
+                    return;
+                };
+
+                if method == "Dismiss" {
+                    complete_prompt(&service, &path, None);
+                    return;
+                }
+
+                let objects = objects.clone();
+                let path = path.clone();
+                let shared = Rc::clone(&service);
+                when_unlocked(&service, has_display, move |unlocked| {
+                    complete_prompt(&shared, &path, unlocked.then_some(objects));
+                });
+            }
+        ))
+        .build()?;
+
+    service
+        .borrow_mut()
+        .prompts
+        .insert(path.clone(), registration);
+    Ok(object_path(&path))
+}
+
+/// Emits `Completed` with the unlocked objects, `None` meaning dismissed.
+fn complete_prompt(service: &Shared, path: &str, unlocked: Option<Vec<ObjectPath>>) {
+    let connection = service.borrow().connection.clone();
+
+    let Some(registration) = service.borrow_mut().prompts.remove(path) else {
+        return;
+    };
+
+    let dismissed = unlocked.is_none();
+    let objects = unlocked.unwrap_or_default();
+
+    if let Err(e) = connection.emit_signal(
+        None,
+        path,
+        PROMPT_INTERFACE,
+        "Completed",
+        Some(&(dismissed, objects.to_variant()).to_variant()),
+    ) {
+        eprintln!("[-] Cannot complete prompt {path}: {e}");
+    }
+
+    let _ = connection.unregister_object(registration);
+}
+
+fn create_item(
+    service: &Shared,
+    properties: &HashMap<String, glib::Variant>,
+    (session, _, value, _): Secret,
+    replace: bool,
+) -> Result<ObjectPath, (&'static str, String)> {
+    if !service.borrow().sessions.contains_key(session.as_str()) {
+        return Err((ERROR_NO_SESSION, "No such session".to_string()));
+    }
+
+    let Some(vault) = vault(service) else {
+        return Err((ERROR_IS_LOCKED, "The collection is locked".to_string()));
+    };
+
+    let label = properties
+        .get(LABEL_PROPERTY)
+        .and_then(|label| label.get::<String>())
+        .unwrap_or_default();
+    let attributes = properties
+        .get(ATTRIBUTES_PROPERTY)
+        .and_then(|attributes| attributes.get::<HashMap<String, String>>())
+        .unwrap_or_default();
+    let password = String::from_utf8(value)
+        .map_err(|_| (ERROR_INVALID_ARGS, "The secret must be text".to_string()))?;
+
+    let mut entry = Entry {
+        title: label,
+        password,
+        ..Default::default()
+    };
+    set_attributes(&mut entry, &attributes);
+    entry
+        .validate()
+        .map_err(|message| (ERROR_INVALID_ARGS, message))?;
+
+    let failed = |e: io::Error| (ERROR_FAILED, e.to_string());
+
+    let existing = if replace {
+        vault
+            .entries()
+            .map_err(failed)?
+            .into_iter()
+            .find(|stored| self::attributes(stored) == self::attributes(&entry))
+    } else {
+        None
+    };
+
+    let id = match existing {
+        Some(existing) => {
+            /* Clients know nothing of one-time passwords, keep them */
+            entry.otp = existing.otp;
+            vault.update(&existing.id, &entry).map_err(failed)?;
+            emit_item_signal(service, "ItemChanged", &existing.id);
+            existing.id
+        }
+        None => {
+            let id = vault.add(&entry).map_err(failed)?;
+            sync_items(service);
+            emit_item_signal(service, "ItemCreated", &id);
+            id
+        }
+    };
+
+    Ok(item_path(&id))
+}
+
+fn search(service: &Shared, query: &HashMap<String, String>) -> Vec<ObjectPath> {
+    let Some(vault) = vault(service) else {
+        return Vec::new();
+    };
+
+    let entries = match vault.entry_list() {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("[-] Error while reading database: {e}");
+            return Vec::new();
+        }
+    };
+
+    entries
+        .iter()
+        .filter(|entry| {
+            let attributes = attributes(entry);
+            query
+                .iter()
+                .all(|(name, value)| attributes.get(name) == Some(value))
+        })
+        .map(|entry| item_path(&entry.id))
+        .collect()
+}
+
+fn emit_item_signal(service: &Shared, signal: &str, id: &str) {
+    let connection = service.borrow().connection.clone();
+
+    if let Err(e) = connection.emit_signal(
+        None,
+        COLLECTION_PATH,
+        COLLECTION_INTERFACE,
+        signal,
+        Some(&(item_path(id),).to_variant()),
+    ) {
+        eprintln!("[-] Cannot emit {signal}: {e}");
+    }
+}
+
+fn secret(session: &ObjectPath, password: String) -> Secret {
+    (
+        session.clone(),
+        Vec::new(),
+        password.into_bytes(),
+        "text/plain".to_string(),
+    )
+}
+
+fn return_io_error(invocation: gio::DBusMethodInvocation, e: io::Error) {
+    let name = match e.kind() {
+        io::ErrorKind::NotFound => ERROR_NO_SUCH_OBJECT,
+        _ => ERROR_FAILED,
+    };
+    invocation.return_dbus_error(name, &e.to_string());
+}
+
+/// Attributes of an item: the `name=value` tags of the entry, plus its
+/// username and URL when no tag already gives them.
+fn attributes(entry: &Entry) -> HashMap<String, String> {
+    let mut attributes: HashMap<String, String> = entry
+        .tags
+        .iter()
+        .filter_map(|tag| tag.split_once('='))
+        .map(|(name, value)| (unescape(name), unescape(value)))
+        .collect();
+
+    attributes
+        .entry("username".to_string())
+        .or_insert_with(|| entry.username.clone());
+    if !entry.url.is_empty() {
+        attributes
+            .entry("url".to_string())
+            .or_insert_with(|| entry.url.clone());
+    }
+
+    attributes
+}
+
+/// Stores the attributes as tags, replacing the previous ones, and fills
+/// the username and URL from the usual libsecret attributes.
+fn set_attributes(entry: &mut Entry, attributes: &HashMap<String, String>) {
+    entry.tags.retain(|tag| !tag.contains('='));
+
+    let mut names: Vec<&String> = attributes.keys().collect();
+    names.sort();
+    entry.tags.extend(
+        names
+            .into_iter()
+            .map(|name| format!("{}={}", escape(name), escape(&attributes[name]))),
+    );
+
+    let username = ["username", "user", "account"]
+        .iter()
+        .find_map(|name| attributes.get(*name))
+        .filter(|username| !username.is_empty());
+    entry.username = match username {
+        Some(username) => username.clone(),
+        None if !entry.title.is_empty() => entry.title.replace(':', "_"),
+        None => "secret-service".to_string(),
+    };
+
+    if let Some(url) = attributes.get("url") {
+        entry.url = url.clone();
+    } else if let Some(server) = attributes.get("server") {
+        let protocol = attributes.get("protocol").map_or("https", String::as_str);
+        let port = attributes
+            .get("port")
+            .filter(|port| !port.is_empty() && *port != "0")
+            .map(|port| format!(":{port}"))
+            .unwrap_or_default();
+        let path = attributes
+            .get("object")
+            .map(|path| format!("/{}", path.trim_start_matches('/')))
+            .unwrap_or_default();
+
+        entry.url = format!("{protocol}://{server}{port}{path}").replace(char::is_whitespace, "");
+    }
+}
+
+/// Escapes the characters tags cannot hold, see `parse_tags`.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '%' | ',' | '=' | ' ' | '\t' | '\n' | '\r' => {
+                escaped.push_str(&format!("%{:02X}", c as u32));
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        let hex = tail.get(..2).and_then(|hex| std::str::from_utf8(hex).ok());
+        match (byte, hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(decoded)) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
@@ -0,0 +1,218 @@
+use std::io;
+use std::path::{self, Path, PathBuf};
+
+use crate::agent::{self, Request};
+use crate::pwds::{self, Entry};
+use crate::vault::Vault;
+
+/// The unlocked database the interfaces work on: opened by this process
+/// with a key it verified, or held by the agent, which answers the entry
+/// requests without handing out the key, see `agent::serve`.
+#[derive(Clone)]
+pub enum Session {
+    Local(Vault),
+    /// The absolute path of the database unlocked in the agent.
+    Agent(PathBuf),
+}
+
+/// Absolute path of `pwds::DB_PATH`, which is relative to the current
+/// directory, as the agent expects it.
+pub fn db_path() -> PathBuf {
+    path::absolute(pwds::DB_PATH).unwrap_or_else(|_| PathBuf::from(pwds::DB_PATH))
+}
+
+/* Some requests only come from the GUI and the Secret Service */
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+impl Session {
+    /// The database as unlocked in the agent, if it is, with a key that
+    /// still decrypts it.
+    pub fn from_agent() -> Option<Self> {
+        let path = db_path();
+
+        agent::status(&path)
+            .unwrap_or(false)
+            .then_some(Session::Agent(path))
+    }
+
+    /// Opens the database with a verified key, then hands it to the agent,
+    /// if one is running, so that the other processes skip the prompt.
+    pub fn open(key: &str) -> io::Result<Self> {
+        let vault = Vault::new(db_path(), key)?;
+        agent::unlock(&vault);
+
+        Ok(Session::Local(vault))
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            Session::Local(vault) => vault.path(),
+            Session::Agent(path) => path,
+        }
+    }
+
+    /// Whether the key still decrypts the database, which another process
+    /// may have re-encrypted, or the agent still holds it unlocked.
+    pub fn verify_key(&self) -> io::Result<bool> {
+        match self {
+            Session::Local(vault) => vault.verify_key(),
+            Session::Agent(path) => agent::status(path),
+        }
+    }
+
+    pub fn check(&self) -> io::Result<()> {
+        match self {
+            Session::Local(vault) => vault.check(),
+            Session::Agent(path) => agent::call(path, Request::Check),
+        }
+    }
+
+    pub fn entries(&self) -> io::Result<Vec<Entry>> {
+        match self {
+            Session::Local(vault) => vault.entries(),
+            Session::Agent(path) => agent::call(path, Request::Entries),
+        }
+    }
+
+    pub fn entry_list(&self) -> io::Result<Vec<Entry>> {
+        match self {
+            Session::Local(vault) => vault.entry_list(),
+            Session::Agent(path) => agent::call(path, Request::EntryList),
+        }
+    }
+
+    pub fn entry(&self, id: &str) -> io::Result<Option<Entry>> {
+        match self {
+            Session::Local(vault) => vault.entry(id),
+            Session::Agent(path) => agent::call(path, Request::Entry { id: id.to_string() }),
+        }
+    }
+
+    pub fn password(&self, id: &str) -> io::Result<Option<String>> {
+        match self {
+            Session::Local(vault) => vault.password(id),
+            Session::Agent(path) => agent::call(path, Request::Password { id: id.to_string() }),
+        }
+    }
+
+    pub fn otp(&self, id: &str) -> io::Result<Option<String>> {
+        match self {
+            Session::Local(vault) => vault.otp(id),
+            Session::Agent(path) => agent::call(path, Request::Otp { id: id.to_string() }),
+        }
+    }
+
+    pub fn find_duplicate(&self, entry: &Entry) -> io::Result<Option<Entry>> {
+        match self {
+            Session::Local(vault) => vault.find_duplicate(entry),
+            Session::Agent(path) => agent::call(
+                path,
+                Request::FindDuplicate {
+                    entry: entry.clone(),
+                },
+            ),
+        }
+    }
+
+    pub fn find_by_username(&self, username: &str) -> io::Result<Vec<Entry>> {
+        match self {
+            Session::Local(vault) => vault.find_by_username(username),
+            Session::Agent(path) => agent::call(
+                path,
+                Request::FindByUsername {
+                    username: username.to_string(),
+                },
+            ),
+        }
+    }
+
+    /// Saves a new entry and returns its id.
+    pub fn add(&self, entry: &Entry) -> io::Result<String> {
+        match self {
+            Session::Local(vault) => vault.add(entry),
+            Session::Agent(path) => agent::call(
+                path,
+                Request::Add {
+                    entry: entry.clone(),
+                },
+            ),
+        }
+    }
+
+    pub fn update(&self, id: &str, entry: &Entry) -> io::Result<()> {
+        match self {
+            Session::Local(vault) => vault.update(id, entry),
+            Session::Agent(path) => agent::call(
+                path,
+                Request::Update {
+                    id: id.to_string(),
+                    entry: entry.clone(),
+                },
+            ),
+        }
+    }
+
+    /// Writes updated and new entries at once, see `Vault::save_all`.
+    pub fn save_all(&self, updated: &[Entry], added: &[Entry]) -> io::Result<Vec<String>> {
+        match self {
+            Session::Local(vault) => vault.save_all(updated, added),
+            Session::Agent(path) => agent::call(
+                path,
+                Request::SaveAll {
+                    updated: updated.to_vec(),
+                    added: added.to_vec(),
+                },
+            ),
+        }
+    }
+
+    pub fn set_password(&self, id: &str, new_password: &str) -> io::Result<()> {
+        match self {
+            Session::Local(vault) => vault.set_password(id, new_password),
+            Session::Agent(path) => agent::call(
+                path,
+                Request::SetPassword {
+                    id: id.to_string(),
+                    password: new_password.to_string(),
+                },
+            ),
+        }
+    }
+
+    pub fn set_otp(&self, id: &str, uri: &str) -> io::Result<()> {
+        match self {
+            Session::Local(vault) => vault.set_otp(id, uri),
+            Session::Agent(path) => agent::call(
+                path,
+                Request::SetOtp {
+                    id: id.to_string(),
+                    uri: uri.to_string(),
+                },
+            ),
+        }
+    }
+
+    pub fn remove(&self, id: &str) -> io::Result<()> {
+        match self {
+            Session::Local(vault) => vault.remove(id),
+            Session::Agent(path) => agent::call(path, Request::Remove { id: id.to_string() }),
+        }
+    }
+
+    /// Re-encrypts the database with `new_key`, see `Vault::rekey`. The
+    /// agent keeps the vault unlocked with the new key.
+    pub fn rekey(&mut self, new_key: &str) -> io::Result<()> {
+        match self {
+            Session::Local(vault) => {
+                vault.rekey(new_key)?;
+                agent::unlock(vault);
+                Ok(())
+            }
+            Session::Agent(path) => agent::call(
+                path,
+                Request::Rekey {
+                    key: new_key.to_string(),
+                },
+            ),
+        }
+    }
+}
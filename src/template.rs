@@ -0,0 +1,149 @@
+/// Field a reference without one points to.
+const DEFAULT_FIELD: &str = "password";
+
+/// Replaces every `{{ pwds "entry" "field" }}` in `text` by what `lookup`
+/// returns for the entry and field, the field defaulting to the password.
+/// Other `{{ }}` pairs are kept, so that templates of other tools survive.
+/// Fails on the first reference that cannot be resolved, giving its line.
+pub fn render(
+    text: &str,
+    mut lookup: impl FnMut(&str, &str) -> Result<String, String>,
+) -> Result<String, String> {
+    let mut rendered = String::with_capacity(text.len());
+    let mut position = 0;
+
+    while let Some(start) = text[position..].find("{{").map(|start| start + position) {
+        let Some(end) = text[start..].find("}}").map(|end| end + start + 2) else {
+            break;
+        };
+
+        rendered.push_str(&text[position..start]);
+        position = end;
+
+        let line = text[..start].matches('\n').count() + 1;
+        let inner = &text[start + 2..end - 2];
+        let Some(words) = words(inner) else {
+            if inner.trim_start().starts_with("pwds") {
+                return Err(format!("line {line}: unclosed quote"));
+            }
+            rendered.push_str(&text[start..end]);
+            continue;
+        };
+
+        let (entry, field) = match words.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            ["pwds", entry] => (entry, DEFAULT_FIELD),
+            ["pwds", entry, field] => (entry, field),
+            ["pwds", ..] => {
+                return Err(format!(
+                    "line {line}: pwds takes an entry and an optional field"
+                ))
+            }
+            _ => {
+                rendered.push_str(&text[start..end]);
+                continue;
+            }
+        };
+
+        let value = lookup(entry, field).map_err(|e| format!("line {line}: {e}"))?;
+        rendered.push_str(&value);
+    }
+
+    rendered.push_str(&text[position..]);
+    Ok(rendered)
+}
+
+/// Splits the inside of a `{{ }}` pair into words. Quoted words can hold
+/// spaces, and `\"` or `\\` inside them. `None` when a quote is not closed.
+fn words(text: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut chars = text.trim().chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+
+        let mut word = String::new();
+        if c == '"' {
+            loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => word.push(chars.next()?),
+                    c => word.push(c),
+                }
+            }
+        } else {
+            word.push(c);
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                word.push(c);
+            }
+        }
+
+        words.push(word);
+    }
+
+    Some(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Looks entries up in a fixed list of `(entry, field, value)`.
+    fn lookup(entry: &str, field: &str) -> Result<String, String> {
+        [
+            ("mail", "password", "hunter2"),
+            ("mail", "username", "alice"),
+            ("Bank account", "password", "with \"quotes\""),
+        ]
+        .iter()
+        .find(|(name, wanted, _)| *name == entry && *wanted == field)
+        .map(|(_, _, value)| value.to_string())
+        .ok_or_else(|| format!("no {field} for {entry}"))
+    }
+
+    #[test]
+    fn fills_references() {
+        let text = "user={{ pwds \"mail\" \"username\" }}\npass={{pwds mail}}\n";
+        assert_eq!(render(text, lookup).unwrap(), "user=alice\npass=hunter2\n");
+
+        let text = r#"{{ pwds "Bank account" }}"#;
+        assert_eq!(render(text, lookup).unwrap(), "with \"quotes\"");
+    }
+
+    #[test]
+    fn keeps_other_templates() {
+        let text = "name: {{ .Values.name }}\nkey: {{ pwds mail }}\nopen: {{ never closed";
+        assert_eq!(
+            render(text, lookup).unwrap(),
+            "name: {{ .Values.name }}\nkey: hunter2\nopen: {{ never closed"
+        );
+    }
+
+    #[test]
+    fn reads_escaped_quotes() {
+        assert_eq!(
+            words(r#" pwds "a \"b\" \\ c" d "#).unwrap(),
+            ["pwds", r#"a "b" \ c"#, "d"]
+        );
+        assert_eq!(words(r#"pwds "open"#), None);
+    }
+
+    #[test]
+    fn reports_the_line() {
+        let text = "first\nsecond {{ pwds bank }}\n";
+        assert_eq!(
+            render(text, lookup).unwrap_err(),
+            "line 2: no password for bank"
+        );
+
+        let text = "\n\n{{ pwds \"mail }}";
+        assert_eq!(render(text, lookup).unwrap_err(), "line 3: unclosed quote");
+
+        let text = "{{ pwds mail password extra }}";
+        assert_eq!(
+            render(text, lookup).unwrap_err(),
+            "line 1: pwds takes an entry and an optional field"
+        );
+    }
+}
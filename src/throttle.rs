@@ -0,0 +1,290 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::{Config, LockoutAction};
+use crate::crypto::adjust_password_length;
+use crate::pwds;
+use crate::session::Session;
+
+pub const STATE_PATH: &str = "pwds.state";
+
+/// Longest wait between two unlock attempts, in seconds.
+const MAX_DELAY_SECS: u64 = 3600;
+
+/// Failed unlock attempts, persisted in `pwds.state` so that restarting
+/// pwds does not reset the back-off.
+#[derive(Debug, Default)]
+pub struct Throttle {
+    pub failed_attempts: u32,
+    /// Unix time of the last failed attempt.
+    pub last_failure: u64,
+}
+
+impl Throttle {
+    /// Loads the state file; a missing or unreadable file means no failures.
+    pub fn load() -> Self {
+        let mut throttle = Self::default();
+
+        if !Path::new(STATE_PATH).exists() {
+            return throttle;
+        }
+
+        let contents = match fs::read_to_string(STATE_PATH) {
+            Ok(contents) => contents,
+            Err(e) => {
+                eprintln!("[-] Error while reading {STATE_PATH}: {e}");
+                return throttle;
+            }
+        };
+
+        for line in contents.lines() {
+            match line.split_once('=').map(|(name, value)| (name.trim(), value.trim())) {
+                Some(("failed_attempts", value)) => {
+                    throttle.failed_attempts = value.parse().unwrap_or_default();
+                }
+                Some(("last_failure", value)) => {
+                    throttle.last_failure = value.parse().unwrap_or_default();
+                }
+                _ => {}
+            }
+        }
+
+        throttle
+    }
+
+    fn save(&self) -> io::Result<()> {
+        fs::write(
+            STATE_PATH,
+            format!(
+                "failed_attempts = {}\nlast_failure = {}\n",
+                self.failed_attempts, self.last_failure
+            ),
+        )
+    }
+
+    /// Seconds left before the next attempt is allowed. The delay doubles
+    /// with every failed attempt: 1s, 2s, 4s... up to one hour.
+    pub fn remaining_delay(&self) -> u64 {
+        if self.failed_attempts == 0 {
+            return 0;
+        }
+
+        let delay = 2u64
+            .checked_pow(self.failed_attempts - 1)
+            .unwrap_or(MAX_DELAY_SECS)
+            .min(MAX_DELAY_SECS);
+
+        (self.last_failure + delay).saturating_sub(now())
+    }
+
+    /// Returns true once `max_attempts` failures were recorded, 0 meaning no limit.
+    pub fn is_locked_out(&self, max_attempts: u32) -> bool {
+        max_attempts > 0 && self.failed_attempts >= max_attempts
+    }
+
+    pub fn record_failure(&mut self) -> io::Result<()> {
+        self.failed_attempts += 1;
+        self.last_failure = now();
+        self.save()
+    }
+
+    /// Forgets the failed attempts after a successful unlock.
+    pub fn record_success(&mut self) -> io::Result<()> {
+        *self = Self::default();
+
+        if Path::new(STATE_PATH).exists() {
+            fs::remove_file(STATE_PATH)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Why `unlock` refused a key.
+#[derive(Debug)]
+pub enum Refusal {
+    /// Too many failed attempts, see `Config::max_unlock_attempts`.
+    LockedOut,
+    /// The failed attempt that locked the vault out wiped the database.
+    Wiped,
+    /// Attempts are refused for this many more seconds.
+    TooSoon(u64),
+    /// The next attempt is allowed in this many seconds.
+    WrongKey(u64),
+    /// Locked out, but the database could not be wiped.
+    WipeFailed(io::Error),
+    /// The database cannot be read, or some entries do not decrypt with
+    /// the key, `InvalidData`.
+    Failed(io::Error),
+}
+
+impl fmt::Display for Refusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Refusal::LockedOut => write!(
+                f,
+                "Too many failed attempts. Remove {STATE_PATH} to unlock again."
+            ),
+            Refusal::Wiped => f.write_str("Too many failed attempts, the database was wiped."),
+            Refusal::TooSoon(delay) => {
+                write!(f, "Too many failed attempts, try again in {delay}s.")
+            }
+            Refusal::WrongKey(delay) => write!(f, "Wrong encryption key, wait {delay}s."),
+            Refusal::WipeFailed(e) => write!(f, "Error while wiping the database: {e}"),
+            Refusal::Failed(e) if e.kind() == io::ErrorKind::InvalidData => {
+                write!(f, "The database is corrupt: {e}")
+            }
+            Refusal::Failed(e) => write!(f, "Error while reading database: {e}"),
+        }
+    }
+}
+
+/// Unlocks the database with a key as the user typed it, the same way in
+/// every interface: no attempt is made while locked out or during the
+/// back-off, failures are recorded, and the one that locks the vault out
+/// wipes the database when `lockout_action` says so. Every entry must
+/// decrypt, so that no interface works on part of the vault.
+pub fn unlock(key: &str) -> Result<Session, Refusal> {
+    let config = Config::load();
+    let mut throttle = Throttle::load();
+
+    if throttle.is_locked_out(config.max_unlock_attempts) {
+        return Err(Refusal::LockedOut);
+    }
+
+    let delay = throttle.remaining_delay();
+    if delay > 0 {
+        return Err(Refusal::TooSoon(delay));
+    }
+
+    let key = adjust_password_length(key);
+
+    if !pwds::verify_key(&key).map_err(Refusal::Failed)? {
+        if let Err(e) = throttle.record_failure() {
+            eprintln!("[-] Error while recording failed attempt: {e}");
+        }
+
+        if !throttle.is_locked_out(config.max_unlock_attempts) {
+            return Err(Refusal::WrongKey(throttle.remaining_delay()));
+        }
+
+        if config.lockout_action == LockoutAction::Wipe {
+            pwds::wipe_db().map_err(Refusal::WipeFailed)?;
+            return Err(Refusal::Wiped);
+        }
+
+        return Err(Refusal::LockedOut);
+    }
+
+    if let Err(e) = throttle.record_success() {
+        eprintln!("[-] Error while resetting failed attempts: {e}");
+    }
+
+    pwds::check_vault(&key).map_err(Refusal::Failed)?;
+    Session::open(&key).map_err(Refusal::Failed)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::pwds::Entry;
+    use crate::vault::Vault;
+
+    #[test]
+    fn delay_doubles() {
+        let throttle = |failed_attempts, last_failure| Throttle {
+            failed_attempts,
+            last_failure,
+        };
+
+        assert_eq!(throttle(0, now()).remaining_delay(), 0);
+        assert!((0..=1).contains(&throttle(1, now()).remaining_delay()));
+        assert!((3..=4).contains(&throttle(3, now()).remaining_delay()));
+        assert!((3599..=3600).contains(&throttle(40, now()).remaining_delay()));
+        assert_eq!(throttle(3, 0).remaining_delay(), 0);
+
+        assert!(!throttle(100, 0).is_locked_out(0));
+        assert!(!throttle(2, 0).is_locked_out(3));
+        assert!(throttle(3, 0).is_locked_out(3));
+    }
+
+    /// Runs the test in a directory of its own, as the database, the
+    /// configuration and the state file are relative to it.
+    struct TempDir {
+        previous: PathBuf,
+        path: PathBuf,
+    }
+
+    impl TempDir {
+        fn enter(name: &str) -> Self {
+            let path = env::temp_dir().join(format!("pwds-{}-{name}", std::process::id()));
+            fs::create_dir_all(&path).unwrap();
+
+            let previous = env::current_dir().unwrap();
+            env::set_current_dir(&path).unwrap();
+            Self { previous, path }
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = env::set_current_dir(&self.previous);
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    fn write_state(failed_attempts: u32, last_failure: u64) {
+        fs::write(
+            STATE_PATH,
+            format!("failed_attempts = {failed_attempts}\nlast_failure = {last_failure}\n"),
+        )
+        .unwrap();
+    }
+
+    /* Every attempt here is refused, so that no key reaches a running agent */
+    #[test]
+    fn unlock_backs_off_then_wipes() {
+        let _dir = TempDir::enter("throttle");
+        fs::write(
+            crate::config::CONFIG_PATH,
+            "max_unlock_attempts = 5\nlockout_action = wipe\n",
+        )
+        .unwrap();
+
+        let vault = Vault::new(pwds::DB_PATH, adjust_password_length("right key")).unwrap();
+        let entry = Entry {
+            username: "alice".to_string(),
+            password: "secret".to_string(),
+            ..Default::default()
+        };
+        vault.add(&entry).unwrap();
+
+        assert!(matches!(unlock("wrong key"), Err(Refusal::WrongKey(0..=1))));
+        assert_eq!(Throttle::load().failed_attempts, 1);
+
+        /* Even the right key waits out the back-off */
+        write_state(3, now());
+        assert!(matches!(unlock("right key"), Err(Refusal::TooSoon(3..=4))));
+        assert_eq!(Throttle::load().failed_attempts, 3);
+
+        /* The failure that reaches the limit wipes the database */
+        write_state(4, 0);
+        assert!(matches!(unlock("wrong key"), Err(Refusal::Wiped)));
+        assert!(!vault.exists());
+
+        assert!(matches!(unlock("right key"), Err(Refusal::LockedOut)));
+    }
+}
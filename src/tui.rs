@@ -0,0 +1,714 @@
+use std::io::{self, Write};
+use std::mem;
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::agent;
+use crate::config::Config;
+use crate::crypto::gen_pwd;
+use crate::pwds::{parse_tags, Entry};
+use crate::search;
+use crate::session::Session;
+
+/// How long to wait for a key before checking the clipboard and autolock timers.
+const TICK: Duration = Duration::from_millis(250);
+
+/// Rows moved by Ctrl-d and Ctrl-u.
+const HALF_PAGE: usize = 10;
+
+const FIELDS: [&str; 7] = [
+    "Title", "Username", "Password", "URL", "Tags", "Notes", "OTP URI",
+];
+const PASSWORD_FIELD: usize = 2;
+const OTP_FIELD: usize = 6;
+
+const HELP: &str = "\
+j/k, Up/Down    move
+g/G             first / last entry
+Ctrl-d/Ctrl-u   half a page down / up
+/               search, Esc clears it
+Enter, v        show / hide the password
+y               copy the password
+Y               copy the username
+a               add an entry
+e               edit the entry
+d               delete the entry
+p               copy a generated password
+r               reload the database
+q, Ctrl-c       quit
+
+In the editor: Tab/Shift-Tab move between fields, Ctrl-g generates
+a password, Ctrl-r shows it and the OTP URI, Ctrl-u clears the field, Enter saves.";
+
+enum Mode {
+    Normal,
+    Search,
+    Form(Form),
+    /// Asks before deleting the entry.
+    Delete(Entry),
+    /// The form duplicates `existing`, see `Entry::is_duplicate_of`.
+    Duplicate { form: Form, existing: Entry },
+    Help,
+}
+
+/// Add and edit dialog, one value per `FIELDS`.
+struct Form {
+    /// Id of the edited entry, empty for a new one.
+    id: String,
+    values: [String; 7],
+    focus: usize,
+    show_password: bool,
+}
+
+impl Form {
+    fn new() -> Self {
+        Self {
+            id: String::new(),
+            values: Default::default(),
+            focus: 0,
+            show_password: false,
+        }
+    }
+
+    fn edit(entry: &Entry) -> Self {
+        Self {
+            id: entry.id.clone(),
+            values: [
+                entry.title.clone(),
+                entry.username.clone(),
+                entry.password.clone(),
+                entry.url.clone(),
+                entry.tags.join(", "),
+                entry.notes.clone(),
+                entry.otp.clone(),
+            ],
+            focus: 0,
+            show_password: false,
+        }
+    }
+
+    fn to_entry(&self) -> Entry {
+        let [title, username, password, url, tags, notes, otp] = &self.values;
+
+        Entry {
+            id: self.id.clone(),
+            title: title.trim().to_string(),
+            username: username.trim().to_string(),
+            password: password.clone(),
+            url: url.trim().to_string(),
+            tags: parse_tags(tags),
+            notes: notes.clone(),
+            otp: otp.trim().to_string(),
+        }
+    }
+
+    /// The password and the URI holding the one-time password secret are
+    /// masked until Ctrl-r.
+    fn is_masked(&self, field: usize) -> bool {
+        (field == PASSWORD_FIELD || field == OTP_FIELD) && !self.show_password
+    }
+}
+
+/// Full-screen terminal interface, offering what the manager and
+/// passwords pages of the GUI offer.
+struct App {
+    session: Session,
+    config: Config,
+    /// Stored entries, without their passwords.
+    entries: Vec<Entry>,
+    /// Indices in `entries` of the entries matching `query`.
+    visible: Vec<usize>,
+    query: String,
+    table: TableState,
+    mode: Mode,
+    /// Id and password of the entry whose password is shown.
+    revealed: Option<(String, String)>,
+    status: String,
+    clear_clipboard_at: Option<Instant>,
+    last_activity: Instant,
+    locked: bool,
+    quit: bool,
+}
+
+/// Runs the terminal interface until the user quits or the vault gets locked.
+pub fn run(session: Session) -> io::Result<()> {
+    let mut app = App {
+        session,
+        config: Config::load(),
+        entries: Vec::new(),
+        visible: Vec::new(),
+        query: String::new(),
+        table: TableState::default(),
+        mode: Mode::Normal,
+        revealed: None,
+        status: "Press ? for help.".to_string(),
+        clear_clipboard_at: None,
+        last_activity: Instant::now(),
+        locked: false,
+        quit: false,
+    };
+    app.reload();
+
+    let mut terminal = ratatui::init();
+    let result = app.main_loop(&mut terminal);
+    ratatui::restore();
+
+    if app.clear_clipboard_at.is_some() {
+        copy_to_clipboard("")?;
+    }
+
+    if app.locked {
+        let _ = agent::lock();
+        println!("[+] Locked after {} minutes without activity.", app.config.autolock_minutes);
+    }
+
+    result
+}
+
+impl App {
+    fn main_loop(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if event::poll(TICK)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        self.last_activity = Instant::now();
+                        self.handle_key(key);
+                    }
+                }
+            }
+
+            self.tick()?;
+        }
+
+        Ok(())
+    }
+
+    /// Clears the clipboard and locks the vault once their timers run out.
+    fn tick(&mut self) -> io::Result<()> {
+        if self
+            .clear_clipboard_at
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            copy_to_clipboard("")?;
+            self.clear_clipboard_at = None;
+            self.status = "Clipboard cleared.".to_string();
+        }
+
+        let autolock = Duration::from_secs(u64::from(self.config.autolock_minutes) * 60);
+        if !autolock.is_zero() && self.last_activity.elapsed() >= autolock {
+            self.locked = true;
+            self.quit = true;
+        }
+
+        Ok(())
+    }
+
+    fn reload(&mut self) {
+        match self.session.entry_list() {
+            Ok(entries) => self.entries = entries,
+            Err(e) => self.status = format!("Error while reading database: {e}"),
+        }
+
+        self.filter();
+    }
+
+    /// Recomputes the visible entries after the list or the query changed.
+    fn filter(&mut self) {
+        let selected_id = self.selected().map(|entry| entry.id.clone());
+
+        self.visible = (0..self.entries.len())
+            .filter(|&index| search::matches(&self.query, &self.entries[index]))
+            .collect();
+
+        let position = selected_id
+            .and_then(|id| {
+                self.visible
+                    .iter()
+                    .position(|&index| self.entries[index].id == id)
+            })
+            .or((!self.visible.is_empty()).then_some(0));
+        self.table.select(position);
+    }
+
+    fn selected(&self) -> Option<&Entry> {
+        self.table
+            .selected()
+            .and_then(|row| self.visible.get(row))
+            .map(|&index| &self.entries[index])
+    }
+
+    fn select(&mut self, row: usize) {
+        if !self.visible.is_empty() {
+            self.table.select(Some(row.min(self.visible.len() - 1)));
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        self.mode = match mem::replace(&mut self.mode, Mode::Normal) {
+            Mode::Normal => self.normal_key(key),
+            Mode::Search => self.search_key(key),
+            Mode::Form(form) => self.form_key(form, key),
+            Mode::Delete(entry) => self.delete_key(entry, key),
+            Mode::Duplicate { form, existing } => self.duplicate_key(form, existing, key),
+            Mode::Help => Mode::Normal,
+        };
+    }
+
+    fn normal_key(&mut self, key: KeyEvent) -> Mode {
+        let row = self.table.selected().unwrap_or_default();
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        match key.code {
+            KeyCode::Char('c') if ctrl => self.quit = true,
+            KeyCode::Char('d') if ctrl => self.select(row + HALF_PAGE),
+            KeyCode::Char('u') if ctrl => self.select(row.saturating_sub(HALF_PAGE)),
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Char('j') | KeyCode::Down => self.select(row + 1),
+            KeyCode::Char('k') | KeyCode::Up => self.select(row.saturating_sub(1)),
+            KeyCode::Char('g') | KeyCode::Home => self.select(0),
+            KeyCode::Char('G') | KeyCode::End => self.select(usize::MAX),
+            KeyCode::Char('/') => return Mode::Search,
+            KeyCode::Esc => {
+                self.query.clear();
+                self.filter();
+            }
+            KeyCode::Enter | KeyCode::Char('v') => self.toggle_password(),
+            KeyCode::Char('y') => self.copy_password(),
+            KeyCode::Char('Y') => {
+                if let Some(username) = self.selected().map(|entry| entry.username.clone()) {
+                    self.copy("Username", &username);
+                }
+            }
+            KeyCode::Char('a') => return Mode::Form(Form::new()),
+            KeyCode::Char('e') => {
+                if let Some(form) = self.edit_form() {
+                    return Mode::Form(form);
+                }
+            }
+            KeyCode::Char('d') => {
+                if let Some(entry) = self.selected() {
+                    return Mode::Delete(entry.clone());
+                }
+            }
+            KeyCode::Char('p') => match gen_pwd() {
+                Ok(password) => self.copy("Generated password", &password),
+                Err(e) => self.status = e.to_string(),
+            },
+            KeyCode::Char('r') => {
+                self.reload();
+                self.status = "Database reloaded.".to_string();
+            }
+            KeyCode::Char('?') => return Mode::Help,
+            _ => {}
+        }
+
+        Mode::Normal
+    }
+
+    fn search_key(&mut self, key: KeyEvent) -> Mode {
+        let row = self.table.selected().unwrap_or_default();
+
+        match key.code {
+            KeyCode::Enter => return Mode::Normal,
+            KeyCode::Esc => {
+                self.query.clear();
+                self.filter();
+                return Mode::Normal;
+            }
+            KeyCode::Down => self.select(row + 1),
+            KeyCode::Up => self.select(row.saturating_sub(1)),
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.filter();
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.filter();
+            }
+            _ => {}
+        }
+
+        Mode::Search
+    }
+
+    fn form_key(&mut self, mut form: Form, key: KeyEvent) -> Mode {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        match key.code {
+            KeyCode::Esc => return Mode::Normal,
+            KeyCode::Enter => return self.save(form),
+            KeyCode::Tab | KeyCode::Down => form.focus = (form.focus + 1) % FIELDS.len(),
+            KeyCode::BackTab | KeyCode::Up => {
+                form.focus = (form.focus + FIELDS.len() - 1) % FIELDS.len();
+            }
+            KeyCode::Char('g') if ctrl => match gen_pwd() {
+                Ok(password) => form.values[PASSWORD_FIELD] = password,
+                Err(e) => self.status = e.to_string(),
+            },
+            KeyCode::Char('r') if ctrl => form.show_password = !form.show_password,
+            KeyCode::Char('u') if ctrl => form.values[form.focus].clear(),
+            KeyCode::Backspace => {
+                form.values[form.focus].pop();
+            }
+            KeyCode::Char(c) if !ctrl => form.values[form.focus].push(c),
+            _ => {}
+        }
+
+        Mode::Form(form)
+    }
+
+    fn delete_key(&mut self, entry: Entry, key: KeyEvent) -> Mode {
+        if key.code != KeyCode::Char('y') {
+            self.status = "Nothing deleted.".to_string();
+            return Mode::Normal;
+        }
+
+        match self.session.remove(&entry.id) {
+            Ok(()) => self.status = format!("Deleted {}.", entry.username),
+            Err(e) => self.status = format!("Error while deleting the entry: {e}"),
+        }
+        self.revealed = None;
+        self.reload();
+
+        Mode::Normal
+    }
+
+    fn duplicate_key(&mut self, form: Form, existing: Entry, key: KeyEvent) -> Mode {
+        let entry = form.to_entry();
+
+        let result = match key.code {
+            KeyCode::Char('m') => {
+                let mut merged = existing.clone();
+                merged.merge(&entry);
+                self.session.update(&existing.id, &merged)
+            }
+            KeyCode::Char('o') => self.session.update(&existing.id, &entry),
+            KeyCode::Char('k') => self.session.add(&entry).map(|_| ()),
+            KeyCode::Esc => return Mode::Form(form),
+            _ => return Mode::Duplicate { form, existing },
+        };
+
+        self.finish_save(form, result)
+    }
+
+    /// Opens the editor on the selected entry, password included.
+    fn edit_form(&mut self) -> Option<Form> {
+        let id = self.selected()?.id.clone();
+
+        match self.session.entry(&id) {
+            Ok(Some(entry)) => Some(Form::edit(&entry)),
+            Ok(None) => {
+                self.status = "The entry no longer exists.".to_string();
+                self.reload();
+                None
+            }
+            Err(e) => {
+                self.status = format!("Error while reading database: {e}");
+                None
+            }
+        }
+    }
+
+    fn save(&mut self, form: Form) -> Mode {
+        let entry = form.to_entry();
+
+        if let Err(message) = entry.validate() {
+            self.status = message;
+            return Mode::Form(form);
+        }
+
+        if !form.id.is_empty() {
+            let result = self.session.update(&form.id, &entry);
+            return self.finish_save(form, result);
+        }
+
+        match self.session.find_duplicate(&entry) {
+            Ok(Some(existing)) => Mode::Duplicate { form, existing },
+            Ok(None) => {
+                let result = self.session.add(&entry).map(|_| ());
+                self.finish_save(form, result)
+            }
+            Err(e) => {
+                self.status = format!("Error while reading database: {e}");
+                Mode::Form(form)
+            }
+        }
+    }
+
+    fn finish_save(&mut self, form: Form, result: io::Result<()>) -> Mode {
+        match result {
+            Ok(()) => {
+                self.status = "Entry saved.".to_string();
+                self.revealed = None;
+                self.reload();
+                Mode::Normal
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                self.status = "Another entry already uses this username and URL.".to_string();
+                Mode::Form(form)
+            }
+            Err(e) => {
+                self.status = format!("Error while saving the entry: {e}");
+                Mode::Form(form)
+            }
+        }
+    }
+
+    fn toggle_password(&mut self) {
+        let Some(id) = self.selected().map(|entry| entry.id.clone()) else {
+            return;
+        };
+
+        if self.revealed.as_ref().is_some_and(|(shown, _)| *shown == id) {
+            self.revealed = None;
+            return;
+        }
+
+        match self.session.password(&id) {
+            Ok(Some(password)) => self.revealed = Some((id, password)),
+            Ok(None) => self.status = "The entry no longer exists.".to_string(),
+            Err(e) => self.status = format!("Error while decrypting the password: {e}"),
+        }
+    }
+
+    fn copy_password(&mut self) {
+        let Some(id) = self.selected().map(|entry| entry.id.clone()) else {
+            return;
+        };
+
+        match self.session.password(&id) {
+            Ok(Some(password)) => self.copy("Password", &password),
+            Ok(None) => self.status = "The entry no longer exists.".to_string(),
+            Err(e) => self.status = format!("Error while decrypting the password: {e}"),
+        }
+    }
+
+    /// Copies to the clipboard, cleared after `clipboard_timeout` seconds.
+    fn copy(&mut self, what: &str, text: &str) {
+        if let Err(e) = copy_to_clipboard(text) {
+            self.status = format!("Cannot copy to the clipboard: {e}");
+            return;
+        }
+
+        let timeout = self.config.clipboard_timeout;
+        if timeout == 0 {
+            self.status = format!("{what} copied.");
+            self.clear_clipboard_at = None;
+        } else {
+            self.status = format!("{what} copied, clearing in {timeout}s.");
+            self.clear_clipboard_at =
+                Some(Instant::now() + Duration::from_secs(u64::from(timeout)));
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [search_area, list_area, details_area, status_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(3),
+            Constraint::Length(8),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        self.draw_search(frame, search_area);
+        self.draw_list(frame, list_area);
+        self.draw_details(frame, details_area);
+        frame.render_widget(Paragraph::new(self.status.as_str()).dim(), status_area);
+
+        match &self.mode {
+            Mode::Form(form) => draw_form(frame, form),
+            Mode::Delete(entry) => draw_dialog(
+                frame,
+                "Delete",
+                &format!("Delete {} {}? [y/N]", entry.username, entry.url),
+            ),
+            Mode::Duplicate { existing, .. } => draw_dialog(
+                frame,
+                "Duplicate",
+                &format!(
+                    "An entry for {} at {} already exists.\n\n\
+                     [m]erge, [o]verwrite, [k]eep both, Esc to go back",
+                    existing.username, existing.url
+                ),
+            ),
+            Mode::Help => draw_dialog(frame, "Keys", HELP),
+            Mode::Normal | Mode::Search => {}
+        }
+    }
+
+    fn draw_search(&self, frame: &mut Frame, area: Rect) {
+        let searching = matches!(self.mode, Mode::Search);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" Search ")
+            .border_style(if searching {
+                Style::new().bold()
+            } else {
+                Style::new()
+            });
+        frame.render_widget(Paragraph::new(self.query.as_str()).block(block), area);
+
+        if searching {
+            let width = self.query.chars().count() as u16;
+            frame.set_cursor_position((area.x + 1 + width, area.y + 1));
+        }
+    }
+
+    fn draw_list(&mut self, frame: &mut Frame, area: Rect) {
+        let title = if self.entries.is_empty() {
+            " No passwords stored. ".to_string()
+        } else if self.visible.is_empty() {
+            " No matching passwords. ".to_string()
+        } else {
+            format!(" {} of {} entries ", self.visible.len(), self.entries.len())
+        };
+
+        let rows: Vec<Row> = self
+            .visible
+            .iter()
+            .map(|&index| {
+                let entry = &self.entries[index];
+                Row::new([
+                    entry.title.clone(),
+                    entry.username.clone(),
+                    entry.url.clone(),
+                    entry.tags.join(", "),
+                ])
+            })
+            .collect();
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(25),
+                Constraint::Percentage(25),
+                Constraint::Percentage(30),
+                Constraint::Percentage(20),
+            ],
+        )
+        .header(Row::new(["Title", "Username", "URL", "Tags"]).bold())
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+
+        frame.render_stateful_widget(table, area, &mut self.table);
+    }
+
+    fn draw_details(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::default().borders(Borders::ALL).title(" Entry ");
+
+        let Some(entry) = self.selected() else {
+            frame.render_widget(block, area);
+            return;
+        };
+
+        let password = match &self.revealed {
+            Some((id, password)) if *id == entry.id => password.as_str(),
+            _ => "******** (Enter to show)",
+        };
+
+        let lines = vec![
+            Line::from(format!("Title:    {}", entry.title)),
+            Line::from(format!("Username: {}", entry.username)),
+            Line::from(format!("Password: {password}")),
+            Line::from(format!("URL:      {}", entry.url)),
+            Line::from(format!("Tags:     {}", entry.tags.join(", "))),
+            Line::from(format!("Notes:    {}", entry.notes.replace('\n', " / "))),
+        ];
+
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
+
+fn draw_form(frame: &mut Frame, form: &Form) {
+    let title = if form.id.is_empty() {
+        " Add an entry "
+    } else {
+        " Edit the entry "
+    };
+    let area = centered(frame.area(), 70, FIELDS.len() as u16 + 4);
+
+    let mut lines: Vec<Line> = FIELDS
+        .iter()
+        .zip(&form.values)
+        .enumerate()
+        .map(|(index, (name, value))| {
+            let value = if form.is_masked(index) {
+                "*".repeat(value.chars().count())
+            } else {
+                value.replace('\n', " / ")
+            };
+
+            let line = Line::from(format!("{name:<9} {value}"));
+            if index == form.focus {
+                line.bold()
+            } else {
+                line
+            }
+        })
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from("Enter to save, Esc to cancel, Ctrl-g to generate a password").dim());
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)),
+        area,
+    );
+
+    let value = &form.values[form.focus];
+    let width = if form.is_masked(form.focus) {
+        value.chars().count()
+    } else {
+        value.replace('\n', " / ").chars().count()
+    };
+    frame.set_cursor_position((
+        area.x + 1 + 10 + width as u16,
+        area.y + 1 + form.focus as u16,
+    ));
+}
+
+fn draw_dialog(frame: &mut Frame, title: &str, text: &str) {
+    let height = text.lines().count() as u16 + 2;
+    let area = centered(frame.area(), 72, height);
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(text)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title(format!(" {title} "))),
+        area,
+    );
+}
+
+/// Returns a rectangle of at most `width` by `height` in the middle of `area`.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Sets the clipboard through the OSC 52 escape sequence, which the
+/// terminal handles, so that copying works over SSH too.
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{encoded}\x07")?;
+    stdout.flush()
+}
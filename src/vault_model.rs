@@ -0,0 +1,162 @@
+use std::io;
+use std::rc::Rc;
+
+use gtk::gio;
+use gtk::prelude::*;
+
+use crate::entry_object::EntryObject;
+use crate::import::Import;
+use crate::pwds::Entry;
+use crate::session::Session;
+use crate::vault;
+
+/// Why the store could not be reloaded, see `VaultModel::new`.
+pub enum ReloadError {
+    /// The key no longer decrypts the database, see `Session::verify_key`.
+    KeyRejected,
+    Failed(io::Error),
+}
+
+/// Decrypted listing of the database shared by every page.
+///
+/// Pages bind to `store()` and follow its `items-changed` signal. The store is
+/// reloaded after every change made through this model and whenever the
+/// database file is modified on disk, e.g. by another process.
+pub struct VaultModel {
+    session: Session,
+    store: gio::ListStore,
+    monitor: Option<gio::FileMonitor>,
+    on_reload_error: Box<dyn Fn(ReloadError)>,
+}
+
+impl VaultModel {
+    /// Lists the entries of `session`. `on_reload_error` is called whenever
+    /// they cannot be read again, which the listing then keeps showing.
+    pub fn new(session: Session, on_reload_error: impl Fn(ReloadError) + 'static) -> Rc<Self> {
+        let monitor = gio::File::for_path(session.path())
+            .monitor_file(gio::FileMonitorFlags::NONE, None::<&gio::Cancellable>)
+            .map_err(|e| eprintln!("[-] Cannot watch the database file: {e}"))
+            .ok();
+
+        let vault = Rc::new(Self {
+            session,
+            store: gio::ListStore::new::<EntryObject>(),
+            monitor,
+            on_reload_error: Box::new(on_reload_error),
+        });
+
+        if let Some(monitor) = &vault.monitor {
+            let vault_weak = Rc::downgrade(&vault);
+
+            monitor.connect_changed(move |_, _, _, event| {
+                if !matches!(
+                    event,
+                    gio::FileMonitorEvent::ChangesDoneHint
+                        | gio::FileMonitorEvent::Created
+                        | gio::FileMonitorEvent::Deleted
+                ) {
+                    return;
+                }
+
+                if let Some(vault) = vault_weak.upgrade() {
+                    vault.reload();
+                }
+            });
+        }
+
+        vault.reload();
+        vault
+    }
+
+    /// List of `EntryObject`s, kept in sync with the database.
+    pub fn store(&self) -> &gio::ListStore {
+        &self.store
+    }
+
+    /// Replaces the content of the store with the entries on disk.
+    pub fn reload(&self) {
+        match self.session.entry_list() {
+            Ok(entries) => {
+                let otp_ids = vault::otp_ids(self.session.path()).unwrap_or_default();
+                let items: Vec<EntryObject> = entries
+                    .iter()
+                    .map(|entry| {
+                        let item = EntryObject::new(entry);
+                        item.set_has_otp(otp_ids.contains(&entry.id));
+                        item
+                    })
+                    .collect();
+                self.store.splice(0, self.store.n_items(), &items);
+            }
+            Err(e) => {
+                let error = match self.session.verify_key() {
+                    Ok(false) => ReloadError::KeyRejected,
+                    _ => ReloadError::Failed(e),
+                };
+                (self.on_reload_error)(error);
+            }
+        }
+    }
+
+    /// Saves a new entry and returns its id.
+    pub fn add(&self, entry: &Entry) -> io::Result<String> {
+        let id = self.session.add(entry)?;
+        self.reload();
+        Ok(id)
+    }
+
+    pub fn modify_password(&self, id: &str, new_password: &str) -> io::Result<()> {
+        self.session.set_password(id, new_password)?;
+        self.reload();
+        Ok(())
+    }
+
+    pub fn update(&self, id: &str, entry: &Entry) -> io::Result<()> {
+        self.session.update(id, entry)?;
+        self.reload();
+        Ok(())
+    }
+
+    pub fn remove(&self, id: &str) -> io::Result<()> {
+        self.session.remove(id)?;
+        self.reload();
+        Ok(())
+    }
+
+    /// Writes the changes of an import, see `Import::apply`.
+    pub fn import(&self, import: &Import) -> io::Result<()> {
+        self.session.save_all(&import.updated, &import.added)?;
+        self.reload();
+        Ok(())
+    }
+
+    pub fn find_duplicate(&self, entry: &Entry) -> io::Result<Option<Entry>> {
+        self.session.find_duplicate(entry)
+    }
+
+    pub fn find_by_username(&self, username: &str) -> io::Result<Vec<Entry>> {
+        self.session.find_by_username(username)
+    }
+
+    pub fn entries(&self) -> io::Result<Vec<Entry>> {
+        self.session.entries()
+    }
+
+    pub fn get_entry(&self, id: &str) -> io::Result<Option<Entry>> {
+        self.session.entry(id)
+    }
+
+    pub fn get_password(&self, id: &str) -> io::Result<Option<String>> {
+        self.session.password(id)
+    }
+
+    pub fn get_otp(&self, id: &str) -> io::Result<Option<String>> {
+        self.session.otp(id)
+    }
+
+    /// Stores a new `otpauth://` URI without reloading the store, as when
+    /// the counter of an HOTP generator moves on.
+    pub fn set_otp(&self, id: &str, uri: &str) -> io::Result<()> {
+        self.session.set_otp(id, uri)
+    }
+}